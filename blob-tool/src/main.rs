@@ -6,7 +6,7 @@ use celestia_types::blob::Commitment;
 use celestia_types::nmt::Namespace;
 use celestia_types::ShareProof;
 use clap::{command, Parser};
-use eq_common::ZKStackEqProofInput;
+use eq_common::{fixtures, ZKStackEqProofInput};
 use sha3::{Digest, Keccak256};
 
 #[derive(Parser, Debug)]
@@ -20,6 +20,9 @@ struct Args {
     namespace: String,
     #[arg(long)]
     commitment: String,
+    /// Also save the node responses used here as fixtures for eq-service's fixture DA backend
+    #[arg(long)]
+    fixture_dir: Option<std::path::PathBuf>,
 }
 
 #[tokio::main]
@@ -117,4 +120,25 @@ async fn main() {
     std::fs::write("proof_input.json", json).expect("Failed writing proof input to file");
 
     println!("Wrote proof input to proof_input.json");
+
+    if let Some(dir) = args.fixture_dir {
+        std::fs::create_dir_all(&dir).expect("Failed creating fixture dir");
+        let write_fixture = |name: String, json: String| {
+            std::fs::write(dir.join(&name), json).expect("Failed writing fixture to file");
+            println!("Wrote fixture {}", dir.join(&name).display());
+        };
+        write_fixture(
+            fixtures::header_file_name(args.height),
+            serde_json::to_string_pretty(&header).expect("Failed serializing header to JSON"),
+        );
+        write_fixture(
+            fixtures::blob_file_name(args.height, commitment.hash()),
+            serde_json::to_string_pretty(&blob).expect("Failed serializing blob to JSON"),
+        );
+        write_fixture(
+            fixtures::range_file_name(args.height, ods_index, ods_index + blob.shares_len() as u64),
+            serde_json::to_string_pretty(&range_response.proof)
+                .expect("Failed serializing share range proof to JSON"),
+        );
+    }
 }
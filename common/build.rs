@@ -9,6 +9,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .build_server(true)
             .build_client(true)
             .out_dir(code_gen_path)
+            .file_descriptor_set_path(code_gen_path.join("eqservice_descriptor.bin"))
             .compile(&["proto/eqservice.proto"], &["proto/"])?;
         Ok(())
     }
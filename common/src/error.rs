@@ -35,6 +35,20 @@ pub enum InclusionServiceError {
 
     #[error("Failed to deserialize KeccakInclusionToDataRootProofOutput")]
     OutputDeserializationError,
+
+    #[error("Job queue is full, try again later")]
+    QueueFull,
+
+    // Added last to keep the encoding of existing errors unchanged
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    /// The job's tenant is over a proof quota, it is retried once the quota resets
+    #[error("Quota exceeded: {message}")]
+    QuotaExceeded {
+        message: String,
+        retry_at_unix_ms: u64,
+    },
 }
 
 impl EncodeLabelValue for InclusionServiceError {
@@ -51,6 +65,9 @@ impl EncodeLabelValue for InclusionServiceError {
             DaClientError(e) => format!("DaClientError({})", e),
             InvalidParameter(e) => format!("InvalidParameter({})", e),
             OutputDeserializationError => "OutputDeserializationError".to_string(),
+            QueueFull => "QueueFull".to_string(),
+            Cancelled(e) => format!("Cancelled({})", e),
+            QuotaExceeded { .. } => "QuotaExceeded".to_string(),
         };
         encoder.write_str(name.as_str())?;
         Ok(())
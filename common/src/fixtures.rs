@@ -0,0 +1,104 @@
+//! File layout for DA fixtures: Celestia node responses saved to disk,
+//! written by `blob-tool` and served by the `eq-service` fixture DA backend.
+//!
+//! All files are JSON, in a single flat directory:
+//! - `header-<height>.json` = `ExtendedHeader`
+//! - `blob-<height>-<commitment hex>.json` = `Blob`
+//! - `range-<height>-<start>-<end>.json` = `ShareProof` for ODS share indexes `[start, end)`
+
+/// File name for a header at `height`
+pub fn header_file_name(height: u64) -> String {
+    format!("header-{height}.json")
+}
+
+/// File name for a blob with `commitment` at `height`
+pub fn blob_file_name(height: u64, commitment: &[u8; 32]) -> String {
+    format!("blob-{height}-{}.json", hex_encode(commitment))
+}
+
+/// File name for a share range proof at `height`, for ODS share indexes `[start, end)`
+pub fn range_file_name(height: u64, start: u64, end: u64) -> String {
+    format!("range-{height}-{start}-{end}.json")
+}
+
+/// A fixture file, parsed back from it's name
+#[derive(Debug, PartialEq, Eq)]
+pub enum FixtureFile {
+    Header { height: u64 },
+    Blob { height: u64, commitment: [u8; 32] },
+    Range { height: u64, start: u64, end: u64 },
+}
+
+impl FixtureFile {
+    /// Parse a file name from [header_file_name], [blob_file_name], or [range_file_name].
+    /// Returns `None` for anything else.
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let stem = name.strip_suffix(".json")?;
+        let mut parts = stem.split('-');
+        let kind = parts.next()?;
+        let height = parts.next()?.parse().ok()?;
+        let fixture = match kind {
+            "header" => FixtureFile::Header { height },
+            "blob" => FixtureFile::Blob {
+                height,
+                commitment: hex_decode_32(parts.next()?)?,
+            },
+            "range" => FixtureFile::Range {
+                height,
+                start: parts.next()?.parse().ok()?,
+                end: parts.next()?.parse().ok()?,
+            },
+            _ => return None,
+        };
+        match parts.next() {
+            None => Some(fixture),
+            Some(_) => None,
+        }
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hex_decode_32(s: &str) -> Option<[u8; 32]> {
+    if s.len() != 64 {
+        return None;
+    }
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(s.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fixture_file_names() {
+        let commitment = [0xab; 32];
+        assert_eq!(
+            FixtureFile::from_file_name(&header_file_name(42)),
+            Some(FixtureFile::Header { height: 42 })
+        );
+        assert_eq!(
+            FixtureFile::from_file_name(&blob_file_name(42, &commitment)),
+            Some(FixtureFile::Blob {
+                height: 42,
+                commitment
+            })
+        );
+        assert_eq!(
+            FixtureFile::from_file_name(&range_file_name(42, 3, 9)),
+            Some(FixtureFile::Range {
+                height: 42,
+                start: 3,
+                end: 9
+            })
+        );
+        assert_eq!(FixtureFile::from_file_name("proof_input.json"), None);
+        assert_eq!(FixtureFile::from_file_name("range-1-2-3-4.json"), None);
+    }
+}
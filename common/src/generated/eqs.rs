@@ -20,6 +20,63 @@ pub struct GetZkStackRequest {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchGetZkStackRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub requests: ::prost::alloc::vec::Vec<GetZkStackRequest>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AggregateZkStackRequest {
+    /// ZKStack chain id
+    #[prost(uint64, tag = "1")]
+    pub chain_id: u64,
+    /// ZKStack batch number
+    #[prost(uint32, tag = "2")]
+    pub batch_number: u32,
+    /// Blobs to aggregate, each with the same chain_id and batch_number
+    #[prost(message, repeated, tag = "3")]
+    pub members: ::prost::alloc::vec::Vec<GetZkStackRequest>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetZkStackMultiBlobRequest {
+    /// ZKStack chain id
+    #[prost(uint64, tag = "1")]
+    pub chain_id: u64,
+    /// ZKStack batch number
+    #[prost(uint32, tag = "2")]
+    pub batch_number: u32,
+    /// Blobs in the order their data is concatenated, each with the same chain_id and batch_number
+    #[prost(message, repeated, tag = "3")]
+    pub blobs: ::prost::alloc::vec::Vec<GetZkStackRequest>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetProofRequest {
+    /// Integration to prove the blob for
+    #[prost(enumeration = "ProofType", tag = "1")]
+    pub proof_type: i32,
+    /// Data Availability (DA) block height
+    #[prost(uint64, tag = "2")]
+    pub height: u64,
+    /// 32 byte DA namespace
+    #[prost(bytes = "vec", tag = "3")]
+    pub namespace: ::prost::alloc::vec::Vec<u8>,
+    /// 32 byte DA blob commitment
+    #[prost(bytes = "vec", tag = "4")]
+    pub commitment: ::prost::alloc::vec::Vec<u8>,
+    /// ZKSTACK only: batch number
+    #[prost(uint32, tag = "5")]
+    pub batch_number: u32,
+    /// ZKSTACK only: chain id
+    #[prost(uint64, tag = "6")]
+    pub chain_id: u64,
+    /// PAYY only: L2 block number
+    #[prost(uint64, tag = "7")]
+    pub l2_block_number: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ProofWithPublicValues {
     /// The actual proof data
     #[prost(bytes = "vec", tag = "1")]
@@ -30,9 +87,31 @@ pub struct ProofWithPublicValues {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProofChunk {
+    /// The next part of the proof data
+    #[prost(bytes = "vec", tag = "1")]
+    pub proof_data: ::prost::alloc::vec::Vec<u8>,
+    /// First chunk only: the public values used to generate the proof
+    #[prost(bytes = "vec", tag = "2")]
+    pub public_values: ::prost::alloc::vec::Vec<u8>,
+    /// First chunk only: SHA3 hash of the zkVM program ELF that proved the request
+    #[prost(bytes = "vec", tag = "3")]
+    pub program_id: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetZkStackResponse {
     #[prost(enumeration = "get_zk_stack_response::Status", tag = "1")]
     pub status: i32,
+    /// Automatic retries of this request attempted so far
+    #[prost(uint32, tag = "6")]
+    pub retry_attempts: u32,
+    /// When RETRYABLE_FAILURE, unix time (ms) of the next automatic retry
+    #[prost(uint64, tag = "7")]
+    pub next_retry_unix_ms: u64,
+    /// When ZKP_PENDING or ZKP_FINISHED, SHA3 hash of the zkVM program ELF proving the request
+    #[prost(bytes = "vec", tag = "8")]
+    pub program_id: ::prost::alloc::vec::Vec<u8>,
     #[prost(oneof = "get_zk_stack_response::ResponseValue", tags = "2, 3, 4, 5")]
     pub response_value: ::core::option::Option<get_zk_stack_response::ResponseValue>,
 }
@@ -109,6 +188,164 @@ pub mod get_zk_stack_response {
         StatusMessage(::prost::alloc::string::String),
     }
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchGetZkStackResponse {
+    /// In the same order as the requests
+    #[prost(message, repeated, tag = "1")]
+    pub responses: ::prost::alloc::vec::Vec<GetZkStackResponse>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListJobsRequest {
+    /// Only list jobs in these states, all jobs if empty
+    #[prost(enumeration = "get_zk_stack_response::Status", repeated, tag = "1")]
+    pub states: ::prost::alloc::vec::Vec<i32>,
+    /// Most jobs in a response, defaults to 100 (at most 1000)
+    #[prost(uint32, tag = "2")]
+    pub page_size: u32,
+    /// The next_page_token of the previous page, empty for the first page
+    #[prost(bytes = "vec", tag = "3")]
+    pub page_token: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListJobsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub jobs: ::prost::alloc::vec::Vec<JobInfo>,
+    /// Empty on the last page
+    #[prost(bytes = "vec", tag = "2")]
+    pub next_page_token: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobKeyRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub job_key: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobInfo {
+    /// Key of the job in the service's DBs
+    #[prost(bytes = "vec", tag = "1")]
+    pub job_key: ::prost::alloc::vec::Vec<u8>,
+    /// The job's request fields
+    #[prost(string, tag = "2")]
+    pub job: ::prost::alloc::string::String,
+    #[prost(enumeration = "get_zk_stack_response::Status", tag = "3")]
+    pub status: i32,
+    /// When failed, the error followed by each error that caused it
+    #[prost(string, repeated, tag = "4")]
+    pub error_chain: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// When RETRYABLE_FAILURE, the state a retry starts from
+    #[prost(string, tag = "5")]
+    pub retry_from: ::prost::alloc::string::String,
+    /// When ZKP_PENDING, the proof request/job id on the prover network
+    #[prost(bytes = "vec", tag = "6")]
+    pub proof_id: ::prost::alloc::vec::Vec<u8>,
+    /// SHA3 hash of the zkVM program ELF, once a proof is requested
+    #[prost(bytes = "vec", tag = "7")]
+    pub program_id: ::prost::alloc::vec::Vec<u8>,
+    /// Automatic retries of this job attempted so far
+    #[prost(uint32, tag = "8")]
+    pub retry_attempts: u32,
+    /// When RETRYABLE_FAILURE, unix time (ms) of the next automatic retry
+    #[prost(uint64, tag = "9")]
+    pub next_retry_unix_ms: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RetryJobRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub job_key: ::prost::alloc::vec::Vec<u8>,
+    #[prost(enumeration = "retry_job_request::Stage", tag = "2")]
+    pub from_stage: i32,
+}
+/// Nested message and enum types in `RetryJobRequest`.
+pub mod retry_job_request {
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Stage {
+        /// Collect the DA inclusion proof again
+        DataAvailability = 0,
+        /// Request a new ZK proof with the collected DA inclusion proof
+        ZkProof = 1,
+    }
+    impl Stage {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Stage::DataAvailability => "DATA_AVAILABILITY",
+                Stage::ZkProof => "ZK_PROOF",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "DATA_AVAILABILITY" => Some(Self::DataAvailability),
+                "ZK_PROOF" => Some(Self::ZkProof),
+                _ => None,
+            }
+        }
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteJobsRequest {
+    /// Finished jobs to delete
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub job_keys: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+    /// Also delete every finished job in these states
+    #[prost(enumeration = "get_zk_stack_response::Status", repeated, tag = "2")]
+    pub states: ::prost::alloc::vec::Vec<i32>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteJobsResponse {
+    #[prost(uint64, tag = "1")]
+    pub deleted: u64,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ProofType {
+    /// Matter Labs' ZKStack, proven by program-keccak-inclusion
+    Zkstack = 0,
+    /// Payy, proven by program-payy-inclusion
+    Payy = 1,
+}
+impl ProofType {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ProofType::Zkstack => "ZKSTACK",
+            ProofType::Payy => "PAYY",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ZKSTACK" => Some(Self::Zkstack),
+            "PAYY" => Some(Self::Payy),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
 pub mod inclusion_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -205,45 +442,941 @@ pub mod inclusion_client {
             req.extensions_mut().insert(GrpcMethod::new("eqs.Inclusion", "GetZKStack"));
             self.inner.unary(req, path, codec).await
         }
-    }
-}
-/// Generated server implementations.
-pub mod inclusion_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with InclusionServer.
-    #[async_trait]
-    pub trait Inclusion: Send + Sync + 'static {
-        async fn get_zk_stack(
-            &self,
-            request: tonic::Request<super::GetZkStackRequest>,
+        /// Start (if needed) and follow a request, with a response for every status change.
+        /// The stream ends once ZKP_FINISHED or PERMANENT_FAILURE is sent.
+        pub async fn watch_zk_stack(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetZkStackRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::GetZkStackResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/eqs.Inclusion/WatchZKStack",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("eqs.Inclusion", "WatchZKStack"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Many GetZKStack requests in one call, with responses in the same order as the requests.
+        pub async fn batch_get_zk_stack(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BatchGetZkStackRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::BatchGetZkStackResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/eqs.Inclusion/BatchGetZKStack",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("eqs.Inclusion", "BatchGetZKStack"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// One proof for many blobs of a single ZKStack batch, proven once every member's own proof is finished.
+        /// Repeated requests yield status updates, like GetZKStack.
+        pub async fn aggregate_zk_stack(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AggregateZkStackRequest>,
         ) -> std::result::Result<
             tonic::Response<super::GetZkStackResponse>,
             tonic::Status,
-        >;
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/eqs.Inclusion/AggregateZKStack",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("eqs.Inclusion", "AggregateZKStack"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// One proof of a ZKStack batch split across many blobs: the keccak hash of their data concatenated in order,
+        /// with the data root of each blob. Repeated requests yield status updates, like GetZKStack.
+        pub async fn get_zk_stack_multi_blob(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetZkStackMultiBlobRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetZkStackResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/eqs.Inclusion/GetZKStackMultiBlob",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("eqs.Inclusion", "GetZKStackMultiBlob"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Like GetZKStack, for any integration given by the request's proof_type.
+        /// A ZKSTACK request is the same job as the GetZKStack request with the same fields.
+        pub async fn get_proof(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetProofRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetZkStackResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/eqs.Inclusion/GetProof");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("eqs.Inclusion", "GetProof"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// The proof of a ZKP_FINISHED request in chunks, without loading it whole, e.g. for large proofs.
+        /// Fails with FAILED_PRECONDITION if the request is not ZKP_FINISHED, nothing is started.
+        pub async fn stream_proof(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetProofRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::ProofChunk>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/eqs.Inclusion/StreamProof",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("eqs.Inclusion", "StreamProof"));
+            self.inner.server_streaming(req, path, codec).await
+        }
     }
-    #[derive(Debug)]
-    pub struct InclusionServer<T: Inclusion> {
-        inner: _Inner<T>,
-        accept_compression_encodings: EnabledCompressionEncodings,
-        send_compression_encodings: EnabledCompressionEncodings,
-        max_decoding_message_size: Option<usize>,
-        max_encoding_message_size: Option<usize>,
+}
+/// Generated client implementations.
+pub mod admin_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    /// Operator access to the service's jobs, served on it's own socket, separate from Inclusion.
+    /// Jobs are identified by their job_key, as listed by ListJobs.
+    #[derive(Debug, Clone)]
+    pub struct AdminClient<T> {
+        inner: tonic::client::Grpc<T>,
     }
-    struct _Inner<T>(Arc<T>);
-    impl<T: Inclusion> InclusionServer<T> {
+    impl<T> AdminClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
         pub fn new(inner: T) -> Self {
-            Self::from_arc(Arc::new(inner))
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
         }
-        pub fn from_arc(inner: Arc<T>) -> Self {
-            let inner = _Inner(inner);
-            Self {
-                inner,
-                accept_compression_encodings: Default::default(),
-                send_compression_encodings: Default::default(),
-                max_decoding_message_size: None,
-                max_encoding_message_size: None,
-            }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> AdminClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            AdminClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        /// Page through jobs, in order of job_key.
+        pub async fn list_jobs(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListJobsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListJobsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/eqs.Admin/ListJobs");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("eqs.Admin", "ListJobs"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// A job's full status, including the error chain of a failure.
+        pub async fn get_job(
+            &mut self,
+            request: impl tonic::IntoRequest<super::JobKeyRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobInfo>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/eqs.Admin/GetJob");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("eqs.Admin", "GetJob"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Stop an in-flight ZK proof request, making the job a PERMANENT_FAILURE.
+        /// FAILED_PRECONDITION until a worker waits on the request, e.g. just after a restart.
+        /// With the CPU prover, a proof already being generated keeps using the CPU until done.
+        pub async fn cancel_job(
+            &mut self,
+            request: impl tonic::IntoRequest<super::JobKeyRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobInfo>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/eqs.Admin/CancelJob");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("eqs.Admin", "CancelJob"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Send a finished job back to the queue, starting from a chosen stage.
+        pub async fn retry_job(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RetryJobRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobInfo>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/eqs.Admin/RetryJob");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("eqs.Admin", "RetryJob"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Delete finished jobs, so a request for them starts over.
+        pub async fn delete_jobs(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteJobsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DeleteJobsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/eqs.Admin/DeleteJobs");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("eqs.Admin", "DeleteJobs"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod inclusion_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with InclusionServer.
+    #[async_trait]
+    pub trait Inclusion: Send + Sync + 'static {
+        async fn get_zk_stack(
+            &self,
+            request: tonic::Request<super::GetZkStackRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetZkStackResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the WatchZKStack method.
+        type WatchZKStackStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::GetZkStackResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Start (if needed) and follow a request, with a response for every status change.
+        /// The stream ends once ZKP_FINISHED or PERMANENT_FAILURE is sent.
+        async fn watch_zk_stack(
+            &self,
+            request: tonic::Request<super::GetZkStackRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::WatchZKStackStream>,
+            tonic::Status,
+        >;
+        /// Many GetZKStack requests in one call, with responses in the same order as the requests.
+        async fn batch_get_zk_stack(
+            &self,
+            request: tonic::Request<super::BatchGetZkStackRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::BatchGetZkStackResponse>,
+            tonic::Status,
+        >;
+        /// One proof for many blobs of a single ZKStack batch, proven once every member's own proof is finished.
+        /// Repeated requests yield status updates, like GetZKStack.
+        async fn aggregate_zk_stack(
+            &self,
+            request: tonic::Request<super::AggregateZkStackRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetZkStackResponse>,
+            tonic::Status,
+        >;
+        /// One proof of a ZKStack batch split across many blobs: the keccak hash of their data concatenated in order,
+        /// with the data root of each blob. Repeated requests yield status updates, like GetZKStack.
+        async fn get_zk_stack_multi_blob(
+            &self,
+            request: tonic::Request<super::GetZkStackMultiBlobRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetZkStackResponse>,
+            tonic::Status,
+        >;
+        /// Like GetZKStack, for any integration given by the request's proof_type.
+        /// A ZKSTACK request is the same job as the GetZKStack request with the same fields.
+        async fn get_proof(
+            &self,
+            request: tonic::Request<super::GetProofRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetZkStackResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the StreamProof method.
+        type StreamProofStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::ProofChunk, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// The proof of a ZKP_FINISHED request in chunks, without loading it whole, e.g. for large proofs.
+        /// Fails with FAILED_PRECONDITION if the request is not ZKP_FINISHED, nothing is started.
+        async fn stream_proof(
+            &self,
+            request: tonic::Request<super::GetProofRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::StreamProofStream>,
+            tonic::Status,
+        >;
+    }
+    #[derive(Debug)]
+    pub struct InclusionServer<T: Inclusion> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: Inclusion> InclusionServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for InclusionServer<T>
+    where
+        T: Inclusion,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/eqs.Inclusion/GetZKStack" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetZKStackSvc<T: Inclusion>(pub Arc<T>);
+                    impl<
+                        T: Inclusion,
+                    > tonic::server::UnaryService<super::GetZkStackRequest>
+                    for GetZKStackSvc<T> {
+                        type Response = super::GetZkStackResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetZkStackRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Inclusion>::get_zk_stack(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetZKStackSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/eqs.Inclusion/WatchZKStack" => {
+                    #[allow(non_camel_case_types)]
+                    struct WatchZKStackSvc<T: Inclusion>(pub Arc<T>);
+                    impl<
+                        T: Inclusion,
+                    > tonic::server::ServerStreamingService<super::GetZkStackRequest>
+                    for WatchZKStackSvc<T> {
+                        type Response = super::GetZkStackResponse;
+                        type ResponseStream = T::WatchZKStackStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetZkStackRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Inclusion>::watch_zk_stack(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = WatchZKStackSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/eqs.Inclusion/BatchGetZKStack" => {
+                    #[allow(non_camel_case_types)]
+                    struct BatchGetZKStackSvc<T: Inclusion>(pub Arc<T>);
+                    impl<
+                        T: Inclusion,
+                    > tonic::server::UnaryService<super::BatchGetZkStackRequest>
+                    for BatchGetZKStackSvc<T> {
+                        type Response = super::BatchGetZkStackResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BatchGetZkStackRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Inclusion>::batch_get_zk_stack(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = BatchGetZKStackSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/eqs.Inclusion/AggregateZKStack" => {
+                    #[allow(non_camel_case_types)]
+                    struct AggregateZKStackSvc<T: Inclusion>(pub Arc<T>);
+                    impl<
+                        T: Inclusion,
+                    > tonic::server::UnaryService<super::AggregateZkStackRequest>
+                    for AggregateZKStackSvc<T> {
+                        type Response = super::GetZkStackResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AggregateZkStackRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Inclusion>::aggregate_zk_stack(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = AggregateZKStackSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/eqs.Inclusion/GetZKStackMultiBlob" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetZKStackMultiBlobSvc<T: Inclusion>(pub Arc<T>);
+                    impl<
+                        T: Inclusion,
+                    > tonic::server::UnaryService<super::GetZkStackMultiBlobRequest>
+                    for GetZKStackMultiBlobSvc<T> {
+                        type Response = super::GetZkStackResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetZkStackMultiBlobRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Inclusion>::get_zk_stack_multi_blob(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetZKStackMultiBlobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/eqs.Inclusion/GetProof" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetProofSvc<T: Inclusion>(pub Arc<T>);
+                    impl<
+                        T: Inclusion,
+                    > tonic::server::UnaryService<super::GetProofRequest>
+                    for GetProofSvc<T> {
+                        type Response = super::GetZkStackResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetProofRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Inclusion>::get_proof(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetProofSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/eqs.Inclusion/StreamProof" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamProofSvc<T: Inclusion>(pub Arc<T>);
+                    impl<
+                        T: Inclusion,
+                    > tonic::server::ServerStreamingService<super::GetProofRequest>
+                    for StreamProofSvc<T> {
+                        type Response = super::ProofChunk;
+                        type ResponseStream = T::StreamProofStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetProofRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Inclusion>::stream_proof(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = StreamProofSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T: Inclusion> Clone for InclusionServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    impl<T: Inclusion> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(Arc::clone(&self.0))
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: Inclusion> tonic::server::NamedService for InclusionServer<T> {
+        const NAME: &'static str = "eqs.Inclusion";
+    }
+}
+/// Generated server implementations.
+pub mod admin_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with AdminServer.
+    #[async_trait]
+    pub trait Admin: Send + Sync + 'static {
+        /// Page through jobs, in order of job_key.
+        async fn list_jobs(
+            &self,
+            request: tonic::Request<super::ListJobsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListJobsResponse>,
+            tonic::Status,
+        >;
+        /// A job's full status, including the error chain of a failure.
+        async fn get_job(
+            &self,
+            request: tonic::Request<super::JobKeyRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobInfo>, tonic::Status>;
+        /// Stop an in-flight ZK proof request, making the job a PERMANENT_FAILURE.
+        /// FAILED_PRECONDITION until a worker waits on the request, e.g. just after a restart.
+        /// With the CPU prover, a proof already being generated keeps using the CPU until done.
+        async fn cancel_job(
+            &self,
+            request: tonic::Request<super::JobKeyRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobInfo>, tonic::Status>;
+        /// Send a finished job back to the queue, starting from a chosen stage.
+        async fn retry_job(
+            &self,
+            request: tonic::Request<super::RetryJobRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobInfo>, tonic::Status>;
+        /// Delete finished jobs, so a request for them starts over.
+        async fn delete_jobs(
+            &self,
+            request: tonic::Request<super::DeleteJobsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DeleteJobsResponse>,
+            tonic::Status,
+        >;
+    }
+    /// Operator access to the service's jobs, served on it's own socket, separate from Inclusion.
+    /// Jobs are identified by their job_key, as listed by ListJobs.
+    #[derive(Debug)]
+    pub struct AdminServer<T: Admin> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: Admin> AdminServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
         }
         pub fn with_interceptor<F>(
             inner: T,
@@ -283,9 +1416,9 @@ pub mod inclusion_server {
             self
         }
     }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for InclusionServer<T>
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for AdminServer<T>
     where
-        T: Inclusion,
+        T: Admin,
         B: Body + Send + 'static,
         B::Error: Into<StdError> + Send + 'static,
     {
@@ -301,25 +1434,23 @@ pub mod inclusion_server {
         fn call(&mut self, req: http::Request<B>) -> Self::Future {
             let inner = self.inner.clone();
             match req.uri().path() {
-                "/eqs.Inclusion/GetZKStack" => {
+                "/eqs.Admin/ListJobs" => {
                     #[allow(non_camel_case_types)]
-                    struct GetZKStackSvc<T: Inclusion>(pub Arc<T>);
-                    impl<
-                        T: Inclusion,
-                    > tonic::server::UnaryService<super::GetZkStackRequest>
-                    for GetZKStackSvc<T> {
-                        type Response = super::GetZkStackResponse;
+                    struct ListJobsSvc<T: Admin>(pub Arc<T>);
+                    impl<T: Admin> tonic::server::UnaryService<super::ListJobsRequest>
+                    for ListJobsSvc<T> {
+                        type Response = super::ListJobsResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::GetZkStackRequest>,
+                            request: tonic::Request<super::ListJobsRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as Inclusion>::get_zk_stack(&inner, request).await
+                                <T as Admin>::list_jobs(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -331,7 +1462,183 @@ pub mod inclusion_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = GetZKStackSvc(inner);
+                        let method = ListJobsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/eqs.Admin/GetJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetJobSvc<T: Admin>(pub Arc<T>);
+                    impl<T: Admin> tonic::server::UnaryService<super::JobKeyRequest>
+                    for GetJobSvc<T> {
+                        type Response = super::JobInfo;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::JobKeyRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Admin>::get_job(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/eqs.Admin/CancelJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct CancelJobSvc<T: Admin>(pub Arc<T>);
+                    impl<T: Admin> tonic::server::UnaryService<super::JobKeyRequest>
+                    for CancelJobSvc<T> {
+                        type Response = super::JobInfo;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::JobKeyRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Admin>::cancel_job(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CancelJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/eqs.Admin/RetryJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct RetryJobSvc<T: Admin>(pub Arc<T>);
+                    impl<T: Admin> tonic::server::UnaryService<super::RetryJobRequest>
+                    for RetryJobSvc<T> {
+                        type Response = super::JobInfo;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RetryJobRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Admin>::retry_job(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RetryJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/eqs.Admin/DeleteJobs" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeleteJobsSvc<T: Admin>(pub Arc<T>);
+                    impl<T: Admin> tonic::server::UnaryService<super::DeleteJobsRequest>
+                    for DeleteJobsSvc<T> {
+                        type Response = super::DeleteJobsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteJobsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Admin>::delete_jobs(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = DeleteJobsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -362,7 +1669,7 @@ pub mod inclusion_server {
             }
         }
     }
-    impl<T: Inclusion> Clone for InclusionServer<T> {
+    impl<T: Admin> Clone for AdminServer<T> {
         fn clone(&self) -> Self {
             let inner = self.inner.clone();
             Self {
@@ -374,7 +1681,7 @@ pub mod inclusion_server {
             }
         }
     }
-    impl<T: Inclusion> Clone for _Inner<T> {
+    impl<T: Admin> Clone for _Inner<T> {
         fn clone(&self) -> Self {
             Self(Arc::clone(&self.0))
         }
@@ -384,7 +1691,7 @@ pub mod inclusion_server {
             write!(f, "{:?}", self.0)
         }
     }
-    impl<T: Inclusion> tonic::server::NamedService for InclusionServer<T> {
-        const NAME: &'static str = "eqs.Inclusion";
+    impl<T: Admin> tonic::server::NamedService for AdminServer<T> {
+        const NAME: &'static str = "eqs.Admin";
     }
 }
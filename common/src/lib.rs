@@ -4,22 +4,28 @@ use celestia_types::{
     RowProof,
 };
 use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
 
 #[cfg(feature = "host")]
 mod error;
 #[cfg(feature = "host")]
 pub use error::{ErrorLabels, InclusionServiceError};
 
+#[cfg(feature = "host")]
+pub mod fixtures;
+
 #[cfg(feature = "grpc")]
 /// gRPC generated bindings
 pub mod eqs {
     include!("generated/eqs.rs");
+
+    /// Encoded `FileDescriptorSet` of `eqservice.proto`, for gRPC server reflection
+    pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!("generated/eqservice_descriptor.bin");
 }
 
 /*
-    For now, we only support ZKStackEqProofs
-    These are used for Celestia integrations with Matter Labs' ZKStack
-    TODO: Add support for Payy Celestia integration
+    ZKStackEqProofs are used for Celestia integrations with Matter Labs' ZKStack,
+    PayyEqProofs for the Payy Celestia integration.
 */
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ZKStackEqProofInput {
@@ -66,31 +72,266 @@ impl ZKStackEqProofOutput {
 
     #[cfg(feature = "host")]
     pub fn from_bytes(data: &[u8]) -> Result<Self, InclusionServiceError> {
+        Self::from_slice(data).ok_or(InclusionServiceError::OutputDeserializationError)
+    }
+
+    /// Decode bytes from [Self::to_vec], `None` if malformed.
+    /// Usable in zkVM programs, see [Self::from_bytes] for the host.
+    pub fn from_slice(data: &[u8]) -> Option<Self> {
         if data.len() != 76 {
-            return Err(InclusionServiceError::OutputDeserializationError);
+            return None;
         }
-        let decoded = ZKStackEqProofOutput {
-            keccak_hash: data[0..32]
-                .try_into()
-                .map_err(|_| InclusionServiceError::OutputDeserializationError)?,
-            data_root: data[32..64]
-                .try_into()
-                .map_err(|_| InclusionServiceError::OutputDeserializationError)?,
-            batch_number: u32::from_le_bytes(
-                data[64..68]
-                    .try_into()
-                    .map_err(|_| InclusionServiceError::OutputDeserializationError)?,
-            ),
-            chain_id: u64::from_le_bytes(
-                data[68..76]
-                    .try_into()
-                    .map_err(|_| InclusionServiceError::OutputDeserializationError)?,
-            ),
-        };
-        Ok(decoded)
+        Some(ZKStackEqProofOutput {
+            keccak_hash: data[0..32].try_into().ok()?,
+            data_root: data[32..64].try_into().ok()?,
+            batch_number: u32::from_le_bytes(data[64..68].try_into().ok()?),
+            chain_id: u64::from_le_bytes(data[68..76].try_into().ok()?),
+        })
+    }
+}
+
+/// One blob of a [ZKStackMultiBlobEqProofInput], with it's inclusion proof
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BlobInclusionInput {
+    #[serde(rename = "blob_data")]
+    pub data: Vec<u8>,
+
+    #[serde(rename = "blob_namespace")]
+    pub namespace_id: Namespace,
+
+    #[serde(rename = "nmt_multiproofs")]
+    pub share_proofs: Vec<NamespaceProof>,
+
+    #[serde(rename = "row_root_multiproof")]
+    pub row_proof: RowProof,
+
+    #[serde(rename = "blob_author")]
+    pub author: Option<AccAddress>,
+
+    /// Data root of the block this blob is included in
+    pub data_root: [u8; 32],
+}
+
+impl From<ZKStackEqProofInput> for BlobInclusionInput {
+    fn from(input: ZKStackEqProofInput) -> Self {
+        BlobInclusionInput {
+            data: input.data,
+            namespace_id: input.namespace_id,
+            share_proofs: input.share_proofs,
+            row_proof: input.row_proof,
+            author: input.author,
+            data_root: input.data_root,
+        }
+    }
+}
+
+/// Like [ZKStackEqProofInput], for a batch split across many blobs (possibly at different heights)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ZKStackMultiBlobEqProofInput {
+    /// Blobs in the order their data is concatenated
+    pub blobs: Vec<BlobInclusionInput>,
+    /// Keccak hash of the concatenated data of all blobs
+    pub keccak_hash: [u8; 32],
+    // batch_number and chain_id are passed through to prevent proofs from being replayed
+    pub batch_number: u32,
+    pub chain_id: u64,
+}
+
+/// Expecting bytes:
+/// (keccak_hash: [u8; 32], batch_number: u32, chain_id: u64, blob_count: u32, data_roots: [[u8; 32]; blob_count])
+pub struct ZKStackMultiBlobEqProofOutput {
+    /// Keccak hash of the concatenated data of all blobs
+    pub keccak_hash: [u8; 32],
+    pub batch_number: u32,
+    pub chain_id: u64,
+    /// Data root of each blob's block, in the order of the blobs
+    pub data_roots: Vec<[u8; 32]>,
+}
+impl ZKStackMultiBlobEqProofOutput {
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(&self.keccak_hash);
+        encoded.extend_from_slice(&self.batch_number.to_le_bytes());
+        encoded.extend_from_slice(&self.chain_id.to_le_bytes());
+        encoded.extend_from_slice(&(self.data_roots.len() as u32).to_le_bytes());
+        for data_root in &self.data_roots {
+            encoded.extend_from_slice(data_root);
+        }
+        encoded
+    }
+
+    #[cfg(feature = "host")]
+    pub fn from_bytes(data: &[u8]) -> Result<Self, InclusionServiceError> {
+        Self::from_slice(data).ok_or(InclusionServiceError::OutputDeserializationError)
+    }
+
+    /// Decode bytes from [Self::to_vec], `None` if malformed
+    pub fn from_slice(data: &[u8]) -> Option<Self> {
+        if data.len() < 48 {
+            return None;
+        }
+        let blob_count = u32::from_le_bytes(data[44..48].try_into().ok()?) as usize;
+        let roots = &data[48..];
+        if roots.len() != blob_count.checked_mul(32)? {
+            return None;
+        }
+        Some(ZKStackMultiBlobEqProofOutput {
+            keccak_hash: data[0..32].try_into().ok()?,
+            batch_number: u32::from_le_bytes(data[32..36].try_into().ok()?),
+            chain_id: u64::from_le_bytes(data[36..44].try_into().ok()?),
+            data_roots: roots
+                .chunks_exact(32)
+                .map(|root| root.try_into().ok())
+                .collect::<Option<_>>()?,
+        })
     }
 }
 
+/// Input for the Payy Celestia integration: one blob, with it's inclusion proof
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PayyEqProofInput {
+    pub blob: BlobInclusionInput,
+    pub keccak_hash: [u8; 32],
+    // l2_block_number is passed through to prevent proofs from being replayed
+    pub l2_block_number: u64,
+}
+
+/// Expecting bytes:
+/// (keccak_hash: [u8; 32], data_root: [u8; 32], namespace: [u8; 29], l2_block_number: u64)
+pub struct PayyEqProofOutput {
+    pub keccak_hash: [u8; 32],
+    pub data_root: [u8; 32],
+    /// The blob's namespace, so a verifier can check it was posted by the rollup
+    pub namespace: [u8; 29],
+    pub l2_block_number: u64,
+}
+impl PayyEqProofOutput {
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(&self.keccak_hash);
+        encoded.extend_from_slice(&self.data_root);
+        encoded.extend_from_slice(&self.namespace);
+        encoded.extend_from_slice(&self.l2_block_number.to_le_bytes());
+        encoded
+    }
+
+    #[cfg(feature = "host")]
+    pub fn from_bytes(data: &[u8]) -> Result<Self, InclusionServiceError> {
+        Self::from_slice(data).ok_or(InclusionServiceError::OutputDeserializationError)
+    }
+
+    /// Decode bytes from [Self::to_vec], `None` if malformed
+    pub fn from_slice(data: &[u8]) -> Option<Self> {
+        if data.len() != 101 {
+            return None;
+        }
+        Some(PayyEqProofOutput {
+            keccak_hash: data[0..32].try_into().ok()?,
+            data_root: data[32..64].try_into().ok()?,
+            namespace: data[64..93].try_into().ok()?,
+            l2_block_number: u64::from_le_bytes(data[93..101].try_into().ok()?),
+        })
+    }
+}
+
+/// Input to the aggregation zkVM program: the public values of many
+/// [ZKStackEqProofOutput] proofs, written to the program's stdin with their compressed proofs.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ZKStackAggregationInput {
+    /// Verifying key hash of the program every member proof is from
+    pub member_vkey_hash: [u32; 8],
+    /// Public values of each member proof, in the same order as the proofs
+    pub member_public_values: Vec<Vec<u8>>,
+    // Every member must be for this batch_number and chain_id
+    pub batch_number: u32,
+    pub chain_id: u64,
+}
+
+/// Expecting bytes:
+/// (members_root: [u8; 32], member_count: u32, member_vkey_hash: [u8; 32], batch_number: u32, chain_id: u64)
+pub struct ZKStackAggregatedEqProofOutput {
+    /// See [members_root]
+    pub members_root: [u8; 32],
+    pub member_count: u32,
+    /// Verifying key hash of the program every member proof is from, see [vkey_hash_bytes]
+    pub member_vkey_hash: [u8; 32],
+    pub batch_number: u32,
+    pub chain_id: u64,
+}
+impl ZKStackAggregatedEqProofOutput {
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(&self.members_root);
+        encoded.extend_from_slice(&self.member_count.to_le_bytes());
+        encoded.extend_from_slice(&self.member_vkey_hash);
+        encoded.extend_from_slice(&self.batch_number.to_le_bytes());
+        encoded.extend_from_slice(&self.chain_id.to_le_bytes());
+        encoded
+    }
+
+    #[cfg(feature = "host")]
+    pub fn from_bytes(data: &[u8]) -> Result<Self, InclusionServiceError> {
+        Self::from_slice(data).ok_or(InclusionServiceError::OutputDeserializationError)
+    }
+
+    /// Decode bytes from [Self::to_vec], `None` if malformed
+    pub fn from_slice(data: &[u8]) -> Option<Self> {
+        if data.len() != 80 {
+            return None;
+        }
+        Some(ZKStackAggregatedEqProofOutput {
+            members_root: data[0..32].try_into().ok()?,
+            member_count: u32::from_le_bytes(data[32..36].try_into().ok()?),
+            member_vkey_hash: data[36..68].try_into().ok()?,
+            batch_number: u32::from_le_bytes(data[68..72].try_into().ok()?),
+            chain_id: u64::from_le_bytes(data[72..80].try_into().ok()?),
+        })
+    }
+}
+
+/// Encode a verifying key hash (as used by [ZKStackAggregationInput]) to bytes,
+/// each word little endian.
+pub fn vkey_hash_bytes(vkey_hash: &[u32; 8]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(vkey_hash) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+/// A keccak Merkle root over the `(keccak_hash, data_root)` pair of each member, in order.
+///
+/// Leaves are `keccak(keccak_hash ++ data_root)`, parents are `keccak(left ++ right)`,
+/// and a node without a sibling is moved up a level unchanged.
+/// No members has a root of all zeros.
+pub fn members_root(members: &[ZKStackEqProofOutput]) -> [u8; 32] {
+    let mut level: Vec<[u8; 32]> = members
+        .iter()
+        .map(|member| {
+            Keccak256::new()
+                .chain_update(member.keccak_hash)
+                .chain_update(member.data_root)
+                .finalize()
+                .into()
+        })
+        .collect();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => Keccak256::new()
+                    .chain_update(left)
+                    .chain_update(right)
+                    .finalize()
+                    .into(),
+                [single] => *single,
+                _ => unreachable!("chunks of 2"),
+            })
+            .collect();
+    }
+    level.first().copied().unwrap_or_default()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -109,4 +350,87 @@ mod test {
         assert_eq!(output.keccak_hash, decoded.keccak_hash);
         assert_eq!(output.data_root, decoded.data_root);
     }
+
+    #[test]
+    #[cfg(feature = "host")]
+    fn test_aggregated_serialization() {
+        let output = ZKStackAggregatedEqProofOutput {
+            members_root: [1; 32],
+            member_count: 2,
+            member_vkey_hash: [3; 32],
+            batch_number: 4u32,
+            chain_id: 5u64,
+        };
+        let decoded = ZKStackAggregatedEqProofOutput::from_bytes(&output.to_vec()).unwrap();
+        assert_eq!(output.members_root, decoded.members_root);
+        assert_eq!(output.member_count, decoded.member_count);
+        assert_eq!(output.member_vkey_hash, decoded.member_vkey_hash);
+        assert_eq!(output.batch_number, decoded.batch_number);
+        assert_eq!(output.chain_id, decoded.chain_id);
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    fn test_multi_blob_serialization() {
+        let output = ZKStackMultiBlobEqProofOutput {
+            keccak_hash: [1; 32],
+            batch_number: 2u32,
+            chain_id: 3u64,
+            data_roots: vec![[4; 32], [5; 32]],
+        };
+        let encoded = output.to_vec();
+        assert_eq!(encoded.len(), 48 + 2 * 32);
+        let decoded = ZKStackMultiBlobEqProofOutput::from_bytes(&encoded).unwrap();
+        assert_eq!(output.keccak_hash, decoded.keccak_hash);
+        assert_eq!(output.batch_number, decoded.batch_number);
+        assert_eq!(output.chain_id, decoded.chain_id);
+        assert_eq!(output.data_roots, decoded.data_roots);
+        assert!(ZKStackMultiBlobEqProofOutput::from_bytes(&encoded[..encoded.len() - 1]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    fn test_payy_serialization() {
+        let output = PayyEqProofOutput {
+            keccak_hash: [1; 32],
+            data_root: [2; 32],
+            namespace: [3; 29],
+            l2_block_number: 4u64,
+        };
+        let decoded = PayyEqProofOutput::from_bytes(&output.to_vec()).unwrap();
+        assert_eq!(output.keccak_hash, decoded.keccak_hash);
+        assert_eq!(output.data_root, decoded.data_root);
+        assert_eq!(output.namespace, decoded.namespace);
+        assert_eq!(output.l2_block_number, decoded.l2_block_number);
+    }
+
+    #[test]
+    fn test_members_root() {
+        let member = |n: u8| ZKStackEqProofOutput {
+            keccak_hash: [n; 32],
+            data_root: [n + 1; 32],
+            batch_number: 0u32,
+            chain_id: 0u64,
+        };
+        let leaf = |n: u8| -> [u8; 32] {
+            Keccak256::new()
+                .chain_update([n; 32])
+                .chain_update([n + 1; 32])
+                .finalize()
+                .into()
+        };
+        let parent = |left: [u8; 32], right: [u8; 32]| -> [u8; 32] {
+            Keccak256::new()
+                .chain_update(left)
+                .chain_update(right)
+                .finalize()
+                .into()
+        };
+        assert_eq!(members_root(&[]), [0; 32]);
+        assert_eq!(members_root(&[member(1)]), leaf(1));
+        assert_eq!(
+            members_root(&[member(1), member(2), member(3)]),
+            parent(parent(leaf(1), leaf(2)), leaf(3))
+        );
+    }
 }
@@ -0,0 +1,52 @@
+#![doc = include_str!("../README.md")]
+#![no_main]
+
+sp1_zkvm::entrypoint!(main);
+use eq_common::{
+    members_root, vkey_hash_bytes, ZKStackAggregatedEqProofOutput, ZKStackAggregationInput,
+    ZKStackEqProofOutput,
+};
+use sha2::{Digest, Sha256};
+
+pub fn main() {
+    println!("cycle-tracker-start: deserialize input");
+    let input: ZKStackAggregationInput = sp1_zkvm::io::read();
+    println!("cycle-tracker-end: deserialize input");
+
+    println!("cycle-tracker-start: verify member proofs");
+    let members: Vec<ZKStackEqProofOutput> = input
+        .member_public_values
+        .iter()
+        .map(|public_values| {
+            // The proofs themselves are read by the zkVM from stdin, in order
+            let public_values_digest = Sha256::digest(public_values);
+            sp1_zkvm::lib::verify::verify_sp1_proof(
+                &input.member_vkey_hash,
+                &public_values_digest.into(),
+            );
+            ZKStackEqProofOutput::from_slice(public_values)
+                .expect("Failed decoding member public values")
+        })
+        .collect();
+    println!("cycle-tracker-end: verify member proofs");
+
+    println!("cycle-tracker-start: check members batch");
+    for member in &members {
+        if member.batch_number != input.batch_number || member.chain_id != input.chain_id {
+            panic!("Member proof is for a different batch_number or chain_id");
+        }
+    }
+    println!("cycle-tracker-end: check members batch");
+
+    println!("cycle-tracker-start: commit output");
+    let output: Vec<u8> = ZKStackAggregatedEqProofOutput {
+        members_root: members_root(&members),
+        member_count: members.len().try_into().expect("Too many member proofs"),
+        member_vkey_hash: vkey_hash_bytes(&input.member_vkey_hash),
+        batch_number: input.batch_number,
+        chain_id: input.chain_id,
+    }
+    .to_vec();
+    sp1_zkvm::io::commit_slice(&output);
+    println!("cycle-tracker-end: commit output");
+}
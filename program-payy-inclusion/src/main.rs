@@ -0,0 +1,67 @@
+#![doc = include_str!("../README.md")]
+#![no_main]
+
+sp1_zkvm::entrypoint!(main);
+use celestia_types::{blob::Blob, hash::Hash, AppVersion, ShareProof};
+use eq_common::{PayyEqProofInput, PayyEqProofOutput};
+use sha3::{Digest, Keccak256};
+
+pub fn main() {
+    println!("cycle-tracker-start: deserialize input");
+    let input: PayyEqProofInput = sp1_zkvm::io::read();
+    let blob_input = input.blob;
+    let data_root_as_hash = Hash::Sha256(blob_input.data_root);
+    println!("cycle-tracker-end: deserialize input");
+
+    println!("cycle-tracker-start: create blob");
+    let blob = match blob_input.author {
+        Some(author) => Blob::new_with_signer(blob_input.namespace_id, blob_input.data, author, AppVersion::V5).expect("Failed creating blob"),
+        None => Blob::new(blob_input.namespace_id, blob_input.data, AppVersion::V5).expect("Failed creating blob"),
+    };
+    println!("cycle-tracker-end: create blob");
+
+    println!("cycle-tracker-start: compute keccak hash");
+    let computed_keccak_hash: [u8; 32] =
+        Keccak256::new().chain_update(&blob.data).finalize().into();
+    println!("cycle-tracker-end: compute keccak hash");
+
+    println!("cycle-tracker-start: convert blob to shares");
+    let rp = ShareProof {
+        data: blob
+            .to_shares()
+            .expect("Failed to convert blob to shares")
+            .into_iter()
+            .map(|share| share.as_ref().try_into().unwrap())
+            .collect(),
+        namespace_id: blob_input.namespace_id,
+        share_proofs: blob_input.share_proofs,
+        row_proof: blob_input.row_proof,
+    };
+    println!("cycle-tracker-end: convert blob to shares");
+
+    println!("cycle-tracker-start: verify proof");
+    rp.verify(data_root_as_hash)
+        .expect("Failed verifying proof");
+    println!("cycle-tracker-end: verify proof");
+
+    println!("cycle-tracker-start: check keccak hash");
+    if computed_keccak_hash != input.keccak_hash {
+        panic!("Computed keccak hash does not match input keccak hash");
+    }
+    println!("cycle-tracker-end: check keccak hash");
+
+    println!("cycle-tracker-start: commit output");
+    let output: Vec<u8> = PayyEqProofOutput {
+        keccak_hash: computed_keccak_hash,
+        data_root: blob_input.data_root,
+        namespace: blob_input
+            .namespace_id
+            .as_bytes()
+            .try_into()
+            .expect("Namespace is 29 bytes"),
+        l2_block_number: input.l2_block_number,
+    }
+    .to_vec();
+    sp1_zkvm::io::commit_slice(&output);
+    println!("cycle-tracker-end: commit output");
+}
@@ -1,15 +1,35 @@
 use clap::Parser;
+use std::path::PathBuf;
 use eq_sdk::{types::BlobId, EqClient};
-use tonic::transport::Endpoint;
 
 #[derive(Parser, Debug)]
 #[command(author, version)]
 #[command(disable_help_flag(true))]
 struct Args {
-    /// RPC endpoint (e.g. "127.0.0.1:50051" or "http://…")
+    /// RPC endpoint (e.g. "127.0.0.1:50051", "http://…" or "https://…")
     #[arg(short, long, env = "EQ_SOCKET")]
     socket: String,
 
+    /// Connect over TLS, implied by an https:// socket or any other TLS option
+    #[arg(long)]
+    tls: bool,
+
+    /// PEM CA certificates to also trust, e.g. of a private CA
+    #[arg(long)]
+    ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate chain, for a service verifying client certificates
+    #[arg(long, requires = "client_key")]
+    client_cert: Option<PathBuf>,
+
+    /// PEM private key of the client certificate
+    #[arg(long, requires = "client_cert")]
+    client_key: Option<PathBuf>,
+
+    /// Name to verify the service's certificate against, if not the socket's host
+    #[arg(long)]
+    domain_name: Option<String>,
+
     /// Block height (u64)
     #[arg(short = 'h', long)]
     height: u64,
@@ -29,25 +49,35 @@ struct Args {
     /// Batch Number, to prevent replay on same chain (u32)
     #[arg(short, long)]
     batch_number: String,
+
+    /// Follow the request, printing every status change until it completes
+    #[arg(short, long)]
+    watch: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    // Build a valid URL for tonic:
-    let url = if args.socket.starts_with("http") {
-        args.socket.clone()
-    } else {
-        format!("http://{}", args.socket)
-    };
-
     // Connect
-    let channel = Endpoint::from_shared(url)?
+    let mut builder = EqClient::builder(args.socket.clone());
+    if args.tls {
+        builder = builder.tls();
+    }
+    if let Some(ca_cert) = &args.ca_cert {
+        builder = builder.ca_certificate(std::fs::read(ca_cert)?);
+    }
+    if let (Some(client_cert), Some(client_key)) = (&args.client_cert, &args.client_key) {
+        builder =
+            builder.client_certificate(std::fs::read(client_cert)?, std::fs::read(client_key)?);
+    }
+    if let Some(domain_name) = &args.domain_name {
+        builder = builder.domain_name(domain_name.clone());
+    }
+    let client = builder
         .connect()
         .await
         .map_err(|e| format!("gRPC connect error: {e}"))?;
-    let client = EqClient::new(channel);
 
     // Reconstruct the canonical "height:namespace:commitment:l2_chain_id:batch_number" string
     let blob_str = format!("{}:{}:{}:{}:{}", args.height, args.namespace, args.commitment, args.l2_chain_id, args.batch_number);
@@ -56,8 +86,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let blob_id: BlobId = blob_str.parse()?;
 
     // Call the RPC
-    let resp = client.get_zk_stack(&blob_id).await?;
-    println!("{:#?}", resp);
+    if args.watch {
+        let mut stream = client.watch_zk_stack(&blob_id).await?;
+        while let Some(resp) = stream.message().await? {
+            println!("{:#?}", resp);
+        }
+    } else {
+        let resp = client.get_zk_stack(&blob_id).await?;
+        println!("{:#?}", resp);
+    }
 
     Ok(())
 }
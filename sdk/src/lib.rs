@@ -1,23 +1,131 @@
 // Re-export eq-common parts
 pub use eq_common::eqs::inclusion_client::InclusionClient;
-pub use eq_common::eqs::{get_zk_stack_response, GetZkStackRequest, GetZkStackResponse};
-pub use eq_common::{ZKStackEqProofInput, ZKStackEqProofOutput};
+pub use eq_common::eqs::{
+    get_zk_stack_response, AggregateZkStackRequest, BatchGetZkStackRequest, GetProofRequest,
+    GetZkStackMultiBlobRequest, GetZkStackRequest, GetZkStackResponse, ProofChunk, ProofType,
+};
+pub use eq_common::{
+    PayyEqProofOutput, ZKStackAggregatedEqProofOutput, ZKStackEqProofInput, ZKStackEqProofOutput,
+    ZKStackMultiBlobEqProofOutput,
+};
 
-use tonic::transport::Channel;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
 use tonic::Status as TonicStatus;
+use tonic::Streaming;
 
 pub mod types;
-pub use types::BlobId;
+pub use types::{BlobId, PayyBlobId};
 
 #[derive(Debug)]
 pub struct EqClient {
     grpc_channel: Channel,
 }
 
+/// Connects an [EqClient] to a service, see [EqClient::builder]
+#[derive(Debug, Clone)]
+pub struct EqClientBuilder {
+    endpoint: String,
+    tls: bool,
+    ca_certificate: Option<Certificate>,
+    identity: Option<Identity>,
+    domain_name: Option<String>,
+}
+
+impl EqClientBuilder {
+    /// Connect over TLS, verifying the service's certificate against the system's root
+    /// certificates. Implied by an `https://` endpoint, or any other TLS option.
+    pub fn tls(mut self) -> Self {
+        self.tls = true;
+        self
+    }
+
+    /// Also trust the CA certificates in this PEM, e.g. of a private CA the service's
+    /// certificate is issued by
+    pub fn ca_certificate(mut self, pem: impl AsRef<[u8]>) -> Self {
+        self.ca_certificate = Some(Certificate::from_pem(pem));
+        self.tls()
+    }
+
+    /// Authenticate with a client certificate (a PEM certificate chain and private key),
+    /// for a service that verifies them
+    pub fn client_certificate(
+        mut self,
+        cert_pem: impl AsRef<[u8]>,
+        key_pem: impl AsRef<[u8]>,
+    ) -> Self {
+        self.identity = Some(Identity::from_pem(cert_pem, key_pem));
+        self.tls()
+    }
+
+    /// The name the service's certificate is verified against, if not the endpoint's host
+    /// (e.g. when connecting by IP address)
+    pub fn domain_name(mut self, domain_name: impl Into<String>) -> Self {
+        self.domain_name = Some(domain_name.into());
+        self.tls()
+    }
+
+    /// The endpoint to connect to, `https://` if using TLS and no scheme is given
+    pub fn endpoint(&self) -> Result<Endpoint, tonic::transport::Error> {
+        let tls = self.tls || self.endpoint.starts_with("https://");
+        let url = match (self.endpoint.contains("://"), tls) {
+            (true, _) => self.endpoint.clone(),
+            (false, true) => format!("https://{}", self.endpoint),
+            (false, false) => format!("http://{}", self.endpoint),
+        };
+        let endpoint = Endpoint::from_shared(url)?;
+        if !tls {
+            return Ok(endpoint);
+        }
+        let mut tls_config = ClientTlsConfig::new();
+        if let Some(ca_certificate) = &self.ca_certificate {
+            tls_config = tls_config.ca_certificate(ca_certificate.clone());
+        }
+        if let Some(identity) = &self.identity {
+            tls_config = tls_config.identity(identity.clone());
+        }
+        if let Some(domain_name) = &self.domain_name {
+            tls_config = tls_config.domain_name(domain_name.clone());
+        }
+        endpoint.tls_config(tls_config)
+    }
+
+    /// Connect now, failing if the service can't be reached
+    pub async fn connect(self) -> Result<EqClient, tonic::transport::Error> {
+        Ok(EqClient::new(self.endpoint()?.connect().await?))
+    }
+
+    /// Connect on the first request
+    pub fn connect_lazy(self) -> Result<EqClient, tonic::transport::Error> {
+        Ok(EqClient::new(self.endpoint()?.connect_lazy()))
+    }
+}
+
 impl EqClient {
     pub fn new(grpc_channel: Channel) -> Self {
         Self { grpc_channel }
     }
+
+    /// Connect to a service at `endpoint`, a URL or `host:port`.
+    /// Plaintext unless the endpoint is `https://` or TLS options are set, e.g.
+    ///
+    /// ```no_run
+    /// # async fn connect() -> Result<(), tonic::transport::Error> {
+    /// let client = eq_sdk::EqClient::builder("eq.example.com:50051")
+    ///     .ca_certificate(std::fs::read("ca.pem").unwrap())
+    ///     .connect()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder(endpoint: impl Into<String>) -> EqClientBuilder {
+        EqClientBuilder {
+            endpoint: endpoint.into(),
+            tls: false,
+            ca_certificate: None,
+            identity: None,
+            domain_name: None,
+        }
+    }
     pub fn get_zk_stack<'a>(
         &'a self,
         request: &'a BlobId,
@@ -26,7 +134,159 @@ impl EqClient {
         Self: Sync,
     {
         async {
-            let request = GetZkStackRequest {
+            let request = zk_stack_request(request)?;
+            let mut client = InclusionClient::new(self.grpc_channel.clone());
+            match client.get_zk_stack(request).await {
+                Ok(response) => Ok(response.into_inner()),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    /// Like [Self::get_zk_stack], for many requests in one call.
+    /// Responses are in the same order as `requests`.
+    pub fn get_zk_stack_many<'a>(
+        &'a self,
+        requests: &'a [BlobId],
+    ) -> impl std::future::Future<Output = Result<Vec<GetZkStackResponse>, TonicStatus>> + Send + 'a
+    where
+        Self: Sync,
+    {
+        async {
+            let requests = requests
+                .iter()
+                .map(zk_stack_request)
+                .collect::<Result<_, _>>()?;
+            let mut client = InclusionClient::new(self.grpc_channel.clone());
+            match client
+                .batch_get_zk_stack(BatchGetZkStackRequest { requests })
+                .await
+            {
+                Ok(response) => Ok(response.into_inner().responses),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    /// One proof for many blobs of a single L2 batch, see [ZKStackAggregatedEqProofOutput].
+    /// Every member must have the given `l2_chain_id` and `batch_number`.
+    /// Repeated calls give status updates, like [Self::get_zk_stack].
+    pub fn aggregate_zk_stack<'a>(
+        &'a self,
+        l2_chain_id: u64,
+        batch_number: u32,
+        members: &'a [BlobId],
+    ) -> impl std::future::Future<Output = Result<GetZkStackResponse, TonicStatus>> + Send + 'a
+    where
+        Self: Sync,
+    {
+        async move {
+            let request = AggregateZkStackRequest {
+                chain_id: l2_chain_id,
+                batch_number,
+                members: members
+                    .iter()
+                    .map(zk_stack_request)
+                    .collect::<Result<_, _>>()?,
+            };
+            let mut client = InclusionClient::new(self.grpc_channel.clone());
+            match client.aggregate_zk_stack(request).await {
+                Ok(response) => Ok(response.into_inner()),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    /// One proof for a single L2 batch split across many blobs, see [ZKStackMultiBlobEqProofOutput].
+    /// `blobs` are in the order their data is concatenated, and must all have the given
+    /// `l2_chain_id` and `batch_number`.
+    /// Repeated calls give status updates, like [Self::get_zk_stack].
+    pub fn get_zk_stack_multi_blob<'a>(
+        &'a self,
+        l2_chain_id: u64,
+        batch_number: u32,
+        blobs: &'a [BlobId],
+    ) -> impl std::future::Future<Output = Result<GetZkStackResponse, TonicStatus>> + Send + 'a
+    where
+        Self: Sync,
+    {
+        async move {
+            let request = GetZkStackMultiBlobRequest {
+                chain_id: l2_chain_id,
+                batch_number,
+                blobs: blobs
+                    .iter()
+                    .map(zk_stack_request)
+                    .collect::<Result<_, _>>()?,
+            };
+            let mut client = InclusionClient::new(self.grpc_channel.clone());
+            match client.get_zk_stack_multi_blob(request).await {
+                Ok(response) => Ok(response.into_inner()),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    /// Like [Self::get_zk_stack], but returns a [Streaming] of responses,
+    /// one for each status change of the request.
+    /// The stream ends after a finished proof or a permanent failure.
+    pub fn watch_zk_stack<'a>(
+        &'a self,
+        request: &'a BlobId,
+    ) -> impl std::future::Future<Output = Result<Streaming<GetZkStackResponse>, TonicStatus>> + Send + 'a
+    where
+        Self: Sync,
+    {
+        async {
+            let request = zk_stack_request(request)?;
+            let mut client = InclusionClient::new(self.grpc_channel.clone());
+            match client.watch_zk_stack(request).await {
+                Ok(response) => Ok(response.into_inner()),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    /// The proof of a finished [Self::get_zk_stack] request, as a [Streaming] of chunks
+    /// to concatenate the `proof_data` of. The first chunk has the public values and program ID.
+    /// Fails with `FAILED_PRECONDITION` if the proof is not finished.
+    pub fn stream_zk_stack_proof<'a>(
+        &'a self,
+        request: &'a BlobId,
+    ) -> impl std::future::Future<Output = Result<Streaming<ProofChunk>, TonicStatus>> + Send + 'a
+    where
+        Self: Sync,
+    {
+        async {
+            let request = zk_stack_request(request)?;
+            let request = GetProofRequest {
+                proof_type: ProofType::Zkstack as i32,
+                height: request.height,
+                namespace: request.namespace,
+                commitment: request.commitment,
+                batch_number: request.batch_number,
+                chain_id: request.chain_id,
+                ..Default::default()
+            };
+            let mut client = InclusionClient::new(self.grpc_channel.clone());
+            match client.stream_proof(request).await {
+                Ok(response) => Ok(response.into_inner()),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    /// Like [Self::get_zk_stack], for the Payy integration, see [PayyEqProofOutput].
+    pub fn get_payy<'a>(
+        &'a self,
+        request: &'a PayyBlobId,
+    ) -> impl std::future::Future<Output = Result<GetZkStackResponse, TonicStatus>> + Send + 'a
+    where
+        Self: Sync,
+    {
+        async {
+            let request = GetProofRequest {
+                proof_type: ProofType::Payy as i32,
                 commitment: request.commitment.hash().to_vec(),
                 namespace: request
                     .namespace
@@ -34,14 +294,28 @@ impl EqClient {
                     .ok_or(TonicStatus::invalid_argument("Namespace invalid"))?
                     .to_vec(),
                 height: request.height.into(),
-                batch_number: request.batch_number,
-                chain_id: request.l2_chain_id,
+                l2_block_number: request.l2_block_number,
+                ..Default::default()
             };
             let mut client = InclusionClient::new(self.grpc_channel.clone());
-            match client.get_zk_stack(request).await {
+            match client.get_proof(request).await {
                 Ok(response) => Ok(response.into_inner()),
                 Err(e) => Err(e),
             }
         }
     }
 }
+
+fn zk_stack_request(blob_id: &BlobId) -> Result<GetZkStackRequest, TonicStatus> {
+    Ok(GetZkStackRequest {
+        commitment: blob_id.commitment.hash().to_vec(),
+        namespace: blob_id
+            .namespace
+            .id_v0()
+            .ok_or(TonicStatus::invalid_argument("Namespace invalid"))?
+            .to_vec(),
+        height: blob_id.height.into(),
+        batch_number: blob_id.batch_number,
+        chain_id: blob_id.l2_chain_id,
+    })
+}
@@ -52,6 +52,51 @@ impl std::fmt::Debug for BlobId {
     }
 }
 
+/// A blob for the Payy Celestia integration
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct PayyBlobId {
+    pub height: BlockHeight,
+    pub namespace: Namespace,
+    pub commitment: Commitment,
+    // Payy specific fields
+    pub l2_block_number: u64,
+}
+
+impl PayyBlobId {
+    pub fn new(
+        height: BlockHeight,
+        namespace: Namespace,
+        commitment: Commitment,
+        l2_block_number: u64,
+    ) -> Self {
+        Self {
+            height,
+            namespace,
+            commitment,
+            l2_block_number,
+        }
+    }
+}
+
+impl std::fmt::Debug for PayyBlobId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let namespace_string;
+        if let Some(namespace) = &self.namespace.id_v0() {
+            namespace_string = base64::engine::general_purpose::STANDARD.encode(namespace);
+        } else {
+            namespace_string = "Invalid v0 ID".to_string()
+        }
+        let commitment_string =
+            base64::engine::general_purpose::STANDARD.encode(&self.commitment.hash());
+        f.debug_struct("PayyJob")
+            .field("height", &self.height.value())
+            .field("namespace", &namespace_string)
+            .field("commitment", &commitment_string)
+            .field("l2_block_number", &self.l2_block_number)
+            .finish()
+    }
+}
+
 /// Format = "height:namespace:commitment:l2_chain_id:batch_number" using integers for height, l2_chain_id, and batch; base64 encoding for namespace and commitment
 impl Display for BlobId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -88,7 +133,6 @@ impl FromStr for BlobId {
             .next()
             .ok_or("Namespace missing (base64)")?
             .to_string();
-        println!("{}", &n_base64);
         let n_bytes = base64::engine::general_purpose::STANDARD.decode(n_base64)?;
         let namespace = Namespace::new_v0(&n_bytes)?;
 
@@ -168,7 +212,6 @@ mod test {
             0u32,
         );
 
-        println!("blob_id: {:?}", blob_id);
         let blob_id_bincode = bincode::serialize(&blob_id).unwrap();
         let blob_id_from_bincode: BlobId = bincode::deserialize(&blob_id_bincode).unwrap();
 
@@ -0,0 +1,392 @@
+//! Offline inspection and repair of the eq-service DB (at `EQ_DB_PATH`), and copying it
+//! to another [StoreBackend].
+//!
+//! Without `--write`, the DB is copied to a temporary directory and opened from there,
+//! so it's files are never modified, and it can be inspected while the service runs.
+//! Fixing jobs needs `--write`, with the service stopped.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use eq_service::internal::retry::RetryState;
+use eq_service::internal::schema;
+use eq_service::internal::store::{copy_store, open_store, JobStore, StoreBackend, StoreTree};
+use eq_service::{Job, JobStatus, SuccNetProgramId};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path of the service's DB
+    #[arg(long, env = "EQ_DB_PATH")]
+    db_path: PathBuf,
+    /// Store the DB is kept in
+    #[arg(long, env = "EQ_DB_BACKEND", default_value = "sled")]
+    db_backend: StoreBackend,
+    /// Open the DB in place, read-write. Required to fix jobs, the service must be stopped!
+    #[arg(long)]
+    write: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print every job as a line of JSON
+    List {
+        /// Only list jobs in this tree
+        #[arg(long)]
+        tree: Option<JobTree>,
+    },
+    /// Print a job as JSON, including it's full status
+    Show {
+        /// Hex encoded job key, as printed by `list`
+        job_key: String,
+    },
+    /// Print every job in both trees, in the wrong tree for it's status, or that can't be decoded.
+    /// Exits with an error if any are found.
+    Check,
+    /// Move a job to the tree it's status belongs in
+    Move {
+        job_key: String,
+        /// The entry to keep, if the job is in both trees
+        #[arg(long)]
+        from: Option<JobTree>,
+    },
+    /// Restart a job from the beginning, as DataAvailabilityPending in the queue
+    Reset { job_key: String },
+    /// Delete every entry of a job, for jobs that can't be decoded
+    Delete { job_key: String },
+    /// Copy every record to a new, empty DB (e.g. of another backend)
+    CopyStore {
+        /// Store the new DB is kept in
+        #[arg(long)]
+        to_backend: StoreBackend,
+        /// Directory of the new DB, set as `EQ_DB_PATH` to run the service with it
+        #[arg(long)]
+        to_path: PathBuf,
+    },
+}
+
+/// The trees of the service's DB that hold jobs, by job key
+const JOB_TREES: [StoreTree; 7] = [
+    StoreTree::Queue,
+    StoreTree::Finished,
+    StoreTree::Retry,
+    StoreTree::JobProgram,
+    StoreTree::FinishedAt,
+    StoreTree::JobTenant,
+    StoreTree::QuotaSpent,
+];
+
+/// The trees a job's [JobStatus] is stored in
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum JobTree {
+    Queue,
+    Finished,
+}
+
+impl JobTree {
+    fn name(self) -> &'static str {
+        self.tree().name()
+    }
+
+    fn tree(self) -> StoreTree {
+        match self {
+            JobTree::Queue => StoreTree::Queue,
+            JobTree::Finished => StoreTree::Finished,
+        }
+    }
+
+    /// The tree a [JobStatus] belongs in
+    fn for_status(job_status: &JobStatus) -> Self {
+        match job_status.is_finished() {
+            true => JobTree::Finished,
+            false => JobTree::Queue,
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    if !args.db_path.exists() {
+        return Err(format!("No DB at {:?}", args.db_path).into());
+    }
+    match args.write {
+        true => open_and_run(&args.db_path, args.db_backend, args.command, true),
+        false => {
+            let snapshot_dir =
+                std::env::temp_dir().join(format!("eq-admin-snapshot-{}", std::process::id()));
+            let result = copy_dir(&args.db_path, &snapshot_dir)
+                .map_err(Box::<dyn Error>::from)
+                .and_then(|_| open_and_run(&snapshot_dir, args.db_backend, args.command, false));
+            std::fs::remove_dir_all(&snapshot_dir)?;
+            result
+        }
+    }
+}
+
+fn open_and_run(
+    db_path: &Path,
+    db_backend: StoreBackend,
+    command: Command,
+    write: bool,
+) -> Result<(), Box<dyn Error>> {
+    let store = open_store(db_backend, db_path)?;
+    let db = store.as_ref();
+    // In read-only mode, only the snapshot is migrated
+    schema::migrate(db)?;
+    if !write
+        && matches!(
+            command,
+            Command::Move { .. } | Command::Reset { .. } | Command::Delete { .. }
+        )
+    {
+        return Err("The DB is opened read-only, pass --write to fix jobs".into());
+    }
+
+    match command {
+        Command::List { tree } => {
+            for job_tree in [JobTree::Queue, JobTree::Finished] {
+                if tree.is_some_and(|tree| tree != job_tree) {
+                    continue;
+                }
+                for entry in db.iter(job_tree.tree()) {
+                    let (job_key, status_data) = entry?;
+                    println!("{}", job_json(db, job_tree, &job_key, &status_data, false)?);
+                }
+            }
+        }
+        Command::Show { job_key } => {
+            let job_key = hex::decode(job_key)?;
+            let mut found = false;
+            for job_tree in [JobTree::Queue, JobTree::Finished] {
+                if let Some(status_data) = db.get(job_tree.tree(), &job_key)? {
+                    println!(
+                        "{:#}",
+                        job_json(db, job_tree, &job_key, &status_data, true)?
+                    );
+                    found = true;
+                }
+            }
+            if !found {
+                return Err("No job with this key".into());
+            }
+        }
+        Command::Check => {
+            let problems = check(db)?;
+            for problem in &problems {
+                println!("{problem}");
+            }
+            if !problems.is_empty() {
+                return Err(format!("Found {} jobs with problems", problems.len()).into());
+            }
+            eprintln!("No problems found");
+        }
+        Command::Move { job_key, from } => move_job(db, &hex::decode(job_key)?, from)?,
+        Command::Reset { job_key } => reset_job(db, &hex::decode(job_key)?)?,
+        Command::Delete { job_key } => delete_job(db, &hex::decode(job_key)?)?,
+        Command::CopyStore {
+            to_backend,
+            to_path,
+        } => {
+            let to_store = open_store(to_backend, &to_path)?;
+            let copied = copy_store(db, to_store.as_ref())?;
+            println!(
+                "Copied {copied} records from the {:?} DB to the {to_backend:?} DB at {to_path:?}",
+                db.backend()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A job as JSON, with it's full [JobStatus] if `full`
+fn job_json(
+    db: &dyn JobStore,
+    tree: JobTree,
+    job_key: &[u8],
+    status_data: &[u8],
+    full: bool,
+) -> Result<Value, Box<dyn Error>> {
+    let retry_state: Option<RetryState> = db
+        .get(StoreTree::Retry, job_key)?
+        .map(|retry_data| schema::decode(&retry_data))
+        .transpose()?;
+    let mut job_json = json!({
+        "job_key": hex::encode(job_key),
+        "tree": tree.name(),
+        "job": match Job::from_key(job_key) {
+            Ok(job) => format!("{job:?}"),
+            Err(e) => format!("Invalid job key: {e}"),
+        },
+        "retry": retry_state,
+        "program_id": db
+            .get(StoreTree::JobProgram, job_key)?
+            .map(|program_id_data| schema::decode::<SuccNetProgramId>(&program_id_data))
+            .transpose()?
+            .map(hex::encode),
+    });
+    match schema::decode::<JobStatus>(status_data) {
+        Ok(job_status) => {
+            job_json["status"] = json!(format!("{job_status:?}"));
+            match &job_status {
+                JobStatus::ZkProofPending(request_id) => {
+                    job_json["proof_request_id"] = json!(hex::encode(request_id));
+                }
+                JobStatus::Failed(e, retry_status) => {
+                    job_json["error"] = json!(e.to_string());
+                    job_json["retry_from"] = json!(retry_status.as_ref().map(|s| format!("{s:?}")));
+                }
+                _ => {}
+            }
+            if full {
+                job_json["status_data"] = serde_json::to_value(&job_status)?;
+            }
+        }
+        Err(e) => job_json["status"] = json!(format!("Invalid status: {e}")),
+    }
+    Ok(job_json)
+}
+
+/// Every job in both trees, in the wrong tree for it's status, or that can't be decoded,
+/// with the command to fix it
+fn check(db: &dyn JobStore) -> Result<Vec<Value>, Box<dyn Error>> {
+    let mut problems = Vec::new();
+    for tree in [JobTree::Queue, JobTree::Finished] {
+        for entry in db.iter(tree.tree()) {
+            let (job_key, status_data) = entry?;
+            let job_key_hex = hex::encode(&job_key);
+            let problem = if let Err(e) = Job::from_key(&job_key) {
+                Some((
+                    format!("Invalid job key: {e}"),
+                    format!("delete {job_key_hex}"),
+                ))
+            } else {
+                match schema::decode::<JobStatus>(&status_data) {
+                    Err(e) => Some((
+                        format!("Invalid status: {e}"),
+                        format!("reset {job_key_hex}"),
+                    )),
+                    Ok(_)
+                        if tree == JobTree::Queue
+                            && db.contains_key(StoreTree::Finished, &job_key)? =>
+                    {
+                        Some((
+                            "In both the queue and finished trees".to_string(),
+                            format!("move --from <queue|finished> {job_key_hex}"),
+                        ))
+                    }
+                    // Reported with the queue entry
+                    Ok(_)
+                        if tree == JobTree::Finished
+                            && db.contains_key(StoreTree::Queue, &job_key)? =>
+                    {
+                        None
+                    }
+                    Ok(job_status) if JobTree::for_status(&job_status) != tree => Some((
+                        format!("{job_status:?} in the {} tree", tree.name()),
+                        format!("move {job_key_hex}"),
+                    )),
+                    Ok(_) => None,
+                }
+            };
+            if let Some((problem, fix)) = problem {
+                problems.push(json!({
+                    "job_key": job_key_hex,
+                    "tree": tree.name(),
+                    "problem": problem,
+                    "fix": format!("eq-admin --write {fix}"),
+                }));
+            }
+        }
+    }
+    Ok(problems)
+}
+
+fn move_job(
+    db: &dyn JobStore,
+    job_key: &[u8],
+    from: Option<JobTree>,
+) -> Result<(), Box<dyn Error>> {
+    let (from, status_data) = match (
+        from,
+        db.get(StoreTree::Queue, job_key)?,
+        db.get(StoreTree::Finished, job_key)?,
+    ) {
+        (None, Some(_), Some(_)) => {
+            return Err("Job is in both trees, pick the entry to keep with --from".into())
+        }
+        (None | Some(JobTree::Queue), Some(status_data), _) => (JobTree::Queue, status_data),
+        (None | Some(JobTree::Finished), _, Some(status_data)) => (JobTree::Finished, status_data),
+        _ => return Err("No job with this key in the tree".into()),
+    };
+    let job_status: JobStatus = schema::decode(&status_data)?;
+    let to = JobTree::for_status(&job_status);
+    db.transaction(
+        &[StoreTree::Queue, StoreTree::Finished, StoreTree::FinishedAt],
+        |tx| {
+            tx.remove(StoreTree::Queue, job_key)?;
+            tx.remove(StoreTree::Finished, job_key)?;
+            // Stamped again by the service if finished
+            tx.remove(StoreTree::FinishedAt, job_key)?;
+            tx.insert(to.tree(), job_key, &status_data)
+        },
+    )?;
+    println!(
+        "Moved {job_status:?} job from the {} tree to the {} tree",
+        from.name(),
+        to.name()
+    );
+    Ok(())
+}
+
+fn reset_job(db: &dyn JobStore, job_key: &[u8]) -> Result<(), Box<dyn Error>> {
+    // The service restarts every job in the queue, and needs to decode it's key
+    let job = Job::from_key(job_key)?;
+    let pending = schema::encode(&JobStatus::DataAvailabilityPending)?;
+    db.transaction(&JOB_TREES, |tx| {
+        tx.remove(StoreTree::Finished, job_key)?;
+        tx.remove(StoreTree::Retry, job_key)?;
+        tx.remove(StoreTree::JobProgram, job_key)?;
+        tx.remove(StoreTree::FinishedAt, job_key)?;
+        tx.insert(StoreTree::Queue, job_key, &pending)
+    })?;
+    println!("Reset {job:?} to DataAvailabilityPending, it restarts with the service");
+    Ok(())
+}
+
+fn delete_job(db: &dyn JobStore, job_key: &[u8]) -> Result<(), Box<dyn Error>> {
+    let removed = db.transaction(&JOB_TREES, |tx| {
+        let mut removed = 0;
+        for tree in JOB_TREES {
+            if tx.remove(tree, job_key)?.is_some() {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    })?;
+    if removed == 0 {
+        return Err("No job with this key".into());
+    }
+    println!(
+        "Deleted {removed} entries of job 0x{}",
+        hex::encode(job_key)
+    );
+    Ok(())
+}
+
+/// Copy a directory and everything in it
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let to = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to)?;
+        } else {
+            std::fs::copy(entry.path(), to)?;
+        }
+    }
+    Ok(())
+}
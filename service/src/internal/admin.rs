@@ -0,0 +1,378 @@
+use std::ops::Bound;
+use std::sync::Arc;
+
+use log::info;
+use tonic::{Request, Response, Status};
+
+use eq_common::eqs::admin_server::Admin;
+use eq_common::eqs::{
+    get_zk_stack_response::Status as ResponseStatus, retry_job_request::Stage, DeleteJobsRequest,
+    DeleteJobsResponse, JobInfo, JobKeyRequest, ListJobsRequest, ListJobsResponse, RetryJobRequest,
+};
+use eq_common::InclusionServiceError;
+
+use crate::internal::schema;
+use crate::internal::store::StoreTree;
+use crate::{InclusionService, Job, JobStatus};
+
+/// Jobs in a ListJobs page, if no page size is requested
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Most jobs in a ListJobs page
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Every state a [Job] is reported in
+const ALL_STATES: [ResponseStatus; 6] = [
+    ResponseStatus::DaPending,
+    ResponseStatus::DaAvailable,
+    ResponseStatus::ZkpPending,
+    ResponseStatus::ZkpFinished,
+    ResponseStatus::RetryableFailure,
+    ResponseStatus::PermanentFailure,
+];
+
+/// The tree a [Job] is stored in, listed in this order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum JobTable {
+    Queue = 0,
+    Finished = 1,
+}
+
+impl JobTable {
+    fn tree(self) -> StoreTree {
+        match self {
+            JobTable::Queue => StoreTree::Queue,
+            JobTable::Finished => StoreTree::Finished,
+        }
+    }
+
+    fn of_state(state: ResponseStatus) -> Self {
+        match state {
+            ResponseStatus::DaPending
+            | ResponseStatus::DaAvailable
+            | ResponseStatus::ZkpPending => JobTable::Queue,
+            ResponseStatus::ZkpFinished
+            | ResponseStatus::RetryableFailure
+            | ResponseStatus::PermanentFailure => JobTable::Finished,
+        }
+    }
+}
+
+/// The operator facing gRPC service, on it's own socket.
+/// Works directly on the service's store, with the same [Job] keys and [JobStatus] encoding.
+pub struct AdminServiceArc(pub Arc<InclusionService>);
+
+#[tonic::async_trait]
+impl Admin for AdminServiceArc {
+    async fn list_jobs(
+        &self,
+        request: Request<ListJobsRequest>,
+    ) -> Result<Response<ListJobsResponse>, Status> {
+        let request = request.into_inner();
+        let mut states = states_from_request(&request.states)?;
+        if states.is_empty() {
+            states = ALL_STATES.to_vec();
+        }
+        let page_size = match request.page_size as usize {
+            0 => DEFAULT_PAGE_SIZE,
+            page_size => page_size.min(MAX_PAGE_SIZE),
+        };
+        // The table and key of the last job listed on the previous page
+        let (start_table, after) = match request.page_token.split_first() {
+            None => (JobTable::Queue, None),
+            Some((0, job_key)) => (JobTable::Queue, Some(job_key)),
+            Some((1, job_key)) => (JobTable::Finished, Some(job_key)),
+            Some(_) => return Err(Status::invalid_argument("Invalid page_token")),
+        };
+
+        let mut jobs = Vec::with_capacity(page_size);
+        let mut next_page_token = Vec::new();
+        'tables: for table in [JobTable::Queue, JobTable::Finished] {
+            if table < start_table || !states.iter().any(|s| JobTable::of_state(*s) == table) {
+                continue;
+            }
+            let lower = match after {
+                Some(job_key) if table == start_table => Bound::Excluded(job_key),
+                _ => Bound::Unbounded,
+            };
+            for entry in self.0.store.iter_from(table.tree(), lower) {
+                let (job_key, status_data) = entry.map_err(|e| Status::internal(e.to_string()))?;
+                let job_status: JobStatus =
+                    schema::decode(&status_data).map_err(|e| Status::internal(e.to_string()))?;
+                if !states.contains(&job_state(&job_status)) {
+                    continue;
+                }
+                if jobs.len() == page_size {
+                    let last_job: &JobInfo = jobs.last().expect("Page is not empty");
+                    let last_table = JobTable::of_state(last_job.status());
+                    next_page_token = [&[last_table as u8], last_job.job_key.as_slice()].concat();
+                    break 'tables;
+                }
+                jobs.push(self.job_info(&job_key, &job_status)?);
+            }
+        }
+
+        Ok(Response::new(ListJobsResponse {
+            jobs,
+            next_page_token,
+        }))
+    }
+
+    async fn get_job(&self, request: Request<JobKeyRequest>) -> Result<Response<JobInfo>, Status> {
+        let job_key = request.into_inner().job_key;
+        let (_, job_status) = self.stored_job(&job_key)?;
+        Ok(Response::new(self.job_info(&job_key, &job_status)?))
+    }
+
+    /// A local prover skips the request if it hasn't started proving, but a CPU proof already
+    /// in progress runs to completion. A network request expires at it's deadline.
+    async fn cancel_job(
+        &self,
+        request: Request<JobKeyRequest>,
+    ) -> Result<Response<JobInfo>, Status> {
+        let job_key = request.into_inner().job_key;
+        let (_, job_status) = self.stored_job(&job_key)?;
+        let JobStatus::ZkProofPending(request_id) = job_status else {
+            return Err(Status::failed_precondition(format!(
+                "Only a job with a pending ZK proof can be cancelled, job is {job_status:?}"
+            )));
+        };
+        info!(
+            "Admin cancelling proof request 0x{} of job 0x{}",
+            hex::encode(request_id),
+            hex::encode(&job_key)
+        );
+        if !self
+            .0
+            .cancel_zk_proof(&job_key, request_id)
+            .map_err(|e| Status::internal(e.to_string()))?
+        {
+            return Err(Status::failed_precondition(
+                "No worker is waiting on the proof request yet, try again once it's resumed",
+            ));
+        }
+        Ok(Response::new(self.job_info(&job_key, &job_status)?))
+    }
+
+    /// Retried jobs are proven with the current program of their kind,
+    /// their automatic retry attempts are not reset.
+    async fn retry_job(
+        &self,
+        request: Request<RetryJobRequest>,
+    ) -> Result<Response<JobInfo>, Status> {
+        let request = request.into_inner();
+        let stage = Stage::try_from(request.from_stage)
+            .map_err(|_| Status::invalid_argument("Invalid from_stage"))?;
+        let job_key = request.job_key;
+        let (table, job_status) = self.stored_job(&job_key)?;
+        if table != JobTable::Finished {
+            return Err(Status::failed_precondition(format!(
+                "Only a finished job can be retried, job is {job_status:?}"
+            )));
+        }
+        let retry_status = match (stage, job_status) {
+            (Stage::DataAvailability, _) => JobStatus::DataAvailabilityPending,
+            (Stage::ZkProof, JobStatus::Failed(_, Some(retry_status)))
+                if matches!(
+                    *retry_status,
+                    JobStatus::DataAvailable(_)
+                        | JobStatus::DataAvailableMultiBlob(_)
+                        | JobStatus::DataAvailablePayy(_)
+                ) =>
+            {
+                *retry_status
+            }
+            (Stage::ZkProof, _) => {
+                return Err(Status::failed_precondition(
+                    "Job has no DA inclusion proof stored, retry from DATA_AVAILABILITY",
+                ))
+            }
+        };
+        let job = Job::from_key(&job_key).map_err(|e| Status::internal(e.to_string()))?;
+        info!("Admin retrying {job:?} from {retry_status:?}");
+
+        self.0
+            .store
+            .remove(StoreTree::JobProgram, &job_key)
+            .map_err(|e| Status::internal(e.to_string()))?;
+        self.0
+            .try_send_job_with_new_status(job_key.clone(), retry_status, job)
+            .map_err(|e| match e {
+                InclusionServiceError::QueueFull => Status::resource_exhausted(e.to_string()),
+                e => Status::internal(e.to_string()),
+            })?;
+        self.0.metrics.jobs_attempted.inc();
+
+        let (_, job_status) = self.stored_job(&job_key)?;
+        Ok(Response::new(self.job_info(&job_key, &job_status)?))
+    }
+
+    /// Jobs that are not finished are skipped
+    async fn delete_jobs(
+        &self,
+        request: Request<DeleteJobsRequest>,
+    ) -> Result<Response<DeleteJobsResponse>, Status> {
+        let request = request.into_inner();
+        let states = states_from_request(&request.states)?;
+        if states
+            .iter()
+            .any(|s| JobTable::of_state(*s) != JobTable::Finished)
+        {
+            return Err(Status::invalid_argument(
+                "Only jobs in finished states can be deleted",
+            ));
+        }
+
+        let mut job_keys = request.job_keys;
+        if !states.is_empty() {
+            for entry in self.0.store.iter(StoreTree::Finished) {
+                let (job_key, status_data) = entry.map_err(|e| Status::internal(e.to_string()))?;
+                let job_status: JobStatus =
+                    schema::decode(&status_data).map_err(|e| Status::internal(e.to_string()))?;
+                if states.contains(&job_state(&job_status)) {
+                    job_keys.push(job_key);
+                }
+            }
+        }
+
+        let mut deleted = 0;
+        for job_key in job_keys {
+            let removed = self
+                .0
+                .remove_finished_job(&job_key, None)
+                .map_err(|e| Status::internal(e.to_string()))?;
+            if removed {
+                deleted += 1;
+            }
+        }
+        info!("Admin deleted {deleted} finished jobs");
+
+        Ok(Response::new(DeleteJobsResponse { deleted }))
+    }
+}
+
+impl AdminServiceArc {
+    /// The table and [JobStatus] of a stored [Job]
+    fn stored_job(&self, job_key: &[u8]) -> Result<(JobTable, JobStatus), Status> {
+        for table in [JobTable::Finished, JobTable::Queue] {
+            if let Some(status_data) = self
+                .0
+                .store
+                .get(table.tree(), job_key)
+                .map_err(|e| Status::internal(e.to_string()))?
+            {
+                let job_status =
+                    schema::decode(&status_data).map_err(|e| Status::internal(e.to_string()))?;
+                return Ok((table, job_status));
+            }
+        }
+        Err(Status::not_found("No job with this job_key"))
+    }
+
+    fn job_info(&self, job_key: &[u8], job_status: &JobStatus) -> Result<JobInfo, Status> {
+        let retry_state = self
+            .0
+            .get_retry_state(job_key)
+            .map_err(|e| Status::internal(e.to_string()))?
+            .unwrap_or_default();
+        let program_id = self
+            .0
+            .get_job_program_id(job_key)
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map(|program_id| program_id.to_vec())
+            .unwrap_or_default();
+        let (error_chain, retry_from) = match job_status {
+            JobStatus::Failed(e, retry_status) => (
+                error_chain(e),
+                retry_status
+                    .as_ref()
+                    .map(|s| format!("{s:?}"))
+                    .unwrap_or_default(),
+            ),
+            _ => (Vec::new(), String::new()),
+        };
+        let proof_id = match job_status {
+            JobStatus::ZkProofPending(request_id) => request_id.to_vec(),
+            _ => Vec::new(),
+        };
+        Ok(JobInfo {
+            job_key: job_key.to_vec(),
+            job: match Job::from_key(job_key) {
+                Ok(job) => format!("{job:?}"),
+                Err(e) => format!("Invalid job key: {e}"),
+            },
+            status: job_state(job_status) as i32,
+            error_chain,
+            retry_from,
+            proof_id,
+            program_id,
+            retry_attempts: retry_state.attempts,
+            next_retry_unix_ms: retry_state.next_retry_unix_ms,
+        })
+    }
+}
+
+/// The state a [JobStatus] is reported in, like a GetZKStack response
+fn job_state(job_status: &JobStatus) -> ResponseStatus {
+    match job_status {
+        JobStatus::DataAvailabilityPending => ResponseStatus::DaPending,
+        JobStatus::DataAvailable(_)
+        | JobStatus::DataAvailableMultiBlob(_)
+        | JobStatus::DataAvailablePayy(_) => ResponseStatus::DaAvailable,
+        JobStatus::ZkProofPending(_) => ResponseStatus::ZkpPending,
+        JobStatus::ZkProofFinished(_)
+        | JobStatus::ZkProofCompacted(_)
+        | JobStatus::ZkProofStored(_) => ResponseStatus::ZkpFinished,
+        JobStatus::Failed(_, Some(_)) => ResponseStatus::RetryableFailure,
+        JobStatus::Failed(_, None) => ResponseStatus::PermanentFailure,
+    }
+}
+
+fn states_from_request(states: &[i32]) -> Result<Vec<ResponseStatus>, Status> {
+    states
+        .iter()
+        .map(|state| {
+            ResponseStatus::try_from(*state)
+                .map_err(|_| Status::invalid_argument(format!("Invalid state {state}")))
+        })
+        .collect()
+}
+
+/// An error, followed by each error that caused it
+fn error_chain(e: &InclusionServiceError) -> Vec<String> {
+    std::iter::successors(Some(e as &dyn std::error::Error), |e| e.source())
+        .map(|e| e.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_job_tables() {
+        let failed =
+            |retry_status| JobStatus::Failed(InclusionServiceError::QueueFull, retry_status);
+        let queued = [
+            JobStatus::DataAvailabilityPending,
+            JobStatus::ZkProofPending([0; 32]),
+        ];
+        let finished = [
+            failed(None),
+            failed(Some(Box::new(JobStatus::DataAvailabilityPending))),
+        ];
+        for job_status in &queued {
+            assert_eq!(JobTable::of_state(job_state(job_status)), JobTable::Queue);
+        }
+        for job_status in &finished {
+            assert_eq!(
+                JobTable::of_state(job_state(job_status)),
+                JobTable::Finished
+            );
+        }
+        assert_eq!(
+            ALL_STATES.len(),
+            ResponseStatus::PermanentFailure as usize + 1
+        );
+    }
+}
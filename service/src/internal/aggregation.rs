@@ -0,0 +1,483 @@
+use crate::internal::drain::DrainGuard;
+use crate::internal::programs::ProgramKind;
+use crate::internal::schema;
+use crate::internal::store::StoreTree;
+use crate::{InclusionService, Job, JobStatus, SuccNetJobId};
+
+use eq_common::{InclusionServiceError, ZKStackAggregationInput};
+use eq_sdk::types::BlobId;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use sp1_sdk::{HashableKey, SP1Proof, SP1ProofMode, SP1ProofWithPublicValues, SP1Stdin};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Many blobs for one L2 batch, each proven by a [Job::AggregationMember] and then together
+/// by the current [ProgramKind::Aggregation] program.
+///
+/// Stored in the aggregation DB, keyed by it's bincode encoding.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AggregationJob {
+    pub chain_id: u64,
+    pub batch_number: u32,
+    /// Every member must have the same `l2_chain_id` and `batch_number`
+    pub members: Vec<BlobId>,
+}
+
+impl AggregationJob {
+    /// Check there are members, and they are all for this batch
+    pub fn validate(&self) -> Result<(), InclusionServiceError> {
+        if self.members.is_empty() {
+            return Err(InclusionServiceError::InvalidParameter(
+                "Aggregation needs at least one member".to_string(),
+            ));
+        }
+        if let Some(member) = self
+            .members
+            .iter()
+            .find(|m| m.l2_chain_id != self.chain_id || m.batch_number != self.batch_number)
+        {
+            return Err(InclusionServiceError::InvalidParameter(format!(
+                "Member {member:?} is not for chain_id {} batch_number {}",
+                self.chain_id, self.batch_number
+            )));
+        }
+        Ok(())
+    }
+
+    /// The [Job::AggregationMember] proving each member, with it's key
+    pub fn member_jobs(&self) -> Result<Vec<(Vec<u8>, Job)>, InclusionServiceError> {
+        self.members
+            .iter()
+            .map(|blob_id| {
+                let job = Job::AggregationMember(blob_id.clone());
+                Ok((job.key()?, job))
+            })
+            .collect()
+    }
+}
+
+/// Used as an [AggregationJob] state machine for the eq-service.
+#[derive(Serialize, Deserialize)]
+pub enum AggregationStatus {
+    /// Waiting for every member [Job] to finish
+    MembersPending,
+    /// Every member is proven, and the aggregation proof is being requested.
+    /// Interrupted requests are started over.
+    RequestingProof,
+    /// The aggregation proof had been requested, awaiting response
+    ZkProofPending(SuccNetJobId),
+    /// The aggregated proof is ready, and the [AggregationJob] is complete
+    ZkProofFinished(SP1ProofWithPublicValues),
+    /// A wrapper for any [InclusionServiceError], with:
+    /// - bool = false --> Permanent failure, a member job failed permanently
+    /// - bool = true  --> Retry is possible, by requesting the aggregation again
+    Failed(InclusionServiceError, bool),
+}
+
+impl std::fmt::Debug for AggregationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AggregationStatus::MembersPending => write!(f, "MembersPending"),
+            AggregationStatus::RequestingProof => write!(f, "RequestingProof"),
+            AggregationStatus::ZkProofPending(_) => write!(f, "ZkProofPending"),
+            AggregationStatus::ZkProofFinished(_) => write!(f, "ZkProofFinished"),
+            AggregationStatus::Failed(_, _) => write!(f, "Failed"),
+        }
+    }
+}
+
+/// How far along the member [Job]s of an [AggregationJob] are
+pub enum MembersProgress {
+    /// Some members are still being worked on, with this many finished
+    Pending(usize),
+    Finished,
+    /// A member failed permanently
+    Failed(InclusionServiceError),
+}
+
+impl InclusionService {
+    /// Start (or restart) an [AggregationJob]: start any of it's member [Job]s not yet known
+    /// (for `tenant`), and schedule the aggregation to run once they are all finished.
+    /// The member proofs and the aggregation proof count against the quotas of `tenant`,
+    /// unless already started by another tenant.
+    ///
+    /// Members with a proof the aggregation can't verify (compacted, or by a program other
+    /// than the current one) are proven again.
+    pub fn start_aggregation(
+        self: &Arc<Self>,
+        aggregation_key: &[u8],
+        aggregation: &AggregationJob,
+        tenant: &str,
+    ) -> Result<(), InclusionServiceError> {
+        let members = aggregation.member_jobs()?;
+        let current_program = self.programs.current(ProgramKind::ZkStackInclusion).id;
+        for (job_key, job) in &members {
+            let unusable = match self.finished_member_status(job_key)? {
+                Some(JobStatus::ZkProofFinished(_)) => {
+                    self.get_job_program_id(job_key)? != Some(current_program)
+                }
+                Some(JobStatus::ZkProofCompacted(_) | JobStatus::ZkProofStored(_)) => true,
+                _ => false,
+            };
+            if unusable && self.remove_finished_job(job_key, None)? {
+                debug!("Proving {job:?} again, it's proof can't be aggregated");
+            }
+        }
+        self.get_or_start_jobs(members, tenant)?;
+        self.record_job_tenant(aggregation_key, tenant)?;
+        self.store_aggregation_status(aggregation_key, &AggregationStatus::MembersPending)?;
+        info!("New {aggregation:?} waiting on members");
+        self.schedule_aggregations();
+        Ok(())
+    }
+
+    pub fn get_aggregation_status(
+        &self,
+        aggregation_key: &[u8],
+    ) -> Result<Option<AggregationStatus>, InclusionServiceError> {
+        self.store
+            .get(StoreTree::Aggregation, aggregation_key)?
+            .map(|v| {
+                schema::decode(&v).map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+            })
+            .transpose()
+    }
+
+    /// The status of a member [Job] in the finished DB, if it's there
+    fn finished_member_status(
+        &self,
+        job_key: &[u8],
+    ) -> Result<Option<JobStatus>, InclusionServiceError> {
+        self.store
+            .get(StoreTree::Finished, job_key)?
+            .map(|v| {
+                schema::decode(&v).map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+            })
+            .transpose()
+    }
+
+    fn store_aggregation_status(
+        &self,
+        aggregation_key: &[u8],
+        status: &AggregationStatus,
+    ) -> Result<(), InclusionServiceError> {
+        debug!("Storing aggregation with updated status: {status:?}");
+        self.store.insert(
+            StoreTree::Aggregation,
+            aggregation_key,
+            &schema::encode(status).expect("Always given serializable aggregation status"),
+        )
+    }
+
+    /// Check the finished DB for the member [Job]s of an [AggregationJob]
+    pub fn members_progress(
+        &self,
+        aggregation: &AggregationJob,
+    ) -> Result<MembersProgress, InclusionServiceError> {
+        let mut finished = 0;
+        for (job_key, _) in aggregation.member_jobs()? {
+            match self.finished_member_status(&job_key)? {
+                Some(JobStatus::ZkProofFinished(_)) => finished += 1,
+                Some(JobStatus::Failed(e, None)) => return Ok(MembersProgress::Failed(e)),
+                // Queued or retrying, so still pending
+                _ => {}
+            }
+        }
+        if finished == aggregation.members.len() {
+            Ok(MembersProgress::Finished)
+        } else {
+            Ok(MembersProgress::Pending(finished))
+        }
+    }
+
+    /// Drive every [AggregationJob] to completion as it's member [Job]s finish.
+    ///
+    /// Members are proven by the [JobStage](crate::internal::queue::JobStage) workers like
+    /// any other `Job`, and an aggregation proof holds a [InclusionService::zk_slot] while
+    /// requested and awaited, so both count towards the ZK stage's concurrency.
+    pub async fn aggregation_worker(self: Arc<Self>) {
+        debug!("Aggregation worker started");
+        let mut job_events = self.subscribe_job_events();
+
+        // Resume aggregations interrupted by a restart
+        for (aggregation_key, status_data) in self.store.iter(StoreTree::Aggregation).flatten() {
+            let (Ok(aggregation), Ok(status)) = (
+                schema::decode::<AggregationJob>(&aggregation_key),
+                schema::decode::<AggregationStatus>(&status_data),
+            ) else {
+                error!("Aggregation DB is in invalid state!");
+                continue;
+            };
+            if matches!(
+                status,
+                AggregationStatus::RequestingProof | AggregationStatus::ZkProofPending(_)
+            ) {
+                debug!("Resuming {aggregation:?} from {status:?}");
+                tokio::spawn(
+                    self.clone()
+                        .run_aggregation(aggregation_key, aggregation, status),
+                );
+            }
+        }
+        self.schedule_aggregations();
+
+        loop {
+            match job_events.recv().await {
+                Ok(event) if event.status.is_final() => self.schedule_aggregations(),
+                Ok(_) => {}
+                // Missed some events, check everything
+                Err(RecvError::Lagged(_)) => self.schedule_aggregations(),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// Start proving every [AggregationStatus::MembersPending] aggregation whose members
+    /// are all finished, and fail those with a permanently failed member.
+    fn schedule_aggregations(self: &Arc<Self>) {
+        for (aggregation_key, status_data) in self.store.iter(StoreTree::Aggregation).flatten() {
+            let Ok(AggregationStatus::MembersPending) = schema::decode(&status_data) else {
+                continue;
+            };
+            let Ok(aggregation) = schema::decode::<AggregationJob>(&aggregation_key) else {
+                error!("Aggregation DB is in invalid state!");
+                continue;
+            };
+            let next_status = match self.members_progress(&aggregation) {
+                Ok(MembersProgress::Pending(_)) => continue,
+                Ok(MembersProgress::Finished) => AggregationStatus::RequestingProof,
+                Ok(MembersProgress::Failed(e)) => {
+                    error!("{aggregation:?} failed, a member failed: {e}");
+                    self.metrics.aggregations_failed.inc();
+                    AggregationStatus::Failed(e, false)
+                }
+                Err(e) => {
+                    error!("Failed checking members of {aggregation:?}: {e}");
+                    continue;
+                }
+            };
+            let next_data =
+                schema::encode(&next_status).expect("Always given serializable aggregation status");
+            // Only one caller moves an aggregation on, even when scheduled concurrently
+            match self.store.compare_and_swap(
+                StoreTree::Aggregation,
+                &aggregation_key,
+                Some(&status_data),
+                &next_data,
+            ) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    error!("Failed storing aggregation status: {e}");
+                    continue;
+                }
+            }
+            if let AggregationStatus::RequestingProof = next_status {
+                info!("All members of {aggregation:?} finished, aggregating");
+                tokio::spawn(self.clone().run_aggregation(
+                    aggregation_key,
+                    aggregation,
+                    next_status,
+                ));
+            }
+        }
+    }
+
+    /// Progress an [AggregationJob] from a proving status to it's finished or failed status.
+    async fn run_aggregation(
+        self: Arc<Self>,
+        aggregation_key: Vec<u8>,
+        aggregation: AggregationJob,
+        status: AggregationStatus,
+    ) {
+        let _zk_slot = self.zk_slot().await;
+        let result = match status {
+            AggregationStatus::RequestingProof => {
+                let Some(saving) = self.drain.start() else {
+                    debug!("Draining, {aggregation:?} is left to resume on restart");
+                    return;
+                };
+                self.prove_aggregation(&aggregation_key, &aggregation, saving)
+                    .await
+            }
+            AggregationStatus::ZkProofPending(request_id) => {
+                self.wait_aggregation_proof(request_id).await
+            }
+            _ => return,
+        };
+        let status = match result {
+            Ok(proof) => {
+                info!("🎉 {aggregation:?} Finished!");
+                self.metrics.aggregations_finished.inc();
+                AggregationStatus::ZkProofFinished(proof)
+            }
+            Err(e) => {
+                error!("{aggregation:?} failed: {e}");
+                self.metrics.aggregations_failed.inc();
+                AggregationStatus::Failed(e, true)
+            }
+        };
+        let _ = self
+            .store_aggregation_status(&aggregation_key, &status)
+            .map_err(|e| error!("Failed storing aggregation status: {e}"));
+    }
+
+    /// Verify the compressed proof of every member [Job] in the [ProgramKind::Aggregation]
+    /// program, and wait on the aggregation proof.
+    /// `saving` is held until the request is stored, so a shutdown can resume it.
+    async fn prove_aggregation(
+        &self,
+        aggregation_key: &[u8],
+        aggregation: &AggregationJob,
+        saving: DrainGuard<'_>,
+    ) -> Result<SP1ProofWithPublicValues, InclusionServiceError> {
+        // Retried by requesting the aggregation again once the quota resets
+        if let Err(exceeded) = self.spend_proof_quota_once(aggregation_key, aggregation.chain_id)? {
+            warn!("{aggregation:?} not proven yet: {exceeded}");
+            return Err(exceeded.into());
+        }
+        let zk_client = self.get_zk_client().await;
+        let member_program = self.programs.current(ProgramKind::ZkStackInclusion);
+        let member_setup = self
+            .get_proof_setup(&member_program, zk_client.clone())
+            .await?;
+
+        let mut member_proofs = Vec::with_capacity(aggregation.members.len());
+        for (job_key, job) in aggregation.member_jobs()? {
+            let proof = match self.finished_member_status(&job_key)? {
+                Some(JobStatus::ZkProofFinished(proof))
+                    if self.get_job_program_id(&job_key)? == Some(member_program.id) =>
+                {
+                    proof
+                }
+                // Requesting the aggregation again proves it again, see `start_aggregation`
+                _ => {
+                    return Err(InclusionServiceError::InternalError(format!(
+                        "{job:?} has no proof of the current program to aggregate"
+                    )))
+                }
+            };
+            member_proofs.push(proof);
+        }
+
+        debug!("Requesting aggregation proof");
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&ZKStackAggregationInput {
+            member_vkey_hash: member_setup.vk.hash_u32(),
+            member_public_values: member_proofs
+                .iter()
+                .map(|proof| proof.public_values.to_vec())
+                .collect(),
+            batch_number: aggregation.batch_number,
+            chain_id: aggregation.chain_id,
+        });
+        for proof in member_proofs {
+            let SP1Proof::Compressed(proof) = proof.proof else {
+                return Err(InclusionServiceError::InternalError(
+                    "Member proof is not compressed".to_string(),
+                ));
+            };
+            stdin.write_proof(*proof, member_setup.vk.vk.clone());
+        }
+        let aggregation_setup = self
+            .get_proof_setup(
+                &self.programs.current(ProgramKind::Aggregation),
+                zk_client.clone(),
+            )
+            .await?;
+        let request_id = zk_client
+            .request_proof(
+                aggregation_setup,
+                stdin,
+                SP1ProofMode::Groth16,
+                self.config.zk_proof_gen_timeout,
+            )
+            .await
+            .map_err(|e| InclusionServiceError::ZkClientError(e.to_string()))?;
+        self.store_aggregation_status(
+            aggregation_key,
+            &AggregationStatus::ZkProofPending(request_id),
+        )?;
+        drop(saving);
+
+        self.wait_aggregation_proof(request_id).await
+    }
+
+    async fn wait_aggregation_proof(
+        &self,
+        request_id: SuccNetJobId,
+    ) -> Result<SP1ProofWithPublicValues, InclusionServiceError> {
+        debug!("Waiting for aggregation proof from prover");
+        self.get_zk_client()
+            .await
+            .wait_proof(request_id)
+            .await
+            .map_err(|e| InclusionServiceError::ZkClientError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::da::FixtureDaBackend;
+    use crate::internal::inclusion::build_zk_proof_input;
+    use celestia_types::block::Height as BlockHeight;
+    use celestia_types::nmt::Namespace;
+    use std::time::Duration;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_aggregation_with_idle_zk_queue() {
+        let namespace = Namespace::new_v0(&[7; 10]).unwrap();
+        let (backend, height, commitment) =
+            FixtureDaBackend::with_test_blob(namespace, b"Never gonna run around");
+        let (service, job_receivers) = InclusionService::for_test(Arc::new(backend));
+        let member = BlobId::new(
+            BlockHeight::try_from(height).unwrap(),
+            namespace,
+            commitment,
+            271,
+            1,
+        );
+        let aggregation = AggregationJob {
+            chain_id: 271,
+            batch_number: 1,
+            members: vec![member.clone()],
+        };
+        let aggregation_key = schema::encode(&aggregation).unwrap();
+
+        // As if restarted while awaiting the aggregation proof, a member's proof stands in
+        let da_client = service.get_da_client().await.unwrap();
+        let input = build_zk_proof_input(&member, da_client.as_ref(), &service.da_cache, |e| {
+            InclusionServiceError::DaClientError(e.to_string())
+        })
+        .await
+        .unwrap();
+        let job = Job::AggregationMember(member);
+        let program = service.programs.current(ProgramKind::ZkStackInclusion);
+        let request_id = service
+            .request_zk_proof(&program, &input, &job, &job.key().unwrap())
+            .await
+            .unwrap();
+        service
+            .store_aggregation_status(
+                &aggregation_key,
+                &AggregationStatus::ZkProofPending(request_id),
+            )
+            .unwrap();
+
+        // Every ZK stage worker waits on an empty queue
+        tokio::spawn(service.clone().job_worker(job_receivers));
+        tokio::spawn(service.clone().aggregation_worker());
+        let finished = tokio::time::timeout(Duration::from_secs(300), async {
+            while !matches!(
+                service.get_aggregation_status(&aggregation_key).unwrap(),
+                Some(AggregationStatus::ZkProofFinished(_))
+            ) {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await;
+        assert!(finished.is_ok(), "Aggregation kept waiting by idle workers");
+    }
+}
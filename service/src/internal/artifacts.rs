@@ -0,0 +1,209 @@
+use eq_common::InclusionServiceError;
+use futures::stream::{BoxStream, StreamExt, TryStreamExt};
+use object_store::aws::AmazonS3Builder;
+use object_store::local::LocalFileSystem;
+use object_store::path::Path as ObjectPath;
+use object_store::prefix::PrefixStore;
+use object_store::{ObjectStore, PutPayload};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// SHA3-256 hash of an artifact's content, that it is stored under
+pub type ArtifactId = [u8; 32];
+
+/// Unreferenced artifacts younger than this are kept by [ArtifactStore::remove_unreferenced],
+/// as they may be written for a [Job](crate::Job) that is not yet finalized
+pub const ARTIFACT_GC_GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// A reference to a proof in the [ArtifactStore], in place of the proof itself,
+/// see [JobStatus::ZkProofStored](crate::JobStatus::ZkProofStored)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoredProof {
+    /// Artifact of the [SP1ProofWithPublicValues::bytes](sp1_sdk::SP1ProofWithPublicValues::bytes)
+    pub proof_data: ArtifactId,
+    pub proof_data_len: u64,
+    /// Small enough to keep in the DB
+    pub public_values: Vec<u8>,
+}
+
+/// Content-addressed storage of large, immutable, [Job](crate::Job) outputs (proofs),
+/// so the DB only keeps a reference to them.
+///
+/// Backed by a local directory (`file:///path`) or an S3-compatible bucket (`s3://bucket/prefix`).
+/// An S3 store is configured with the usual `AWS_*` env vars, e.g. with
+/// `AWS_ENDPOINT=http://127.0.0.1:9000` and `AWS_ALLOW_HTTP=true` for a local MinIO.
+pub struct ArtifactStore {
+    store: Arc<dyn ObjectStore>,
+    url: String,
+}
+
+impl ArtifactStore {
+    pub fn open(url: &str) -> Result<Self, InclusionServiceError> {
+        let store: Arc<dyn ObjectStore> = if let Some(dir) = url.strip_prefix("file://") {
+            std::fs::create_dir_all(dir).map_err(internal_error)?;
+            Arc::new(LocalFileSystem::new_with_prefix(dir).map_err(internal_error)?)
+        } else if let Some(location) = url.strip_prefix("s3://") {
+            let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+            let s3 = AmazonS3Builder::from_env()
+                .with_bucket_name(bucket)
+                .build()
+                .map_err(internal_error)?;
+            match prefix.trim_matches('/') {
+                "" => Arc::new(s3),
+                prefix => Arc::new(PrefixStore::new(s3, prefix)),
+            }
+        } else {
+            return Err(InclusionServiceError::InvalidParameter(format!(
+                "Artifact store {url} must be a file:// or s3:// URL"
+            )));
+        };
+        Ok(ArtifactStore {
+            store,
+            url: url.to_string(),
+        })
+    }
+
+    /// Store an artifact, returning it's ID. Storing the same content again does nothing new.
+    pub async fn put(&self, data: Vec<u8>) -> Result<ArtifactId, InclusionServiceError> {
+        let id: ArtifactId = Sha3_256::digest(&data).into();
+        self.store
+            .put(&artifact_path(&id), PutPayload::from(data))
+            .await
+            .map_err(internal_error)?;
+        Ok(id)
+    }
+
+    /// The whole content of an artifact, checked against it's ID
+    pub async fn get(&self, id: &ArtifactId) -> Result<Vec<u8>, InclusionServiceError> {
+        let data = self
+            .store
+            .get(&artifact_path(id))
+            .await
+            .map_err(internal_error)?
+            .bytes()
+            .await
+            .map_err(internal_error)?
+            .to_vec();
+        let hash: ArtifactId = Sha3_256::digest(&data).into();
+        if hash != *id {
+            return Err(InclusionServiceError::InternalError(format!(
+                "Artifact {} is corrupt in {}",
+                hex::encode(id),
+                self.url
+            )));
+        }
+        Ok(data)
+    }
+
+    /// The content of an artifact as it is read from the store, in chunks
+    pub async fn stream(
+        &self,
+        id: &ArtifactId,
+    ) -> Result<BoxStream<'static, Result<Vec<u8>, InclusionServiceError>>, InclusionServiceError>
+    {
+        Ok(self
+            .store
+            .get(&artifact_path(id))
+            .await
+            .map_err(internal_error)?
+            .into_stream()
+            .map_ok(|chunk| chunk.to_vec())
+            .map_err(internal_error)
+            .boxed())
+    }
+
+    /// Remove every artifact not in `referenced`, other than those written in the last
+    /// [ARTIFACT_GC_GRACE]. Returns the number removed.
+    pub async fn remove_unreferenced(
+        &self,
+        referenced: &HashSet<ArtifactId>,
+        now_unix_ms: u64,
+    ) -> Result<usize, InclusionServiceError> {
+        let written_before = now_unix_ms.saturating_sub(ARTIFACT_GC_GRACE.as_millis() as u64);
+        let mut unreferenced = Vec::new();
+        let mut artifacts = self.store.list(None);
+        while let Some(meta) = artifacts.next().await {
+            let meta = meta.map_err(internal_error)?;
+            let id = meta
+                .location
+                .filename()
+                .and_then(|name| hex::decode(name).ok())
+                .and_then(|id| ArtifactId::try_from(id).ok());
+            // Anything else in the store is not ours to remove
+            let Some(id) = id else {
+                continue;
+            };
+            if !referenced.contains(&id)
+                && (meta.last_modified.timestamp_millis() as u64) < written_before
+            {
+                unreferenced.push(meta.location);
+            }
+        }
+        for location in &unreferenced {
+            self.store.delete(location).await.map_err(internal_error)?;
+        }
+        Ok(unreferenced.len())
+    }
+}
+
+fn artifact_path(id: &ArtifactId) -> ObjectPath {
+    ObjectPath::from(hex::encode(id))
+}
+
+fn internal_error(e: impl ToString) -> InclusionServiceError {
+    InclusionServiceError::InternalError(e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_artifacts() {
+        let dir = std::env::temp_dir().join(format!("eq-artifacts-{}", std::process::id()));
+        let artifacts = ArtifactStore::open(&format!("file://{}", dir.display())).unwrap();
+
+        let id = artifacts.put(b"proof".to_vec()).await.unwrap();
+        assert_eq!(artifacts.put(b"proof".to_vec()).await.unwrap(), id);
+        assert_eq!(artifacts.get(&id).await.unwrap(), b"proof");
+        let streamed: Vec<u8> = artifacts
+            .stream(&id)
+            .await
+            .unwrap()
+            .try_concat()
+            .await
+            .unwrap();
+        assert_eq!(streamed, b"proof");
+
+        // Recently written, so kept even if unreferenced
+        let now = crate::internal::retry::unix_ms_now();
+        assert_eq!(
+            artifacts
+                .remove_unreferenced(&HashSet::new(), now)
+                .await
+                .unwrap(),
+            0
+        );
+        let later = now + ARTIFACT_GC_GRACE.as_millis() as u64 + 1_000;
+        assert_eq!(
+            artifacts
+                .remove_unreferenced(&HashSet::from([id]), later)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            artifacts
+                .remove_unreferenced(&HashSet::new(), later)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(artifacts.get(&id).await.is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::internal::aggregation::AggregationJob;
+use crate::internal::config::TenantSettings;
+use crate::internal::retry::unix_ms_now;
+use crate::Job;
+
+use celestia_types::nmt::Namespace;
+use eq_common::InclusionServiceError;
+use hmac::{Hmac, Mac};
+use log::warn;
+use prost::Message;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::{Request, Status};
+
+/// Metadata of a bearer token credential, as `Bearer <token>`
+pub const AUTHORIZATION_HEADER: &str = "authorization";
+/// Metadata of an HMAC signed request: the ID of the key it's signed with
+pub const HMAC_KEY_ID_HEADER: &str = "x-eq-key-id";
+/// Metadata of an HMAC signed request: when it was signed, in unix seconds
+pub const HMAC_TIMESTAMP_HEADER: &str = "x-eq-timestamp";
+/// Metadata of an HMAC signed request: the hex signature, see [hmac_signature]
+pub const HMAC_SIGNATURE_HEADER: &str = "x-eq-signature";
+
+/// Signed requests are rejected if their timestamp is further than this from our clock,
+/// limiting how long a captured request can be replayed
+pub const HMAC_MAX_SKEW: Duration = Duration::from_secs(5 * 60);
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The DER TLS client certificate of an HTTP gateway request, as a request extension.
+/// Tonic's own `TlsConnectInfo` can only be made by it's server.
+#[derive(Debug, Clone)]
+pub struct PeerCertificate(pub Vec<u8>);
+
+/// A client of the service, allowed to request proofs for some chains and namespaces
+#[derive(Debug)]
+pub struct Tenant {
+    pub name: String,
+    /// Any chain if `None`
+    chain_ids: Option<HashSet<u64>>,
+    /// Any namespace if `None`, by [Namespace::as_bytes]
+    namespaces: Option<HashSet<Vec<u8>>>,
+}
+
+impl Tenant {
+    /// Reject a [Job] for anything but this tenant's chain IDs and namespaces
+    pub fn authorize_job(&self, job: &Job) -> Result<(), Status> {
+        match job {
+            Job::ZkStack(blob) | Job::AggregationMember(blob) => {
+                self.authorize_blob(Some(blob.l2_chain_id), &blob.namespace)
+            }
+            Job::ZkStackMultiBlob(multi_blob) => multi_blob
+                .blobs
+                .iter()
+                .try_for_each(|blob| self.authorize_blob(Some(blob.l2_chain_id), &blob.namespace)),
+            // Payy blobs have no chain ID, so need a tenant allowed any chain
+            Job::Payy(blob) => self.authorize_blob(None, &blob.namespace),
+        }
+    }
+
+    /// Reject an [AggregationJob] with any member not allowed by [Self::authorize_job]
+    pub fn authorize_aggregation(&self, aggregation: &AggregationJob) -> Result<(), Status> {
+        aggregation
+            .members
+            .iter()
+            .try_for_each(|member| self.authorize_blob(Some(member.l2_chain_id), &member.namespace))
+    }
+
+    fn authorize_blob(&self, chain_id: Option<u64>, namespace: &Namespace) -> Result<(), Status> {
+        if let Some(chain_ids) = &self.chain_ids {
+            if !chain_id.is_some_and(|chain_id| chain_ids.contains(&chain_id)) {
+                return Err(self.denied(format!("chain_id {}", chain_id.unwrap_or_default())));
+            }
+        }
+        if let Some(namespaces) = &self.namespaces {
+            if !namespaces.contains(namespace.as_bytes()) {
+                return Err(self.denied(format!(
+                    "namespace {}",
+                    hex::encode(namespace.id_v0().unwrap_or_default())
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn denied(&self, what: String) -> Status {
+        warn!("Tenant {} denied access to {what}", self.name);
+        Status::permission_denied(format!("Not allowed to request proofs for {what}"))
+    }
+}
+
+/// Maps the credentials of a gRPC request to the [Tenant] making it.
+///
+/// A request is authenticated by any one of:
+/// - a TLS client certificate, by the SHA-256 fingerprint of it's DER encoding,
+///   if the server verifies client certificates
+/// - a bearer token, in the [AUTHORIZATION_HEADER]
+/// - an HMAC-SHA256 signature of the request, see [hmac_signature]
+pub struct Authenticator {
+    /// By the SHA-256 of the token, so a lookup leaks no timing on the token itself
+    bearer_tokens: HashMap<[u8; 32], Arc<Tenant>>,
+    /// By key ID, with the secret key
+    hmac_keys: HashMap<String, (Vec<u8>, Arc<Tenant>)>,
+    /// By SHA-256 fingerprint
+    client_certs: HashMap<[u8; 32], Arc<Tenant>>,
+}
+
+impl Authenticator {
+    /// Every credential must be unique to one tenant
+    pub fn new(tenants: &[TenantSettings]) -> Result<Self, InclusionServiceError> {
+        let mut names = HashSet::new();
+        let mut auth = Authenticator {
+            bearer_tokens: HashMap::new(),
+            hmac_keys: HashMap::new(),
+            client_certs: HashMap::new(),
+        };
+        for settings in tenants {
+            let invalid = |e: String| {
+                InclusionServiceError::InvalidParameter(format!("Tenant {}: {e}", settings.name))
+            };
+            if !names.insert(&settings.name) {
+                return Err(invalid("name is not unique".to_string()));
+            }
+            let namespaces = settings
+                .namespaces
+                .as_ref()
+                .map(|namespaces| {
+                    namespaces
+                        .iter()
+                        .map(|namespace| {
+                            hex::decode(namespace)
+                                .ok()
+                                .and_then(|id| Namespace::new_v0(&id).ok())
+                                .map(|namespace| namespace.as_bytes().to_vec())
+                                .ok_or_else(|| {
+                                    invalid(format!(
+                                        "namespace {namespace} is not a hex v0 namespace"
+                                    ))
+                                })
+                        })
+                        .collect::<Result<_, _>>()
+                })
+                .transpose()?;
+            let tenant = Arc::new(Tenant {
+                name: settings.name.clone(),
+                chain_ids: settings
+                    .chain_ids
+                    .as_ref()
+                    .map(|chain_ids| chain_ids.iter().copied().collect()),
+                namespaces,
+            });
+
+            for token in &settings.bearer_tokens {
+                let digest = Sha256::digest(token.as_bytes()).into();
+                if auth.bearer_tokens.insert(digest, tenant.clone()).is_some() {
+                    return Err(invalid("bearer token is not unique".to_string()));
+                }
+            }
+            for key in &settings.hmac_keys {
+                if auth
+                    .hmac_keys
+                    .insert(
+                        key.id.clone(),
+                        (key.secret.as_bytes().to_vec(), tenant.clone()),
+                    )
+                    .is_some()
+                {
+                    return Err(invalid(format!("HMAC key ID {} is not unique", key.id)));
+                }
+            }
+            for fingerprint in &settings.client_cert_sha256 {
+                let digest = hex::decode(fingerprint.replace(':', ""))
+                    .ok()
+                    .and_then(|digest| <[u8; 32]>::try_from(digest).ok())
+                    .ok_or_else(|| {
+                        invalid(format!(
+                            "client certificate fingerprint {fingerprint} is not a hex SHA-256"
+                        ))
+                    })?;
+                if auth.client_certs.insert(digest, tenant.clone()).is_some() {
+                    return Err(invalid(format!(
+                        "client certificate {fingerprint} is not unique"
+                    )));
+                }
+            }
+        }
+        Ok(auth)
+    }
+
+    /// The [Tenant] making a request, or `UNAUTHENTICATED` if it has no valid credentials
+    pub fn authenticate<M: Message>(&self, request: &Request<M>) -> Result<Arc<Tenant>, Status> {
+        self.authenticate_at(request, unix_ms_now() / 1000)
+    }
+
+    fn authenticate_at<M: Message>(
+        &self,
+        request: &Request<M>,
+        now_unix_s: u64,
+    ) -> Result<Arc<Tenant>, Status> {
+        // A certificate we don't know may be trusted by the client CA for something else,
+        // so the request can still authenticate otherwise
+        let fingerprint = match request.peer_certs() {
+            Some(certs) => certs
+                .first()
+                .map(|leaf| <[u8; 32]>::from(Sha256::digest(leaf.get_ref()))),
+            None => request
+                .extensions()
+                .get::<PeerCertificate>()
+                .map(|leaf| Sha256::digest(&leaf.0).into()),
+        };
+        let cert_tenant =
+            fingerprint.and_then(|fingerprint| self.client_certs.get(&fingerprint).cloned());
+        if let Some(tenant) = cert_tenant {
+            return Ok(tenant);
+        }
+
+        let metadata = request.metadata();
+        if let Some(authorization) = metadata.get(AUTHORIZATION_HEADER) {
+            let token = authorization
+                .to_str()
+                .ok()
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .ok_or_else(|| Status::unauthenticated("Expected a Bearer token"))?;
+            let digest: [u8; 32] = Sha256::digest(token.trim().as_bytes()).into();
+            return self
+                .bearer_tokens
+                .get(&digest)
+                .cloned()
+                .ok_or_else(|| Status::unauthenticated("Unknown bearer token"));
+        }
+
+        if let Some(key_id) = metadata.get(HMAC_KEY_ID_HEADER) {
+            let header = |name: &str| {
+                metadata
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or_else(|| Status::unauthenticated(format!("Signed request has no {name}")))
+            };
+            let (key, tenant) = key_id
+                .to_str()
+                .ok()
+                .and_then(|key_id| self.hmac_keys.get(key_id))
+                .ok_or_else(|| Status::unauthenticated("Unknown HMAC key ID"))?;
+            let timestamp: u64 = header(HMAC_TIMESTAMP_HEADER)?
+                .parse()
+                .map_err(|_| Status::unauthenticated("Request timestamp must be unix seconds"))?;
+            if now_unix_s.abs_diff(timestamp) > HMAC_MAX_SKEW.as_secs() {
+                return Err(Status::unauthenticated(
+                    "Request timestamp is too far from the server's clock",
+                ));
+            }
+            let signature = hex::decode(header(HMAC_SIGNATURE_HEADER)?)
+                .map_err(|_| Status::unauthenticated("Request signature must be hex"))?;
+            let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any size");
+            mac.update(&signed_message(timestamp, request.get_ref()));
+            mac.verify_slice(&signature)
+                .map_err(|_| Status::unauthenticated("Invalid request signature"))?;
+            return Ok(tenant.clone());
+        }
+
+        Err(Status::unauthenticated(
+            "Credentials required: a client certificate, bearer token or signed request",
+        ))
+    }
+}
+
+/// The HMAC-SHA256, with a tenant's secret key, of the timestamp (unix seconds) as decimal,
+/// a newline, then the protobuf encoding of the request message.
+/// Sent as hex in the [HMAC_SIGNATURE_HEADER].
+pub fn hmac_signature(secret: &[u8], timestamp: u64, message: &impl Message) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC takes keys of any size");
+    mac.update(&signed_message(timestamp, message));
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn signed_message(timestamp: u64, message: &impl Message) -> Vec<u8> {
+    let mut signed = format!("{timestamp}\n").into_bytes();
+    message.encode(&mut signed).expect("Vec grows as needed");
+    signed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::config::HmacKeySettings;
+    use eq_common::eqs::GetZkStackRequest;
+    use eq_sdk::types::BlobId;
+
+    #[test]
+    fn test_tenant_credentials() {
+        let namespace = Namespace::new_v0(&[1; 10]).unwrap();
+        let auth = Authenticator::new(&[TenantSettings {
+            name: "rollup".to_string(),
+            chain_ids: Some(vec![7]),
+            namespaces: Some(vec![hex::encode([1; 10])]),
+            bearer_tokens: vec!["token".to_string()],
+            hmac_keys: vec![HmacKeySettings {
+                id: "key".to_string(),
+                secret: "secret".to_string(),
+            }],
+            client_cert_sha256: vec![hex::encode(Sha256::digest(b"cert"))],
+            limits: Default::default(),
+        }])
+        .unwrap();
+        let message = GetZkStackRequest {
+            height: 1,
+            namespace: [1; 10].to_vec(),
+            commitment: [0; 32].to_vec(),
+            chain_id: 7,
+            batch_number: 1,
+        };
+
+        let mut request = Request::new(message.clone());
+        assert!(auth.authenticate_at(&request, 1000).is_err());
+        request
+            .metadata_mut()
+            .insert(AUTHORIZATION_HEADER, "Bearer token".parse().unwrap());
+        let tenant = auth.authenticate_at(&request, 1000).unwrap();
+        assert_eq!(tenant.name, "rollup");
+        request
+            .metadata_mut()
+            .insert(AUTHORIZATION_HEADER, "Bearer guess".parse().unwrap());
+        assert!(auth.authenticate_at(&request, 1000).is_err());
+
+        let mut signed = Request::new(message.clone());
+        let signature = hex::encode(hmac_signature(b"secret", 1000, &message));
+        let metadata = signed.metadata_mut();
+        metadata.insert(HMAC_KEY_ID_HEADER, "key".parse().unwrap());
+        metadata.insert(HMAC_TIMESTAMP_HEADER, "1000".parse().unwrap());
+        metadata.insert(HMAC_SIGNATURE_HEADER, signature.parse().unwrap());
+        assert!(auth.authenticate_at(&signed, 1010).is_ok());
+        // Stale, or for another message
+        assert!(auth.authenticate_at(&signed, 2000).is_err());
+        signed.get_mut().chain_id = 8;
+        assert!(auth.authenticate_at(&signed, 1010).is_err());
+
+        // A client certificate of the HTTP gateway
+        let mut gateway = Request::new(message.clone());
+        gateway
+            .extensions_mut()
+            .insert(PeerCertificate(b"other".to_vec()));
+        assert!(auth.authenticate_at(&gateway, 1000).is_err());
+        gateway
+            .extensions_mut()
+            .insert(PeerCertificate(b"cert".to_vec()));
+        assert_eq!(auth.authenticate_at(&gateway, 1000).unwrap().name, "rollup");
+
+        let job = |chain_id, namespace| {
+            Job::ZkStack(BlobId::new(
+                1u64.try_into().unwrap(),
+                namespace,
+                celestia_types::blob::Commitment::new([0; 32].into()),
+                chain_id,
+                1,
+            ))
+        };
+        assert!(tenant.authorize_job(&job(7, namespace)).is_ok());
+        let denied = tenant.authorize_job(&job(8, namespace)).unwrap_err();
+        assert_eq!(denied.code(), tonic::Code::PermissionDenied);
+        let other_namespace = Namespace::new_v0(&[2; 10]).unwrap();
+        assert!(tenant.authorize_job(&job(7, other_namespace)).is_err());
+    }
+}
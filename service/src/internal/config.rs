@@ -0,0 +1,749 @@
+use crate::internal::artifacts::ArtifactStore;
+use crate::internal::auth::Authenticator;
+use crate::internal::drain::DEFAULT_SHUTDOWN_DEADLINE;
+use crate::internal::inclusion::InclusionServiceConfig;
+use crate::internal::limits::{LimitConfig, Limits};
+use crate::internal::prover::ZkProofMode;
+use crate::internal::retention::{RetentionConfig, RetentionPolicy};
+use crate::internal::retry::RetryPolicy;
+use crate::internal::store::StoreBackend;
+
+use eq_common::InclusionServiceError;
+use serde::{Deserialize, Deserializer};
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+
+/// Env vars that override a [ServiceConfig] setting, by it's key
+pub const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("CELESTIA_NODE_HTTP", "da.node_http"),
+    ("CELESTIA_NODE_AUTH_TOKEN", "da.node_auth_token"),
+    ("CELESTIA_FIXTURE_DIR", "da.fixture_dir"),
+    ("EQ_DA_CONCURRENCY", "da.concurrency"),
+    ("EQ_DA_CACHE_CAPACITY", "da.cache_capacity"),
+    ("ZK_PROOF_MODE", "zk.proof_mode"),
+    ("PROOF_GEN_TIMEOUT_SECONDS", "zk.proof_gen_timeout_seconds"),
+    ("EQ_ZK_CONCURRENCY", "zk.concurrency"),
+    ("EQ_PROGRAM_DIR", "zk.program_dir"),
+    ("EQ_SOCKET", "listen.grpc"),
+    ("EQ_ADMIN_SOCKET", "listen.admin"),
+    ("EQ_HTTP_SOCKET", "listen.http"),
+    ("EQ_PROMETHEUS_SOCKET", "listen.prometheus"),
+    ("EQ_TLS_CERT", "tls.cert"),
+    ("EQ_TLS_KEY", "tls.key"),
+    ("EQ_TLS_CLIENT_CA", "tls.client_ca"),
+    ("EQ_DB_PATH", "db.path"),
+    ("EQ_DB_BACKEND", "db.backend"),
+    ("EQ_ARTIFACT_STORE", "db.artifact_store"),
+    ("EQ_JOB_QUEUE_CAPACITY", "jobs.queue_capacity"),
+    (
+        "EQ_SHUTDOWN_DEADLINE_SECONDS",
+        "jobs.shutdown_deadline_seconds",
+    ),
+    ("EQ_RETRY_MAX_ATTEMPTS", "retry.max_attempts"),
+    ("EQ_RETRY_DA_DELAY_SECONDS", "retry.da_delay_seconds"),
+    ("EQ_RETRY_ZK_DELAY_SECONDS", "retry.zk_delay_seconds"),
+    ("EQ_RETRY_MAX_DELAY_SECONDS", "retry.max_delay_seconds"),
+    (
+        "EQ_RETAIN_PROOFS_MAX_AGE_SECONDS",
+        "retention.proofs.max_age_seconds",
+    ),
+    ("EQ_RETAIN_PROOFS_MAX_COUNT", "retention.proofs.max_count"),
+    ("EQ_RETAIN_PROOFS_MAX_BYTES", "retention.proofs.max_bytes"),
+    (
+        "EQ_RETAIN_FAILURES_MAX_AGE_SECONDS",
+        "retention.failures.max_age_seconds",
+    ),
+    (
+        "EQ_RETAIN_FAILURES_MAX_COUNT",
+        "retention.failures.max_count",
+    ),
+    (
+        "EQ_RETAIN_FAILURES_MAX_BYTES",
+        "retention.failures.max_bytes",
+    ),
+    ("EQ_COMPACT_PROOFS", "retention.compact_proofs"),
+    ("EQ_LIMIT_JOBS_PER_SECOND", "limits.jobs_per_second"),
+    ("EQ_LIMIT_BURST", "limits.burst"),
+    ("EQ_LIMIT_PROOFS_PER_DAY", "limits.proofs_per_day"),
+    ("EQ_LIMIT_PROOFS_PER_MONTH", "limits.proofs_per_month"),
+];
+
+/// Every setting of the service, see `example.toml`.
+///
+/// Settings are layered: defaults, then a TOML file, then [ENV_OVERRIDES],
+/// then `key=value` overrides (from the CLI), see [Self::load].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServiceConfig {
+    pub da: DaSettings,
+    pub zk: ZkSettings,
+    pub listen: ListenSettings,
+    pub tls: TlsSettings,
+    pub db: DbSettings,
+    pub jobs: JobSettings,
+    pub retry: RetrySettings,
+    pub retention: RetentionSettings,
+    pub auth: AuthSettings,
+    pub limits: LimitSettings,
+}
+
+/// Where blobs are collected from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaSettings {
+    /// Required, unless using fixtures
+    pub node_http: Option<String>,
+    /// Required, unless using fixtures
+    pub node_auth_token: Option<String>,
+    /// Serve DA data from `blob-tool` fixtures in this directory, rather than a Celestia node
+    pub fixture_dir: Option<PathBuf>,
+    /// Max jobs collecting DA data at once
+    pub concurrency: usize,
+    /// Max blobs with DA proofs cached, 0 to disable
+    pub cache_capacity: usize,
+}
+
+impl Default for DaSettings {
+    fn default() -> Self {
+        DaSettings {
+            node_http: None,
+            node_auth_token: None,
+            fixture_dir: None,
+            concurrency: 8,
+            cache_capacity: 64,
+        }
+    }
+}
+
+/// How proofs are generated
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ZkSettings {
+    #[serde(deserialize_with = "from_str")]
+    pub proof_mode: ZkProofMode,
+    /// Required
+    pub proof_gen_timeout_seconds: Option<u64>,
+    /// Max jobs requesting or awaiting ZK proofs at once
+    pub concurrency: usize,
+    /// Rolled out program ELFs, in place of those built into the service
+    pub program_dir: Option<PathBuf>,
+}
+
+impl Default for ZkSettings {
+    fn default() -> Self {
+        ZkSettings {
+            proof_mode: ZkProofMode::Network,
+            proof_gen_timeout_seconds: None,
+            concurrency: 16,
+            program_dir: None,
+        }
+    }
+}
+
+/// Sockets the service listens on
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListenSettings {
+    /// Required
+    pub grpc: Option<SocketAddr>,
+    /// The admin service is off unless set, as it has no access control of it's own
+    pub admin: Option<SocketAddr>,
+    /// The HTTP/JSON gateway is off unless set
+    pub http: Option<SocketAddr>,
+    /// Required
+    pub prometheus: Option<SocketAddr>,
+}
+
+/// TLS of the Inclusion gRPC service and HTTP gateway, plaintext if no certificate is set
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsSettings {
+    /// PEM certificate chain of the server
+    pub cert: Option<PathBuf>,
+    /// PEM private key of the server
+    pub key: Option<PathBuf>,
+    /// PEM CA certificates that client certificates are verified against.
+    /// If set, clients must have a certificate, unless `client_auth_optional`
+    pub client_ca: Option<PathBuf>,
+    pub client_auth_optional: bool,
+}
+
+/// Where jobs and their proofs are kept
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DbSettings {
+    /// Required
+    pub path: Option<PathBuf>,
+    #[serde(deserialize_with = "from_str")]
+    pub backend: StoreBackend,
+    /// Proofs are kept in the DB if unset, see [ArtifactStore]
+    pub artifact_store: Option<String>,
+}
+
+impl Default for DbSettings {
+    fn default() -> Self {
+        DbSettings {
+            path: None,
+            backend: StoreBackend::Sled,
+            artifact_store: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobSettings {
+    /// Max jobs waiting in each stage's queue
+    pub queue_capacity: usize,
+    /// How long a shutdown waits on in-flight jobs to save their progress
+    pub shutdown_deadline_seconds: u64,
+}
+
+impl Default for JobSettings {
+    fn default() -> Self {
+        JobSettings {
+            queue_capacity: 256,
+            shutdown_deadline_seconds: DEFAULT_SHUTDOWN_DEADLINE.as_secs(),
+        }
+    }
+}
+
+/// See [RetryPolicy]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetrySettings {
+    pub max_attempts: u32,
+    pub da_delay_seconds: u64,
+    pub zk_delay_seconds: u64,
+    pub max_delay_seconds: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        let policy = RetryPolicy::default();
+        RetrySettings {
+            max_attempts: policy.max_attempts,
+            da_delay_seconds: policy.da_base_delay.as_secs(),
+            zk_delay_seconds: policy.zk_base_delay.as_secs(),
+            max_delay_seconds: policy.max_delay.as_secs(),
+        }
+    }
+}
+
+/// See [RetentionConfig]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionSettings {
+    pub proofs: RetentionPolicySettings,
+    pub failures: RetentionPolicySettings,
+    pub compact_proofs: bool,
+}
+
+/// See [RetentionPolicy], unset for no limit
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionPolicySettings {
+    pub max_age_seconds: Option<u64>,
+    pub max_count: Option<usize>,
+    pub max_bytes: Option<u64>,
+}
+
+/// Who may use the Inclusion gRPC service, see [Authenticator]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSettings {
+    /// Anyone may use the service if empty
+    pub tenants: Vec<TenantSettings>,
+}
+
+/// A client of the service, with it's credentials and what it may request proofs for
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantSettings {
+    pub name: String,
+    /// Allowed `l2_chain_id`s, any if unset
+    pub chain_ids: Option<Vec<u64>>,
+    /// Allowed hex v0 namespace IDs, as sent in requests, any if unset
+    pub namespaces: Option<Vec<String>>,
+    #[serde(default)]
+    pub bearer_tokens: Vec<String>,
+    #[serde(default)]
+    pub hmac_keys: Vec<HmacKeySettings>,
+    /// Hex SHA-256 fingerprints of TLS client certificates
+    #[serde(default)]
+    pub client_cert_sha256: Vec<String>,
+    /// Any unset are taken from the `[limits]` section
+    #[serde(default)]
+    pub limits: LimitSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HmacKeySettings {
+    pub id: String,
+    pub secret: String,
+}
+
+/// See [Limits], unset for no limit
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitSettings {
+    pub jobs_per_second: Option<f64>,
+    /// Defaults to `jobs_per_second`, rounded up
+    pub burst: Option<u32>,
+    pub proofs_per_day: Option<u64>,
+    pub proofs_per_month: Option<u64>,
+}
+
+impl ServiceConfig {
+    /// Defaults, overridden by the TOML file at `path` (if any), then by the [ENV_OVERRIDES]
+    /// that are set, then by `overrides` of the form `key=value` (e.g. `zk.concurrency=4`).
+    pub fn load(path: Option<&Path>, overrides: &[String]) -> Result<Self, InclusionServiceError> {
+        let mut config = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(path).map_err(|e| {
+                    InclusionServiceError::InvalidParameter(format!(
+                        "Can't read config file {}: {e}",
+                        path.display()
+                    ))
+                })?;
+                toml::from_str(&text).map_err(|e| {
+                    InclusionServiceError::InvalidParameter(format!(
+                        "Config file {}: {e}",
+                        path.display()
+                    ))
+                })?
+            }
+            None => ServiceConfig::default(),
+        };
+        for (var, key) in ENV_OVERRIDES {
+            // Set but empty is treated as unset, as with docker's `--env-file`
+            if let Some(value) = std::env::var(var).ok().filter(|v| !v.is_empty()) {
+                config.set(key, &value).map_err(|e| {
+                    InclusionServiceError::InvalidParameter(format!("{var} env var: {e}"))
+                })?;
+            }
+        }
+        for key_value in overrides {
+            let (key, value) = key_value.split_once('=').ok_or_else(|| {
+                InclusionServiceError::InvalidParameter(format!(
+                    "Override '{key_value}' must be of the form key=value"
+                ))
+            })?;
+            config.set(key.trim(), value.trim())?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Set one setting by it's key, as in the TOML file (e.g. `retry.max_attempts`)
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), InclusionServiceError> {
+        match key {
+            "da.node_http" => self.da.node_http = Some(value.to_string()),
+            "da.node_auth_token" => self.da.node_auth_token = Some(value.to_string()),
+            "da.fixture_dir" => self.da.fixture_dir = Some(value.into()),
+            "da.concurrency" => self.da.concurrency = parse(key, value)?,
+            "da.cache_capacity" => self.da.cache_capacity = parse(key, value)?,
+            "zk.proof_mode" => self.zk.proof_mode = parse(key, value)?,
+            "zk.proof_gen_timeout_seconds" => {
+                self.zk.proof_gen_timeout_seconds = Some(parse(key, value)?)
+            }
+            "zk.concurrency" => self.zk.concurrency = parse(key, value)?,
+            "zk.program_dir" => self.zk.program_dir = Some(value.into()),
+            "listen.grpc" => self.listen.grpc = Some(parse(key, value)?),
+            "listen.admin" => self.listen.admin = Some(parse(key, value)?),
+            "listen.http" => self.listen.http = Some(parse(key, value)?),
+            "listen.prometheus" => self.listen.prometheus = Some(parse(key, value)?),
+            "tls.cert" => self.tls.cert = Some(value.into()),
+            "tls.key" => self.tls.key = Some(value.into()),
+            "tls.client_ca" => self.tls.client_ca = Some(value.into()),
+            "tls.client_auth_optional" => self.tls.client_auth_optional = parse(key, value)?,
+            "db.path" => self.db.path = Some(value.into()),
+            "db.backend" => self.db.backend = parse(key, value)?,
+            "db.artifact_store" => self.db.artifact_store = Some(value.to_string()),
+            "jobs.queue_capacity" => self.jobs.queue_capacity = parse(key, value)?,
+            "jobs.shutdown_deadline_seconds" => {
+                self.jobs.shutdown_deadline_seconds = parse(key, value)?
+            }
+            "retry.max_attempts" => self.retry.max_attempts = parse(key, value)?,
+            "retry.da_delay_seconds" => self.retry.da_delay_seconds = parse(key, value)?,
+            "retry.zk_delay_seconds" => self.retry.zk_delay_seconds = parse(key, value)?,
+            "retry.max_delay_seconds" => self.retry.max_delay_seconds = parse(key, value)?,
+            "retention.compact_proofs" => self.retention.compact_proofs = parse(key, value)?,
+            "limits.jobs_per_second" => self.limits.jobs_per_second = Some(parse(key, value)?),
+            "limits.burst" => self.limits.burst = Some(parse(key, value)?),
+            "limits.proofs_per_day" => self.limits.proofs_per_day = Some(parse(key, value)?),
+            "limits.proofs_per_month" => self.limits.proofs_per_month = Some(parse(key, value)?),
+            _ => match key
+                .strip_prefix("retention.")
+                .and_then(|k| k.split_once('.'))
+            {
+                Some(("proofs", field)) => self.retention.proofs.set(key, field, value)?,
+                Some(("failures", field)) => self.retention.failures.set(key, field, value)?,
+                _ => return Err(unknown_setting(key)),
+            },
+        }
+        Ok(())
+    }
+
+    /// Check for missing or nonsensical settings, reporting all of them at once
+    pub fn validate(&self) -> Result<(), InclusionServiceError> {
+        let mut problems = Vec::new();
+        if self.da.fixture_dir.is_none() {
+            if self.da.node_http.is_none() {
+                problems.push("da.node_http is required, unless da.fixture_dir is set".to_string());
+            }
+            if self.da.node_auth_token.is_none() {
+                problems.push(
+                    "da.node_auth_token is required, unless da.fixture_dir is set".to_string(),
+                );
+            }
+        }
+        if !self.zk.proof_gen_timeout_seconds.is_some_and(|t| t > 0) {
+            problems.push("zk.proof_gen_timeout_seconds is required, and more than 0".to_string());
+        }
+        if self.zk.proof_mode == ZkProofMode::Network
+            && std::env::var("NETWORK_PRIVATE_KEY").is_err()
+        {
+            problems.push(
+                "NETWORK_PRIVATE_KEY env var is required for zk.proof_mode = network".to_string(),
+            );
+        }
+        for (key, value) in [
+            ("da.concurrency", self.da.concurrency),
+            ("zk.concurrency", self.zk.concurrency),
+            ("jobs.queue_capacity", self.jobs.queue_capacity),
+        ] {
+            if value == 0 {
+                problems.push(format!("{key} must be more than 0"));
+            }
+        }
+        for (key, value) in [
+            ("listen.grpc", self.listen.grpc.is_some()),
+            ("listen.prometheus", self.listen.prometheus.is_some()),
+            ("db.path", self.db.path.is_some()),
+        ] {
+            if !value {
+                problems.push(format!("{key} is required"));
+            }
+        }
+        if self.tls.cert.is_some() != self.tls.key.is_some() {
+            problems.push("tls.cert and tls.key must be set together".to_string());
+        }
+        if self.tls.client_ca.is_some() && self.tls.cert.is_none() {
+            problems.push("tls.client_ca requires tls.cert and tls.key".to_string());
+        }
+        // Tenant credentials would be sent in plaintext
+        if self.listen.http.is_some() && !self.auth.tenants.is_empty() && self.tls.cert.is_none() {
+            problems
+                .push("listen.http with auth.tenants requires tls.cert and tls.key".to_string());
+        }
+        if let Err(e) = Authenticator::new(&self.auth.tenants) {
+            problems.push(format!("auth.tenants: {e}"));
+        }
+        let tenant_limits = self
+            .auth
+            .tenants
+            .iter()
+            .map(|tenant| (format!("auth.tenants {}", tenant.name), &tenant.limits));
+        for (section, limits) in [("limits".to_string(), &self.limits)]
+            .into_iter()
+            .chain(tenant_limits)
+        {
+            if limits
+                .jobs_per_second
+                .is_some_and(|rate| !(rate.is_finite() && rate > 0.0))
+            {
+                problems.push(format!("{section} jobs_per_second must be more than 0"));
+            }
+        }
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(InclusionServiceError::InvalidParameter(format!(
+                "Invalid config:\n  {}",
+                problems.join("\n  ")
+            ))),
+        }
+    }
+
+    pub fn zk_proof_gen_timeout(&self) -> Duration {
+        Duration::from_secs(self.zk.proof_gen_timeout_seconds.unwrap_or_default())
+    }
+
+    /// TLS of the Inclusion gRPC service, reading it's certificates. `None` for plaintext.
+    pub fn grpc_tls_config(&self) -> Result<Option<ServerTlsConfig>, InclusionServiceError> {
+        let (Some(cert), Some(key)) = (&self.tls.cert, &self.tls.key) else {
+            return Ok(None);
+        };
+        let mut tls =
+            ServerTlsConfig::new().identity(Identity::from_pem(read_pem(cert)?, read_pem(key)?));
+        if let Some(client_ca) = &self.tls.client_ca {
+            tls = tls
+                .client_ca_root(Certificate::from_pem(read_pem(client_ca)?))
+                .client_auth_optional(self.tls.client_auth_optional);
+        }
+        Ok(Some(tls))
+    }
+
+    /// TLS of the HTTP gateway, the same as [Self::grpc_tls_config]. `None` for plaintext.
+    pub fn rest_tls_config(&self) -> Result<Option<TlsAcceptor>, InclusionServiceError> {
+        let (Some(cert), Some(key)) = (&self.tls.cert, &self.tls.key) else {
+            return Ok(None);
+        };
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| invalid_tls(e.to_string()))?;
+        let builder = match &self.tls.client_ca {
+            None => builder.with_no_client_auth(),
+            Some(client_ca) => {
+                let mut roots = RootCertStore::empty();
+                for ca in read_certs(client_ca)? {
+                    roots
+                        .add(ca)
+                        .map_err(|e| invalid_tls(format!("{}: {e}", client_ca.display())))?;
+                }
+                let mut verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+                if self.tls.client_auth_optional {
+                    verifier = verifier.allow_unauthenticated();
+                }
+                builder.with_client_cert_verifier(
+                    verifier.build().map_err(|e| invalid_tls(e.to_string()))?,
+                )
+            }
+        };
+        let mut tls = builder
+            .with_single_cert(read_certs(cert)?, read_key(key)?)
+            .map_err(|e| invalid_tls(e.to_string()))?;
+        tls.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Some(TlsAcceptor::from(Arc::new(tls))))
+    }
+
+    /// The config of the [InclusionService](crate::InclusionService), opening the artifact store
+    pub fn inclusion_config(&self) -> Result<InclusionServiceConfig, InclusionServiceError> {
+        let default_retry = RetryPolicy::default();
+        Ok(InclusionServiceConfig {
+            da_node_token: self.da.node_auth_token.clone().unwrap_or_default(),
+            da_node_http: self.da.node_http.clone().unwrap_or_default(),
+            da_fixture_dir: self.da.fixture_dir.clone(),
+            zk_proof_gen_timeout: self.zk_proof_gen_timeout(),
+            zk_proof_mode: self.zk.proof_mode,
+            da_concurrency: self.da.concurrency,
+            da_cache_capacity: self.da.cache_capacity,
+            zk_concurrency: self.zk.concurrency,
+            retry_policy: RetryPolicy {
+                max_attempts: self.retry.max_attempts,
+                da_base_delay: Duration::from_secs(self.retry.da_delay_seconds),
+                zk_base_delay: Duration::from_secs(self.retry.zk_delay_seconds),
+                max_delay: Duration::from_secs(self.retry.max_delay_seconds),
+                ..default_retry
+            },
+            retention: RetentionConfig {
+                proofs: self.retention.proofs.policy(),
+                failures: self.retention.failures.policy(),
+                compact_proofs: self.retention.compact_proofs,
+            },
+            artifact_store: self
+                .db
+                .artifact_store
+                .as_deref()
+                .map(ArtifactStore::open)
+                .transpose()?,
+            shutdown_deadline: Duration::from_secs(self.jobs.shutdown_deadline_seconds),
+            limits: LimitConfig {
+                default: self.limits.limits(),
+                tenants: self
+                    .auth
+                    .tenants
+                    .iter()
+                    .map(|tenant| (tenant.name.clone(), tenant.limits.or(&self.limits).limits()))
+                    .collect(),
+            },
+            auth: match self.auth.tenants.is_empty() {
+                true => None,
+                false => Some(Authenticator::new(&self.auth.tenants)?),
+            },
+        })
+    }
+}
+
+impl RetentionPolicySettings {
+    fn set(&mut self, key: &str, field: &str, value: &str) -> Result<(), InclusionServiceError> {
+        match field {
+            "max_age_seconds" => self.max_age_seconds = Some(parse(key, value)?),
+            "max_count" => self.max_count = Some(parse(key, value)?),
+            "max_bytes" => self.max_bytes = Some(parse(key, value)?),
+            _ => return Err(unknown_setting(key)),
+        }
+        Ok(())
+    }
+
+    fn policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            max_age: self.max_age_seconds.map(Duration::from_secs),
+            max_count: self.max_count,
+            max_bytes: self.max_bytes,
+        }
+    }
+}
+
+impl LimitSettings {
+    /// These limits, with any unset taken from `defaults`
+    fn or(&self, defaults: &LimitSettings) -> LimitSettings {
+        LimitSettings {
+            jobs_per_second: self.jobs_per_second.or(defaults.jobs_per_second),
+            burst: self.burst.or(defaults.burst),
+            proofs_per_day: self.proofs_per_day.or(defaults.proofs_per_day),
+            proofs_per_month: self.proofs_per_month.or(defaults.proofs_per_month),
+        }
+    }
+
+    fn limits(&self) -> Limits {
+        Limits {
+            jobs_per_second: self.jobs_per_second,
+            burst: self
+                .burst
+                .or(self.jobs_per_second.map(|rate| rate.ceil() as u32))
+                .unwrap_or(1),
+            proofs_per_day: self.proofs_per_day,
+            proofs_per_month: self.proofs_per_month,
+        }
+    }
+}
+
+fn read_pem(path: &Path) -> Result<Vec<u8>, InclusionServiceError> {
+    std::fs::read(path).map_err(|e| {
+        InclusionServiceError::InvalidParameter(format!("Can't read {}: {e}", path.display()))
+    })
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, InclusionServiceError> {
+    rustls_pemfile::certs(&mut read_pem(path)?.as_slice())
+        .collect::<Result<_, _>>()
+        .map_err(|e| invalid_tls(format!("{}: {e}", path.display())))
+}
+
+fn read_key(path: &Path) -> Result<PrivateKeyDer<'static>, InclusionServiceError> {
+    rustls_pemfile::private_key(&mut read_pem(path)?.as_slice())
+        .map_err(|e| invalid_tls(format!("{}: {e}", path.display())))?
+        .ok_or_else(|| invalid_tls(format!("{}: no private key", path.display())))
+}
+
+fn invalid_tls(e: String) -> InclusionServiceError {
+    InclusionServiceError::InvalidParameter(format!("Invalid TLS config: {e}"))
+}
+
+fn parse<T>(key: &str, value: &str) -> Result<T, InclusionServiceError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value.parse().map_err(|e| {
+        InclusionServiceError::InvalidParameter(format!("{key} = '{value}' is invalid: {e}"))
+    })
+}
+
+fn unknown_setting(key: &str) -> InclusionServiceError {
+    InclusionServiceError::InvalidParameter(format!("Unknown setting {key}"))
+}
+
+/// Deserialize a setting with it's [FromStr], as used for overrides
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_layered_config() {
+        let mut config: ServiceConfig = toml::from_str(
+            r#"
+            [da]
+            fixture_dir = "./fixtures"
+            concurrency = 2
+
+            [zk]
+            proof_mode = "mock"
+            proof_gen_timeout_seconds = 600
+
+            [listen]
+            grpc = "127.0.0.1:50051"
+            prometheus = "0.0.0.0:9091"
+
+            [db]
+            path = "/tmp/db-eq-service-testing"
+            backend = "sqlite"
+
+            [retention.proofs]
+            max_count = 10
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.da.concurrency, 2);
+        assert_eq!(config.zk.concurrency, 16);
+        assert_eq!(config.db.backend, StoreBackend::Sqlite);
+
+        config.set("zk.concurrency", "4").unwrap();
+        config
+            .set("retention.failures.max_age_seconds", "60")
+            .unwrap();
+        let inclusion_config = config.inclusion_config().unwrap();
+        assert_eq!(inclusion_config.zk_concurrency, 4);
+        assert_eq!(inclusion_config.retention.proofs.max_count, Some(10));
+        assert_eq!(
+            inclusion_config.retention.failures.max_age,
+            Some(Duration::from_secs(60))
+        );
+
+        assert!(config.set("zk.concurrency", "many").is_err());
+        assert!(config.set("retention.everything.max_count", "1").is_err());
+        assert!(config.set("da.nodes", "1").is_err());
+        assert!(toml::from_str::<ServiceConfig>("[da]\nnodes = 1").is_err());
+
+        // Every problem is reported
+        config.zk.concurrency = 0;
+        config.listen.grpc = None;
+        let e = config.validate().unwrap_err().to_string();
+        assert!(e.contains("zk.concurrency") && e.contains("listen.grpc"));
+
+        // Tenant credentials aren't accepted over a plaintext HTTP gateway
+        let mut config: ServiceConfig = toml::from_str(
+            r#"
+            [listen]
+            http = "127.0.0.1:8080"
+
+            [[auth.tenants]]
+            name = "rollup"
+            bearer_tokens = ["secret"]
+            "#,
+        )
+        .unwrap();
+        let e = config.validate().unwrap_err().to_string();
+        assert!(e.contains("listen.http with auth.tenants"));
+        config.tls.cert = Some("server.pem".into());
+        config.tls.key = Some("server.key".into());
+        let e = config.validate().unwrap_err().to_string();
+        assert!(!e.contains("listen.http with auth.tenants"));
+    }
+}
@@ -0,0 +1,320 @@
+use async_trait::async_trait;
+use celestia_rpc::{BlobClient, Client as CelestiaJSONClient, HeaderClient, ShareClient};
+use celestia_types::{blob::Commitment, nmt::Namespace, Blob, ExtendedHeader, ShareProof};
+use eq_common::fixtures::FixtureFile;
+use eq_common::InclusionServiceError;
+use jsonrpsee::core::ClientError as JsonRpcError;
+use jsonrpsee::types::ErrorObjectOwned;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The Celestia node calls needed to build a [ZKStackEqProofInput](eq_common::ZKStackEqProofInput).
+///
+/// Errors are [jsonrpsee] errors, as returned by a real node,
+/// so that all backends are handled the same way by the service.
+#[async_trait]
+pub trait DaBackend: Send + Sync {
+    /// Height of the newest header the node has, to check it is reachable
+    async fn head_height(&self) -> Result<u64, JsonRpcError>;
+
+    async fn header_get_by_height(&self, height: u64) -> Result<ExtendedHeader, JsonRpcError>;
+
+    async fn blob_get(
+        &self,
+        height: u64,
+        namespace: Namespace,
+        commitment: Commitment,
+    ) -> Result<Blob, JsonRpcError>;
+
+    /// Proof for ODS share indexes `[start, end)`
+    async fn share_get_range(
+        &self,
+        header: &ExtendedHeader,
+        start: u64,
+        end: u64,
+    ) -> Result<ShareProof, JsonRpcError>;
+}
+
+#[async_trait]
+impl DaBackend for CelestiaJSONClient {
+    async fn head_height(&self) -> Result<u64, JsonRpcError> {
+        Ok(HeaderClient::header_local_head(self)
+            .await?
+            .height()
+            .value())
+    }
+
+    async fn header_get_by_height(&self, height: u64) -> Result<ExtendedHeader, JsonRpcError> {
+        HeaderClient::header_get_by_height(self, height).await
+    }
+
+    async fn blob_get(
+        &self,
+        height: u64,
+        namespace: Namespace,
+        commitment: Commitment,
+    ) -> Result<Blob, JsonRpcError> {
+        BlobClient::blob_get(self, height, namespace, commitment).await
+    }
+
+    async fn share_get_range(
+        &self,
+        header: &ExtendedHeader,
+        start: u64,
+        end: u64,
+    ) -> Result<ShareProof, JsonRpcError> {
+        let range_response = ShareClient::share_get_range(self, header, start, end).await?;
+        Ok(range_response.proof)
+    }
+}
+
+/// An in-memory stand-in for a Celestia node, serving saved responses.
+///
+/// Missing data is reported with the same error messages a node would use,
+/// see [FixtureDaBackend::not_found].
+#[derive(Default)]
+pub struct FixtureDaBackend {
+    headers: HashMap<u64, ExtendedHeader>,
+    blobs: HashMap<(u64, [u8; 32]), Blob>,
+    ranges: HashMap<(u64, u64, u64), ShareProof>,
+}
+
+impl FixtureDaBackend {
+    /// Load all fixtures `blob-tool --fixture-dir` wrote into `dir`.
+    /// See [eq_common::fixtures] for the layout.
+    pub fn from_dir(dir: &Path) -> Result<Self, InclusionServiceError> {
+        let mut backend = Self::default();
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?
+                .path();
+            let Some(fixture) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(FixtureFile::from_file_name)
+            else {
+                warn!("Skipping unknown DA fixture file {path:?}");
+                continue;
+            };
+            debug!("Loading DA fixture {fixture:?}");
+            let json = std::fs::read_to_string(&path)
+                .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
+            let parse_err = |e: serde_json::Error| {
+                InclusionServiceError::InternalError(format!("{path:?}: {e}"))
+            };
+            match fixture {
+                FixtureFile::Header { height } => {
+                    backend.insert_header(height, serde_json::from_str(&json).map_err(parse_err)?)
+                }
+                FixtureFile::Blob { height, .. } => {
+                    backend.insert_blob(height, serde_json::from_str(&json).map_err(parse_err)?)
+                }
+                FixtureFile::Range { height, start, end } => backend.insert_range(
+                    height,
+                    start,
+                    end,
+                    serde_json::from_str(&json).map_err(parse_err)?,
+                ),
+            }
+        }
+        Ok(backend)
+    }
+
+    pub fn insert_header(&mut self, height: u64, header: ExtendedHeader) {
+        self.headers.insert(height, header);
+    }
+
+    pub fn insert_blob(&mut self, height: u64, blob: Blob) {
+        self.blobs.insert((height, *blob.commitment.hash()), blob);
+    }
+
+    pub fn insert_range(&mut self, height: u64, start: u64, end: u64, proof: ShareProof) {
+        self.ranges.insert((height, start, end), proof);
+    }
+
+    /// The error a Celestia node responds with when it lacks the data
+    fn not_found(message: &str) -> JsonRpcError {
+        JsonRpcError::Call(ErrorObjectOwned::owned(1, message, None::<()>))
+    }
+}
+
+#[async_trait]
+impl DaBackend for FixtureDaBackend {
+    async fn head_height(&self) -> Result<u64, JsonRpcError> {
+        self.headers
+            .keys()
+            .max()
+            .copied()
+            .ok_or_else(|| Self::not_found("header: not found"))
+    }
+
+    async fn header_get_by_height(&self, height: u64) -> Result<ExtendedHeader, JsonRpcError> {
+        self.headers
+            .get(&height)
+            .cloned()
+            .ok_or_else(|| Self::not_found("header: not found"))
+    }
+
+    async fn blob_get(
+        &self,
+        height: u64,
+        namespace: Namespace,
+        commitment: Commitment,
+    ) -> Result<Blob, JsonRpcError> {
+        self.blobs
+            .get(&(height, *commitment.hash()))
+            .filter(|blob| blob.namespace == namespace)
+            .cloned()
+            .ok_or_else(|| Self::not_found("blob: not found"))
+    }
+
+    async fn share_get_range(
+        &self,
+        header: &ExtendedHeader,
+        start: u64,
+        end: u64,
+    ) -> Result<ShareProof, JsonRpcError> {
+        self.ranges
+            .get(&(header.height().value(), start, end))
+            .cloned()
+            .ok_or_else(|| Self::not_found("share: range not found"))
+    }
+}
+
+#[cfg(test)]
+impl FixtureDaBackend {
+    /// Fixtures of a single blob, alone in a 1x1 data square, with proofs generated from
+    /// the square as a node would. Returns the blob's height and commitment.
+    pub(crate) fn with_test_blob(namespace: Namespace, data: &[u8]) -> (Self, u64, Commitment) {
+        use celestia_types::nmt::NamespaceProof;
+        use celestia_types::test_utils::ExtendedHeaderGenerator;
+        use celestia_types::{AppVersion, DataAvailabilityHeader, ExtendedDataSquare};
+        use celestia_types::{MerkleProof, RowProof};
+        use nmt_rs::nmt_proof::NamespaceProof as NmtNamespaceProof;
+
+        let mut blob = Blob::new(namespace, data.to_vec(), AppVersion::V5).unwrap();
+        blob.index = Some(0);
+        let shares = blob.to_shares().unwrap();
+        assert_eq!(shares.len(), 1, "Test blob must fit in a single share");
+        let share = shares[0].as_ref().to_vec();
+
+        let eds = ExtendedDataSquare::from_ods(vec![share.clone()], AppVersion::V5).unwrap();
+        let dah = DataAvailabilityHeader::from_eds(&eds);
+        let share_proof: NamespaceProof = NmtNamespaceProof::PresenceProof {
+            proof: eds.row_nmt(0).unwrap().build_range_proof(0..1),
+            ignore_max_ns: true,
+        }
+        .into();
+        // The data root is the merkle root of every row root, then every column root
+        let roots: Vec<_> = dah
+            .row_roots()
+            .iter()
+            .chain(dah.column_roots())
+            .map(|root| root.to_array())
+            .collect();
+        let (row_merkle_proof, _) = MerkleProof::new(0, &roots).unwrap();
+        let range_proof = ShareProof {
+            data: vec![share.try_into().unwrap()],
+            namespace_id: namespace,
+            share_proofs: vec![share_proof],
+            row_proof: RowProof {
+                row_roots: vec![dah.row_roots()[0].clone()],
+                proofs: vec![row_merkle_proof],
+                start_row: 0,
+                end_row: 0,
+            },
+        };
+        range_proof.verify(dah.hash()).unwrap();
+
+        let mut header = ExtendedHeaderGenerator::new().next();
+        header.dah = dah;
+        let height = header.height().value();
+        let commitment = blob.commitment;
+        let mut backend = Self::default();
+        backend.insert_header(height, header);
+        backend.insert_blob(height, blob);
+        backend.insert_range(height, 0, 1, range_proof);
+        (backend, height, commitment)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::schema;
+    use crate::internal::store::StoreTree;
+    use crate::{InclusionService, Job, JobStatus};
+    use celestia_types::block::Height as BlockHeight;
+    use eq_sdk::types::BlobId;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_fixture_blob_proven() {
+        let namespace = Namespace::new_v0(&[7; 10]).unwrap();
+        let (backend, height, commitment) =
+            FixtureDaBackend::with_test_blob(namespace, b"Never gonna give you up");
+        let (service, _job_receivers) = InclusionService::for_test(Arc::new(backend));
+        let job = Job::ZkStack(BlobId::new(
+            BlockHeight::try_from(height).unwrap(),
+            namespace,
+            commitment,
+            271,
+            1,
+        ));
+        let job_key = job.key().unwrap();
+        let stored = |tree| {
+            service
+                .store
+                .get(tree, &job_key)
+                .unwrap()
+                .map(|data| schema::decode::<JobStatus>(&data).unwrap())
+        };
+
+        service
+            .try_send_job_with_new_status(
+                job_key.clone(),
+                JobStatus::DataAvailabilityPending,
+                job.clone(),
+            )
+            .unwrap();
+
+        // Each call progresses the job through one stage, as a stage worker would
+        service.prove(job.clone()).await.unwrap();
+        match stored(StoreTree::Queue) {
+            Some(JobStatus::DataAvailable(input)) => {
+                assert_eq!(input.data, b"Never gonna give you up");
+                assert_eq!((input.chain_id, input.batch_number), (271, 1));
+            }
+            other => panic!("Expected DataAvailable, got {other:?}"),
+        }
+        service.prove(job).await.unwrap();
+        assert!(stored(StoreTree::Queue).is_none());
+        match stored(StoreTree::Finished) {
+            Some(JobStatus::ZkProofFinished(_)) => {}
+            other => panic!("Expected ZkProofFinished, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fixture_missing_data_errors() {
+        let backend = FixtureDaBackend::default();
+        match backend.header_get_by_height(1).await {
+            Err(JsonRpcError::Call(e)) => assert!(e.message().starts_with("header: not found")),
+            _ => panic!("Expected a node-like call error"),
+        }
+        let blob = backend
+            .blob_get(
+                1,
+                Namespace::new_v0(&[1; 10]).unwrap(),
+                Commitment::new([0; 32].into()),
+            )
+            .await;
+        match blob {
+            Err(JsonRpcError::Call(e)) => assert!(e.message().starts_with("blob: not found")),
+            _ => panic!("Expected a node-like call error"),
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use crate::internal::da::DaBackend;
+use crate::internal::inclusion::build_blob_inclusion_input;
+
+use celestia_types::{blob::Commitment, nmt::Namespace};
+use eq_common::{InclusionServiceError, ZKStackEqProofInput};
+use jsonrpsee::core::ClientError as JsonRpcError;
+use prometheus_client::metrics::counter::Counter;
+use sha3::{Digest, Keccak256};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::OnceCell;
+
+/// Where a blob is on Celestia, all that it's DA proof depends on
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BlobCoordinates {
+    height: u64,
+    namespace: Vec<u8>,
+    commitment: [u8; 32],
+}
+
+type CachedProof = Arc<OnceCell<Arc<ZKStackEqProofInput>>>;
+
+#[derive(Default)]
+struct CacheEntries {
+    proofs: HashMap<BlobCoordinates, CachedProof>,
+    /// Oldest first, evicted once over capacity
+    order: VecDeque<BlobCoordinates>,
+}
+
+/// DA proofs of recently collected blobs, keyed only by where the blob is on Celestia.
+///
+/// [Job](crate::Job)s for the same blob that differ only in their replay protection
+/// (`chain_id` and `batch_number`) share one fetch from the DA node and one keccak hash,
+/// even if requested at once. A cached [ZKStackEqProofInput] is a template: use
+/// [with_replay_protection] to give it a `Job`'s own fields.
+pub struct DaProofCache {
+    capacity: usize,
+    entries: StdMutex<CacheEntries>,
+    hits: Counter<u64>,
+    misses: Counter<u64>,
+}
+
+impl DaProofCache {
+    /// Keep up to `capacity` blobs, a capacity of 0 disables caching.
+    /// Counts lookups in [PromMetrics::da_cache_hits](crate::internal::prom_metrics::PromMetrics::da_cache_hits)
+    /// and [PromMetrics::da_cache_misses](crate::internal::prom_metrics::PromMetrics::da_cache_misses).
+    pub fn new(capacity: usize, hits: Counter<u64>, misses: Counter<u64>) -> Self {
+        DaProofCache {
+            capacity,
+            entries: StdMutex::new(CacheEntries::default()),
+            hits,
+            misses,
+        }
+    }
+
+    /// The DA proof template of a blob, collected from a [DaBackend] unless cached.
+    /// Errors from the DA node are mapped by `on_da_error`.
+    pub async fn get_or_build(
+        &self,
+        height: u64,
+        namespace: Namespace,
+        commitment: Commitment,
+        client: &dyn DaBackend,
+        on_da_error: impl Fn(JsonRpcError) -> InclusionServiceError,
+    ) -> Result<Arc<ZKStackEqProofInput>, InclusionServiceError> {
+        self.get_or_try_init(
+            BlobCoordinates {
+                height,
+                namespace: namespace.as_bytes().to_vec(),
+                commitment: *commitment.hash(),
+            },
+            || build_da_proof_template(height, namespace, commitment, client, on_da_error),
+        )
+        .await
+    }
+
+    /// Concurrent callers for the same blob wait on a single `build`. If it fails, only
+    /// that caller gets the error (as mapped for it's own `Job`), and the next caller
+    /// waiting runs it's own `build`.
+    async fn get_or_try_init<F, Fut>(
+        &self,
+        coordinates: BlobCoordinates,
+        build: F,
+    ) -> Result<Arc<ZKStackEqProofInput>, InclusionServiceError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ZKStackEqProofInput, InclusionServiceError>>,
+    {
+        if self.capacity == 0 {
+            self.misses.inc();
+            return build().await.map(Arc::new);
+        }
+        let mut built = false;
+        let proof = self
+            .entry(coordinates)
+            .get_or_try_init(|| {
+                built = true;
+                async move { build().await.map(Arc::new) }
+            })
+            .await
+            .cloned();
+        match built {
+            true => self.misses.inc(),
+            false => self.hits.inc(),
+        };
+        proof
+    }
+
+    /// The cache entry for a blob, added (evicting the oldest entries) if missing.
+    /// Callers already waiting on an evicted entry still share it's result.
+    fn entry(&self, coordinates: BlobCoordinates) -> CachedProof {
+        let mut entries = self.entries.lock().expect("DA cache lock poisoned");
+        if let Some(proof) = entries.proofs.get(&coordinates) {
+            return proof.clone();
+        }
+        let proof = CachedProof::default();
+        entries.proofs.insert(coordinates.clone(), proof.clone());
+        entries.order.push_back(coordinates);
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.proofs.remove(&oldest);
+            }
+        }
+        proof
+    }
+}
+
+/// A cached DA proof template with a [Job](crate::Job)'s own replay protection
+pub fn with_replay_protection(
+    template: &ZKStackEqProofInput,
+    chain_id: u64,
+    batch_number: u32,
+) -> ZKStackEqProofInput {
+    ZKStackEqProofInput {
+        chain_id,
+        batch_number,
+        ..template.clone()
+    }
+}
+
+/// Collect a blob's data, inclusion proof and keccak hash from a [DaBackend],
+/// without any replay protection (a `chain_id` and `batch_number` of 0).
+async fn build_da_proof_template(
+    height: u64,
+    namespace: Namespace,
+    commitment: Commitment,
+    client: &dyn DaBackend,
+    on_da_error: impl Fn(JsonRpcError) -> InclusionServiceError,
+) -> Result<ZKStackEqProofInput, InclusionServiceError> {
+    let blob =
+        build_blob_inclusion_input(height, namespace, commitment, client, on_da_error).await?;
+    let keccak_hash: [u8; 32] = Keccak256::new().chain_update(&blob.data).finalize().into();
+
+    Ok(ZKStackEqProofInput {
+        data: blob.data,
+        namespace_id: blob.namespace_id,
+        share_proofs: blob.share_proofs,
+        row_proof: blob.row_proof,
+        data_root: blob.data_root,
+        keccak_hash,
+        batch_number: 0,
+        chain_id: 0,
+        author: blob.author,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::da::FixtureDaBackend;
+    use crate::internal::inclusion::build_zk_proof_input;
+    use async_trait::async_trait;
+    use celestia_types::{block::Height as BlockHeight, Blob, ExtendedHeader, ShareProof};
+    use eq_sdk::types::BlobId;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts the blobs fetched from a [FixtureDaBackend]
+    struct CountingDaBackend {
+        inner: FixtureDaBackend,
+        blob_gets: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl DaBackend for CountingDaBackend {
+        async fn head_height(&self) -> Result<u64, JsonRpcError> {
+            self.inner.head_height().await
+        }
+
+        async fn header_get_by_height(&self, height: u64) -> Result<ExtendedHeader, JsonRpcError> {
+            self.inner.header_get_by_height(height).await
+        }
+
+        async fn blob_get(
+            &self,
+            height: u64,
+            namespace: Namespace,
+            commitment: Commitment,
+        ) -> Result<Blob, JsonRpcError> {
+            self.blob_gets.fetch_add(1, Ordering::SeqCst);
+            self.inner.blob_get(height, namespace, commitment).await
+        }
+
+        async fn share_get_range(
+            &self,
+            header: &ExtendedHeader,
+            start: u64,
+            end: u64,
+        ) -> Result<ShareProof, JsonRpcError> {
+            self.inner.share_get_range(header, start, end).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_protection_shares_fetch() {
+        let (hits, misses) = (Counter::default(), Counter::default());
+        let cache = DaProofCache::new(4, hits.clone(), misses.clone());
+        let namespace = Namespace::new_v0(&[2; 10]).unwrap();
+        let (inner, height, commitment) =
+            FixtureDaBackend::with_test_blob(namespace, b"Never gonna let you down");
+        let backend = CountingDaBackend {
+            inner,
+            blob_gets: AtomicUsize::new(0),
+        };
+        let height = BlockHeight::try_from(height).unwrap();
+
+        let mut inputs = Vec::new();
+        for (chain_id, batch_number) in [(271, 1), (324, 9)] {
+            let job = BlobId::new(height, namespace, commitment, chain_id, batch_number);
+            let input = build_zk_proof_input(&job, &backend, &cache, |e| {
+                InclusionServiceError::DaClientError(e.to_string())
+            })
+            .await
+            .unwrap();
+            inputs.push(input);
+        }
+
+        assert_eq!(backend.blob_gets.load(Ordering::SeqCst), 1);
+        assert_eq!((misses.get(), hits.get()), (1, 1));
+        // Each job gets it's own replay protection, on the same DA proof
+        assert_eq!((inputs[0].chain_id, inputs[0].batch_number), (271, 1));
+        assert_eq!((inputs[1].chain_id, inputs[1].batch_number), (324, 9));
+        assert_eq!(inputs[0].data, b"Never gonna let you down");
+        assert_eq!(inputs[0].data, inputs[1].data);
+        assert_eq!(inputs[0].keccak_hash, inputs[1].keccak_hash);
+        assert_eq!(inputs[0].data_root, inputs[1].data_root);
+    }
+
+    #[tokio::test]
+    async fn test_failed_fetch_not_cached() {
+        let misses = Counter::default();
+        let cache = DaProofCache::new(1, Counter::default(), misses.clone());
+        let backend = FixtureDaBackend::default();
+        let namespace = Namespace::new_v0(&[1; 10]).unwrap();
+        let da_errors = AtomicUsize::new(0);
+        for height in [1, 1, 2] {
+            let result = cache
+                .get_or_build(
+                    height,
+                    namespace,
+                    Commitment::new([0; 32].into()),
+                    &backend,
+                    |e| {
+                        da_errors.fetch_add(1, Ordering::SeqCst);
+                        InclusionServiceError::DaClientError(e.to_string())
+                    },
+                )
+                .await;
+            assert!(result.is_err());
+        }
+        // Every job sees it's own error, a failure is fetched again
+        assert_eq!(da_errors.load(Ordering::SeqCst), 3);
+        assert_eq!(misses.get(), 3);
+        // Only the newest blob is kept
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.proofs.len(), 1);
+        assert_eq!(entries.order.front().map(|c| c.height), Some(2));
+    }
+}
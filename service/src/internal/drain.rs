@@ -0,0 +1,106 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{watch, Notify};
+
+/// How long a shutdown waits on in-flight work by default, see [Drain::wait_idle]
+pub const DEFAULT_SHUTDOWN_DEADLINE: Duration = Duration::from_secs(30);
+
+/// Tracks work that must save it's progress to the DB before the service shuts down:
+/// collecting DA data, and requesting a ZK proof until it's request ID is stored.
+///
+/// Waiting on a requested proof is not tracked, as it is resumed from the stored
+/// request ID on restart.
+pub struct Drain {
+    draining: watch::Sender<bool>,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+/// Held while doing work tracked by a [Drain]
+pub struct DrainGuard<'a>(&'a Drain);
+
+impl Default for Drain {
+    fn default() -> Self {
+        Drain {
+            draining: watch::channel(false).0,
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+        }
+    }
+}
+
+impl Drain {
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    /// Stop starting new tracked work, see [Self::start]
+    pub fn begin(&self) {
+        self.draining.send_replace(true);
+    }
+
+    /// Resolves once [Self::begin] is called
+    pub async fn draining(&self) {
+        let _ = self
+            .draining
+            .subscribe()
+            .wait_for(|draining| *draining)
+            .await;
+    }
+
+    /// Start tracked work, held until the guard is dropped.
+    /// `None` once draining, the work is then left to resume on restart.
+    pub fn start(&self) -> Option<DrainGuard<'_>> {
+        // Counted before checking, so a drain that begins in between waits on us
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = DrainGuard(self);
+        (!self.is_draining()).then_some(guard)
+    }
+
+    /// Wait up to `deadline` for all tracked work to finish.
+    /// Returns false if the deadline passed first.
+    pub async fn wait_idle(&self, deadline: Duration) -> bool {
+        tokio::time::timeout(deadline, async {
+            loop {
+                // Created before checking, so a notification in between is not missed
+                let idle = self.idle.notified();
+                if self.in_flight.load(Ordering::SeqCst) == 0 {
+                    return;
+                }
+                idle.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+}
+
+impl Drop for DrainGuard<'_> {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain() {
+        let drain = Drain::default();
+        assert!(drain.wait_idle(Duration::from_millis(10)).await);
+        let work = drain.start().unwrap();
+        assert!(!drain.wait_idle(Duration::from_millis(10)).await);
+
+        drain.begin();
+        drain.draining().await;
+        assert!(drain.start().is_none());
+
+        let (idle, _) = tokio::join!(drain.wait_idle(Duration::from_secs(10)), async {
+            drop(work)
+        });
+        assert!(idle);
+    }
+}
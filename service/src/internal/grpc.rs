@@ -1,17 +1,48 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
+use futures::future;
+use futures::stream::{self, BoxStream, StreamExt};
 use log::{debug, error, info, warn};
-use tonic::{Request, Response, Status};
+use prost::Message;
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Code, Request, Response, Status};
+use tonic_types::{ErrorDetails, StatusExt};
 
 use eq_common::eqs::inclusion_server::Inclusion;
 use eq_common::eqs::{
     get_zk_stack_response::{ResponseValue, Status as ResponseStatus},
-    GetZkStackRequest, GetZkStackResponse, ProofWithPublicValues,
+    AggregateZkStackRequest, BatchGetZkStackRequest, BatchGetZkStackResponse, GetProofRequest,
+    GetZkStackMultiBlobRequest, GetZkStackRequest, GetZkStackResponse, ProofChunk, ProofType,
+    ProofWithPublicValues,
 };
 
-use celestia_types::{blob::Commitment, nmt::Namespace};
+use celestia_types::{blob::Commitment, block::Height as BlockHeight, nmt::Namespace};
 
-use crate::{InclusionService, Job, JobStatus};
+use crate::internal::aggregation::{AggregationJob, AggregationStatus, MembersProgress};
+use crate::internal::artifacts::ArtifactStore;
+use crate::internal::auth::Tenant;
+use crate::internal::limits::{ClientId, QuotaExceeded};
+use crate::internal::retry::unix_ms_now;
+use crate::internal::schema;
+use crate::internal::store::StoreTree;
+use crate::{InclusionService, Job, JobStatus, MultiBlobJob};
+use eq_common::InclusionServiceError;
+use eq_sdk::types::{BlobId, PayyBlobId};
+
+/// Most requests accepted in one BatchGetZKStack call, members in one AggregateZKStack call,
+/// or blobs in one GetZKStackMultiBlob call
+const MAX_BATCH_SIZE: usize = 256;
+
+/// Responses buffered for a WatchZKStack client before we wait on it to read more
+const WATCH_BUFFER: usize = 16;
+
+/// Most proof data in a StreamProof chunk, for a proof kept in the DB.
+/// Chunks of a proof in the [ArtifactStore] are as read from the store.
+const PROOF_CHUNK_SIZE: usize = 64 * 1024;
 
 // I hate this workaround. Kill it with fire.
 pub struct InclusionServiceArc(pub Arc<InclusionService>);
@@ -23,155 +54,808 @@ impl Inclusion for InclusionServiceArc {
         request: Request<GetZkStackRequest>,
     ) -> Result<Response<GetZkStackResponse>, Status> {
         self.0.metrics.grpc_req.inc();
-        let request = request.into_inner();
-        let job = Job::new(
-            request
-                .height
-                .try_into()
-                .map_err(|_| Status::invalid_argument("Block Height must be u64"))?,
-            // TODO: should we have some handling of versions here?
-            Namespace::new_v0(&request.namespace).map_err(|_| {
-                Status::invalid_argument("Namespace v0 expected! Must be 32 bytes, check encoding")
-            })?,
-            Commitment::new(request.commitment.try_into().map_err(|_| {
-                Status::invalid_argument("Commitment must be 32 bytes, check encoding")
-            })?),
-            request.chain_id,
-            request.batch_number,
-        );
+        let caller = self.caller(&request)?;
+        let job = Job::ZkStack(blob_id_from_request(request.into_inner())?);
+        caller.authorize(&job)?;
 
         info!("Received grpc request for: {job:?}");
 
-        let job_key = bincode::serialize(&job).map_err(|e| Status::internal(e.to_string()))?;
+        self.get_or_start_job(&caller, job).await
+    }
+
+    type WatchZKStackStream = ReceiverStream<Result<GetZkStackResponse, Status>>;
+
+    async fn watch_zk_stack(
+        &self,
+        request: Request<GetZkStackRequest>,
+    ) -> Result<Response<Self::WatchZKStackStream>, Status> {
+        self.0.metrics.grpc_req.inc();
+        let caller = self.caller(&request)?;
+        let job = Job::ZkStack(blob_id_from_request(request.into_inner())?);
+        caller.authorize(&job)?;
+
+        info!("Received grpc watch request for: {job:?}");
+
+        let job_key = job.key().map_err(|e| Status::internal(e.to_string()))?;
+
+        // Subscribe before reading the current status, so no change is missed in between
+        let mut job_events = self.0.subscribe_job_events();
+        let (first_response, done) = match self.current_status(&job_key)? {
+            Some(job_status) => (
+                self.status_response(&job_key, &job_status).await?,
+                job_status.is_final(),
+            ),
+            None => (self.start_job(&caller, job_key.clone(), job)?, false),
+        };
+
+        let (tx, rx) = mpsc::channel(WATCH_BUFFER);
+        let watcher = InclusionServiceArc(self.0.clone());
+        tokio::spawn(async move {
+            if tx.send(Ok(first_response)).await.is_err() || done {
+                return;
+            }
+            loop {
+                let job_event = tokio::select! {
+                    job_event = job_events.recv() => job_event,
+                    _ = watcher.0.drain.draining() => {
+                        let _ = tx.send(Err(shutting_down_status())).await;
+                        return;
+                    }
+                    // The client went away, don't hold the subscription until the job's next event
+                    _ = tx.closed() => return,
+                };
+                let job_status = match job_event {
+                    Ok(event) if event.job_key == job_key => event.status,
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => {
+                        // Events for this job may be lost, read the latest from the DB instead
+                        debug!("Watcher lagged by {missed} job events, reloading status");
+                        match watcher.current_status(&job_key) {
+                            Ok(Some(job_status)) => Arc::new(job_status),
+                            Ok(None) => continue,
+                            Err(e) => {
+                                let _ = tx.send(Err(e)).await;
+                                return;
+                            }
+                        }
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+                let response = watcher.status_response(&job_key, &job_status).await;
+                let failed = response.is_err();
+                if tx.send(response).await.is_err() || failed || job_status.is_final() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn batch_get_zk_stack(
+        &self,
+        request: Request<BatchGetZkStackRequest>,
+    ) -> Result<Response<BatchGetZkStackResponse>, Status> {
+        self.0.metrics.grpc_req.inc();
+        let caller = self.caller(&request)?;
+        let requests = request.into_inner().requests;
+        if requests.len() > MAX_BATCH_SIZE {
+            return Err(Status::invalid_argument(format!(
+                "Batch has {} requests, at most {MAX_BATCH_SIZE} allowed",
+                requests.len()
+            )));
+        }
+
+        info!("Received grpc batch request for {} jobs", requests.len());
+
+        let jobs = requests
+            .into_iter()
+            .enumerate()
+            .map(|(i, request)| {
+                let job = Job::ZkStack(blob_id_from_request(request).map_err(|e| {
+                    Status::invalid_argument(format!("Request {i}: {}", e.message()))
+                })?);
+                caller
+                    .authorize(&job)
+                    .map_err(|e| Status::new(e.code(), format!("Request {i}: {}", e.message())))?;
+                let job_key = job.key().map_err(|e| Status::internal(e.to_string()))?;
+                Ok((job_key, job))
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+        let job_keys: Vec<_> = jobs.iter().map(|(job_key, _)| job_key.clone()).collect();
+
+        self.accepting_jobs()?;
+        self.admit_new_jobs(&caller, &jobs)?;
+        let statuses = self
+            .0
+            .get_or_start_jobs(jobs, caller.tenant_name())
+            .map_err(|e| match e {
+                InclusionServiceError::QueueFull => queue_full_status(),
+                e => Status::internal(e.to_string()),
+            })?;
+
+        let mut responses = Vec::with_capacity(job_keys.len());
+        for (job_key, job_status) in job_keys.iter().zip(statuses) {
+            responses.push(match job_status {
+                Some(job_status) => self.status_response(job_key, &job_status).await?,
+                None => new_job_response(),
+            });
+        }
+
+        Ok(Response::new(BatchGetZkStackResponse { responses }))
+    }
+
+    async fn aggregate_zk_stack(
+        &self,
+        request: Request<AggregateZkStackRequest>,
+    ) -> Result<Response<GetZkStackResponse>, Status> {
+        self.0.metrics.grpc_req.inc();
+        let caller = self.caller(&request)?;
+        let request = request.into_inner();
+        if request.members.len() > MAX_BATCH_SIZE {
+            return Err(Status::invalid_argument(format!(
+                "Aggregation has {} members, at most {MAX_BATCH_SIZE} allowed",
+                request.members.len()
+            )));
+        }
+        let aggregation = AggregationJob {
+            chain_id: request.chain_id,
+            batch_number: request.batch_number,
+            members: request
+                .members
+                .into_iter()
+                .enumerate()
+                .map(|(i, member)| {
+                    blob_id_from_request(member).map_err(|e| {
+                        Status::invalid_argument(format!("Member {i}: {}", e.message()))
+                    })
+                })
+                .collect::<Result<_, Status>>()?,
+        };
+        aggregation
+            .validate()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        if let Some(tenant) = &caller.tenant {
+            tenant.authorize_aggregation(&aggregation)?;
+        }
+
+        info!("Received grpc aggregation request for: {aggregation:?}");
+
+        let aggregation_key =
+            schema::encode(&aggregation).map_err(|e| Status::internal(e.to_string()))?;
+        let start_aggregation = || {
+            self.accepting_jobs()?;
+            let members = aggregation
+                .member_jobs()
+                .map_err(|e| Status::internal(e.to_string()))?;
+            self.admit_new_jobs(&caller, &members)?;
+            self.0
+                .start_aggregation(&aggregation_key, &aggregation, caller.tenant_name())
+                .map_err(|e| match e {
+                    InclusionServiceError::QueueFull => queue_full_status(),
+                    e => Status::internal(e.to_string()),
+                })
+        };
+
+        let response = match self
+            .0
+            .get_aggregation_status(&aggregation_key)
+            .map_err(|e| Status::internal(e.to_string()))?
+        {
+            None => {
+                start_aggregation()?;
+                GetZkStackResponse {
+                    status: ResponseStatus::DaPending as i32,
+                    response_value: Some(ResponseValue::StatusMessage(
+                        "New aggregation started! Call again for status and results".to_string(),
+                    )),
+                    ..Default::default()
+                }
+            }
+            Some(AggregationStatus::MembersPending) => {
+                let message = match self
+                    .0
+                    .members_progress(&aggregation)
+                    .map_err(|e| Status::internal(e.to_string()))?
+                {
+                    MembersProgress::Pending(finished) => format!(
+                        "Waiting on member jobs, {finished} of {} finished",
+                        aggregation.members.len()
+                    ),
+                    _ => "Member jobs done, aggregation starting".to_string(),
+                };
+                GetZkStackResponse {
+                    status: ResponseStatus::DaPending as i32,
+                    response_value: Some(ResponseValue::StatusMessage(message)),
+                    ..Default::default()
+                }
+            }
+            Some(AggregationStatus::RequestingProof) => GetZkStackResponse {
+                status: ResponseStatus::ZkpPending as i32,
+                response_value: Some(ResponseValue::StatusMessage(
+                    "Members proven, requesting aggregation proof".to_string(),
+                )),
+                ..Default::default()
+            },
+            Some(AggregationStatus::ZkProofPending(request_id)) => GetZkStackResponse {
+                status: ResponseStatus::ZkpPending as i32,
+                response_value: Some(ResponseValue::ProofId(request_id.to_vec())),
+                ..Default::default()
+            },
+            Some(AggregationStatus::ZkProofFinished(proof)) => GetZkStackResponse {
+                status: ResponseStatus::ZkpFinished as i32,
+                response_value: Some(ResponseValue::Proof(ProofWithPublicValues {
+                    proof_data: proof.bytes(),
+                    public_values: proof.public_values.to_vec(),
+                })),
+                ..Default::default()
+            },
+            Some(AggregationStatus::Failed(error, false)) => GetZkStackResponse {
+                status: ResponseStatus::PermanentFailure as i32,
+                response_value: Some(ResponseValue::ErrorMessage(format!("{error:?}"))),
+                ..Default::default()
+            },
+            Some(AggregationStatus::Failed(error, true)) => {
+                warn!("Aggregation is Retryable Failure, retrying");
+                start_aggregation()?;
+                GetZkStackResponse {
+                    status: ResponseStatus::RetryableFailure as i32,
+                    response_value: Some(ResponseValue::ErrorMessage(format!(
+                        "Retrying! Previous error: {error:?}"
+                    ))),
+                    ..Default::default()
+                }
+            }
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn get_zk_stack_multi_blob(
+        &self,
+        request: Request<GetZkStackMultiBlobRequest>,
+    ) -> Result<Response<GetZkStackResponse>, Status> {
+        self.0.metrics.grpc_req.inc();
+        let caller = self.caller(&request)?;
+        let request = request.into_inner();
+        if request.blobs.len() > MAX_BATCH_SIZE {
+            return Err(Status::invalid_argument(format!(
+                "Request has {} blobs, at most {MAX_BATCH_SIZE} allowed",
+                request.blobs.len()
+            )));
+        }
+        let multi_blob = MultiBlobJob {
+            chain_id: request.chain_id,
+            batch_number: request.batch_number,
+            blobs: request
+                .blobs
+                .into_iter()
+                .enumerate()
+                .map(|(i, blob)| {
+                    blob_id_from_request(blob)
+                        .map_err(|e| Status::invalid_argument(format!("Blob {i}: {}", e.message())))
+                })
+                .collect::<Result<_, Status>>()?,
+        };
+        multi_blob
+            .validate()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let job = Job::ZkStackMultiBlob(multi_blob);
+        caller.authorize(&job)?;
+
+        info!("Received grpc multi-blob request for: {job:?}");
+
+        self.get_or_start_job(&caller, job).await
+    }
+
+    async fn get_proof(
+        &self,
+        request: Request<GetProofRequest>,
+    ) -> Result<Response<GetZkStackResponse>, Status> {
+        self.0.metrics.grpc_req.inc();
+        let caller = self.caller(&request)?;
+        let job = job_from_proof_request(request.into_inner())?;
+        caller.authorize(&job)?;
+
+        info!("Received grpc proof request for: {job:?}");
+
+        self.get_or_start_job(&caller, job).await
+    }
+
+    type StreamProofStream = BoxStream<'static, Result<ProofChunk, Status>>;
+
+    async fn stream_proof(
+        &self,
+        request: Request<GetProofRequest>,
+    ) -> Result<Response<Self::StreamProofStream>, Status> {
+        self.0.metrics.grpc_req.inc();
+        let caller = self.caller(&request)?;
+        let job = job_from_proof_request(request.into_inner())?;
+        caller.authorize(&job)?;
+
+        info!("Received grpc proof stream request for: {job:?}");
+
+        let job_key = job.key().map_err(|e| Status::internal(e.to_string()))?;
+        let Some(job_status) = self.current_status(&job_key)?.filter(|job_status| {
+            matches!(
+                job_status,
+                JobStatus::ZkProofFinished(_)
+                    | JobStatus::ZkProofCompacted(_)
+                    | JobStatus::ZkProofStored(_)
+            )
+        }) else {
+            return Err(Status::failed_precondition(
+                "Proof is not finished, check the request's status with GetProof",
+            ));
+        };
+
+        let (public_values, proof_data) = match &job_status {
+            JobStatus::ZkProofStored(stored) => (
+                stored.public_values.clone(),
+                self.artifact_store()?
+                    .stream(&stored.proof_data)
+                    .await
+                    .map_err(|e| Status::internal(e.to_string()))?,
+            ),
+            _ => {
+                let proof = job_status
+                    .compact_proof()
+                    .ok_or_else(|| Status::internal("Job has no proof"))?;
+                let chunks: Vec<_> = proof
+                    .proof_data
+                    .chunks(PROOF_CHUNK_SIZE)
+                    .map(|chunk| Ok::<_, InclusionServiceError>(chunk.to_vec()))
+                    .collect();
+                (proof.public_values, stream::iter(chunks).boxed())
+            }
+        };
+        let first_chunk = ProofChunk {
+            proof_data: Vec::new(),
+            public_values,
+            program_id: self.program_id(&job_key)?,
+        };
+        let chunks = proof_data.map(|chunk| {
+            chunk
+                .map(|proof_data| ProofChunk {
+                    proof_data,
+                    ..Default::default()
+                })
+                .map_err(|e| Status::internal(e.to_string()))
+        });
+
+        Ok(Response::new(
+            stream::once(future::ready(Ok(first_chunk)))
+                .chain(chunks)
+                .boxed(),
+        ))
+    }
+}
+
+impl InclusionServiceArc {
+    /// Report on a [Job], starting it if the service has not seen it before
+    async fn get_or_start_job(
+        &self,
+        caller: &Caller,
+        job: Job,
+    ) -> Result<Response<GetZkStackResponse>, Status> {
+        let job_key = job.key().map_err(|e| Status::internal(e.to_string()))?;
+
+        match self.current_status(&job_key)? {
+            Some(job_status) => Ok(Response::new(
+                self.status_response(&job_key, &job_status).await?,
+            )),
+            None => Ok(Response::new(self.start_job(caller, job_key, job)?)),
+        }
+    }
 
+    /// The stored [JobStatus] of a [Job], if it is known
+    fn current_status(&self, job_key: &[u8]) -> Result<Option<JobStatus>, Status> {
         // Check DB for finished jobs
         if let Some(proof_data) = self
             .0
-            .finished_db
-            .get(&job_key)
+            .store
+            .get(StoreTree::Finished, job_key)
             .map_err(|e| Status::internal(e.to_string()))?
         {
             let job_status: JobStatus =
-                bincode::deserialize(&proof_data).map_err(|e| Status::internal(e.to_string()))?;
-            match job_status {
-                JobStatus::ZkProofFinished(proof) => {
-                    debug!("Job finished, returning proof");
-
-                    return Ok(Response::new(GetZkStackResponse {
-                        status: ResponseStatus::ZkpFinished as i32,
-                        response_value: Some(ResponseValue::Proof(ProofWithPublicValues {
-                            proof_data: proof.bytes(),
-                            public_values: proof.public_values.to_vec(),
-                        })),
-                    }));
-                }
-                JobStatus::Failed(error, maybe_status) => {
-                    match maybe_status {
-                        None => {
-                            warn!("Job is PERMANENT FAILURE, returning status");
-                            return Ok(Response::new(GetZkStackResponse {
-                                status: ResponseStatus::PermanentFailure as i32,
-                                response_value: Some(ResponseValue::ErrorMessage(format!(
-                                    "{error:?}"
-                                ))),
-                            }));
-                        }
-                        Some(retry_status) => {
-                            warn!("Job is Retryable Failure, returning status & retrying");
-                            self.0.metrics.jobs_attempted.inc();
-                            // We retry errors on each call to the gRPC
-                            // for a specific [Job] by sending to the queue
-                            match self.0.send_job_with_new_status(job_key, *retry_status, job) {
-                                Ok(_) => {
-                                    return Ok(Response::new(GetZkStackResponse {
-                                        status: ResponseStatus::RetryableFailure as i32,
-                                        response_value: Some(ResponseValue::ErrorMessage(format!(
-                                            "Retrying! Previous error: {error:?}"
-                                        ))),
-                                    }));
-                                }
-                                Err(e) => {
-                                    return Ok(Response::new(GetZkStackResponse {
-                                        status: ResponseStatus::PermanentFailure as i32,
-                                        response_value: Some(ResponseValue::ErrorMessage(format!(
-                                            "Internal Failure: {e:?}"
-                                        ))),
-                                    }));
-                                }
-                            }
-                        }
-                    }
-                }
+                schema::decode(&proof_data).map_err(|e| Status::internal(e.to_string()))?;
+            return match job_status {
+                JobStatus::ZkProofFinished(_)
+                | JobStatus::ZkProofCompacted(_)
+                | JobStatus::ZkProofStored(_)
+                | JobStatus::Failed(_, _) => Ok(Some(job_status)),
                 _ => {
                     let e = "Finished DB is in invalid state";
                     error!("{e}");
-                    return Err(Status::internal(e));
+                    Err(Status::internal(e))
                 }
-            }
+            };
         }
 
         // Check DB for pending jobs
         if let Some(queue_data) = self
             .0
-            .queue_db
-            .get(&job_key)
+            .store
+            .get(StoreTree::Queue, job_key)
             .map_err(|e| Status::internal(e.to_string()))?
         {
             debug!("Job in pending queue");
             let job_status: JobStatus =
-                bincode::deserialize(&queue_data).map_err(|e| Status::internal(e.to_string()))?;
-            match job_status {
-                JobStatus::DataAvailabilityPending => {
-                    return Ok(Response::new(GetZkStackResponse {
-                        status: ResponseStatus::DaPending as i32,
-                        response_value: Some(ResponseValue::StatusMessage(
-                            "Trying to collect DA inclusion proof".to_string(),
-                        )),
-                    }));
-                }
-                JobStatus::DataAvailable(_) => {
-                    return Ok(Response::new(GetZkStackResponse {
-                        status: ResponseStatus::DaAvailable as i32,
-                        response_value: Some(ResponseValue::StatusMessage(
-                            "Valid DA inclusion proof, requesting ZKP".to_string(),
-                        )),
-                    }));
-                }
-                JobStatus::ZkProofPending(job_id) => {
-                    return Ok(Response::new(GetZkStackResponse {
-                        status: ResponseStatus::ZkpPending as i32,
-                        response_value: Some(ResponseValue::ProofId(job_id.to_vec())),
-                    }));
-                }
+                schema::decode(&queue_data).map_err(|e| Status::internal(e.to_string()))?;
+            return match job_status {
+                JobStatus::DataAvailabilityPending
+                | JobStatus::DataAvailable(_)
+                | JobStatus::DataAvailableMultiBlob(_)
+                | JobStatus::DataAvailablePayy(_)
+                | JobStatus::ZkProofPending(_) => Ok(Some(job_status)),
                 _ => {
-                    let e = "Job queue is in invalid state for {job:?}";
+                    let e = format!("Job queue is in invalid state: {job_status:?}");
                     error!("{e}");
-                    return Err(Status::internal(e));
+                    Err(Status::internal(e))
+                }
+            };
+        }
+
+        Ok(None)
+    }
+
+    /// Report a [JobStatus] to a client
+    async fn status_response(
+        &self,
+        job_key: &[u8],
+        job_status: &JobStatus,
+    ) -> Result<GetZkStackResponse, Status> {
+        let response = match job_status {
+            JobStatus::DataAvailabilityPending => GetZkStackResponse {
+                status: ResponseStatus::DaPending as i32,
+                response_value: Some(ResponseValue::StatusMessage(
+                    "Trying to collect DA inclusion proof".to_string(),
+                )),
+                ..Default::default()
+            },
+            JobStatus::DataAvailable(_)
+            | JobStatus::DataAvailableMultiBlob(_)
+            | JobStatus::DataAvailablePayy(_) => GetZkStackResponse {
+                status: ResponseStatus::DaAvailable as i32,
+                response_value: Some(ResponseValue::StatusMessage(
+                    "Valid DA inclusion proof, requesting ZKP".to_string(),
+                )),
+                ..Default::default()
+            },
+            JobStatus::ZkProofPending(job_id) => GetZkStackResponse {
+                status: ResponseStatus::ZkpPending as i32,
+                response_value: Some(ResponseValue::ProofId(job_id.to_vec())),
+                program_id: self.program_id(job_key)?,
+                ..Default::default()
+            },
+            JobStatus::ZkProofFinished(_)
+            | JobStatus::ZkProofCompacted(_)
+            | JobStatus::ZkProofStored(_) => {
+                debug!("Job finished, returning proof");
+                GetZkStackResponse {
+                    status: ResponseStatus::ZkpFinished as i32,
+                    response_value: Some(ResponseValue::Proof(self.proof(job_status).await?)),
+                    program_id: self.program_id(job_key)?,
+                    ..Default::default()
+                }
+            }
+            JobStatus::Failed(error, maybe_status) => {
+                let retry_state = self
+                    .0
+                    .get_retry_state(job_key)
+                    .map_err(|e| Status::internal(e.to_string()))?
+                    .unwrap_or_default();
+                match maybe_status {
+                    None => {
+                        warn!("Job is PERMANENT FAILURE, returning status");
+                        GetZkStackResponse {
+                            status: ResponseStatus::PermanentFailure as i32,
+                            response_value: Some(ResponseValue::ErrorMessage(format!("{error:?}"))),
+                            retry_attempts: retry_state.attempts,
+                            ..Default::default()
+                        }
+                    }
+                    Some(_) => {
+                        // Retries are scheduled by the service, not on calls to the gRPC
+                        debug!("Job is Retryable Failure, returning status");
+                        GetZkStackResponse {
+                            status: ResponseStatus::RetryableFailure as i32,
+                            response_value: Some(ResponseValue::ErrorMessage(format!(
+                                "Retry {} of {} scheduled! Previous error: {error:?}",
+                                retry_state.attempts, self.0.config.retry_policy.max_attempts
+                            ))),
+                            retry_attempts: retry_state.attempts,
+                            next_retry_unix_ms: retry_state.next_retry_unix_ms,
+                        }
+                    }
                 }
             }
+        };
+        Ok(response)
+    }
+
+    /// The proof of a finished [Job], read from the [ArtifactStore] if it's kept there
+    async fn proof(&self, job_status: &JobStatus) -> Result<ProofWithPublicValues, Status> {
+        if let JobStatus::ZkProofStored(stored) = job_status {
+            return Ok(ProofWithPublicValues {
+                proof_data: self
+                    .artifact_store()?
+                    .get(&stored.proof_data)
+                    .await
+                    .map_err(|e| Status::internal(e.to_string()))?,
+                public_values: stored.public_values.clone(),
+            });
         }
+        let proof = job_status
+            .compact_proof()
+            .ok_or_else(|| Status::internal("Job has no proof"))?;
+        Ok(ProofWithPublicValues {
+            proof_data: proof.proof_data,
+            public_values: proof.public_values,
+        })
+    }
+
+    fn artifact_store(&self) -> Result<&ArtifactStore, Status> {
+        self.0.config.artifact_store.as_ref().ok_or_else(|| {
+            Status::internal("Proof is kept in an artifact store, but none is configured")
+        })
+    }
+
+    /// The ID of the program a [Job] is proven with, empty if it has not requested a proof
+    fn program_id(&self, job_key: &[u8]) -> Result<Vec<u8>, Status> {
+        Ok(self
+            .0
+            .get_job_program_id(job_key)
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map(|program_id| program_id.to_vec())
+            .unwrap_or_default())
+    }
+
+    /// Who made a request, authenticated if tenants are configured
+    fn caller<M: Message>(&self, request: &Request<M>) -> Result<Caller, Status> {
+        Ok(Caller {
+            tenant: self
+                .0
+                .config
+                .auth
+                .as_ref()
+                .map(|auth| auth.authenticate(request))
+                .transpose()?,
+            ip: request.remote_addr().map(|addr| addr.ip()),
+        })
+    }
 
+    /// Check a caller's rate limit and proof quota before starting any of `jobs` that are new,
+    /// each counted against it's chain ID
+    fn admit_new_jobs(&self, caller: &Caller, jobs: &[(Vec<u8>, Job)]) -> Result<(), Status> {
+        let mut new_jobs = BTreeMap::<u64, usize>::new();
+        for (job_key, job) in jobs {
+            if self.current_status(job_key)?.is_none() {
+                *new_jobs.entry(job.chain_id()).or_default() += 1;
+            }
+        }
+        for (chain_id, count) in new_jobs {
+            let client = ClientId {
+                tenant: caller.tenant_name().to_string(),
+                chain_id,
+            };
+            let labels = (&client).into();
+            if let Err(exceeded) = self
+                .0
+                .check_proof_quota(&client)
+                .map_err(|e| Status::internal(e.to_string()))?
+            {
+                self.0.metrics.quota_exceeded.get_or_create(&labels).inc();
+                return Err(quota_exceeded_status(&exceeded));
+            }
+            let limits = self.0.config.limits.for_tenant(&client.tenant);
+            if let Err(wait) =
+                self.0
+                    .rate_limiter
+                    .try_acquire(&caller.rate_limit_key(), chain_id, limits, count)
+            {
+                self.0
+                    .metrics
+                    .jobs_rate_limited
+                    .get_or_create(&labels)
+                    .inc();
+                return Err(rate_limited_status(&client, wait));
+            }
+        }
+        Ok(())
+    }
+
+    /// New [Job]s are rejected once the service is shutting down
+    fn accepting_jobs(&self) -> Result<(), Status> {
+        match self.0.drain.is_draining() {
+            true => Err(shutting_down_status()),
+            false => Ok(()),
+        }
+    }
+
+    /// Queue a [Job] the service has not seen before, for a caller
+    fn start_job(
+        &self,
+        caller: &Caller,
+        job_key: Vec<u8>,
+        job: Job,
+    ) -> Result<GetZkStackResponse, Status> {
+        self.accepting_jobs()?;
+        self.admit_new_jobs(caller, &[(job_key.clone(), job.clone())])?;
         info!("New {job:?} sending to worker and adding to queue");
-        self.0.metrics.jobs_attempted.inc();
-        self.0
-            .queue_db
-            .insert(
-                &job_key,
-                bincode::serialize(&JobStatus::DataAvailabilityPending)
-                    .map_err(|e| Status::internal(e.to_string()))?,
-            )
+        // Recorded first, so it's known before the job requests a proof
+        let recorded = self
+            .0
+            .record_job_tenant(&job_key, caller.tenant_name())
             .map_err(|e| Status::internal(e.to_string()))?;
-
         self.0
-            .job_sender
-            .send(Some(job.clone()))
-            .map_err(|e| Status::internal(e.to_string()))?;
+            .try_send_job_with_new_status(job_key.clone(), JobStatus::DataAvailabilityPending, job)
+            .map_err(|e| {
+                if recorded {
+                    let _ = self.0.store.remove(StoreTree::JobTenant, &job_key);
+                }
+                match e {
+                    InclusionServiceError::QueueFull => queue_full_status(),
+                    e => Status::internal(e.to_string()),
+                }
+            })?;
+        self.0.metrics.jobs_attempted.inc();
 
-        Ok(Response::new(GetZkStackResponse {
-            status: ResponseStatus::DaPending as i32,
-            response_value: Some(ResponseValue::StatusMessage(
-                "New job started! Call again for status and results".to_string(),
-            )),
-        }))
+        Ok(new_job_response())
     }
 }
+
+/// Who made a request
+struct Caller {
+    /// `None` if authentication is off
+    tenant: Option<Arc<Tenant>>,
+    ip: Option<IpAddr>,
+}
+
+impl Caller {
+    /// Reject a [Job] the tenant may not request, before it is looked up or started
+    fn authorize(&self, job: &Job) -> Result<(), Status> {
+        match &self.tenant {
+            Some(tenant) => tenant.authorize_job(job),
+            None => Ok(()),
+        }
+    }
+
+    /// The tenant [Job]s are recorded and counted against, "" without authentication
+    fn tenant_name(&self) -> &str {
+        self.tenant
+            .as_ref()
+            .map_or("", |tenant| tenant.name.as_str())
+    }
+
+    /// Rate limited by tenant, or by IP address without authentication
+    fn rate_limit_key(&self) -> String {
+        match (&self.tenant, self.ip) {
+            (Some(tenant), _) => tenant.name.clone(),
+            (None, Some(ip)) => ip.to_string(),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+fn new_job_response() -> GetZkStackResponse {
+    GetZkStackResponse {
+        status: ResponseStatus::DaPending as i32,
+        response_value: Some(ResponseValue::StatusMessage(
+            "New job started! Call again for status and results".to_string(),
+        )),
+        ..Default::default()
+    }
+}
+
+fn blob_id_from_request(request: GetZkStackRequest) -> Result<BlobId, Status> {
+    let (height, namespace, commitment) =
+        blob_from_request(request.height, &request.namespace, request.commitment)?;
+    Ok(BlobId::new(
+        height,
+        namespace,
+        commitment,
+        request.chain_id,
+        request.batch_number,
+    ))
+}
+
+/// A [Job] of the kind given by the request's [ProofType]
+fn job_from_proof_request(request: GetProofRequest) -> Result<Job, Status> {
+    let proof_type = ProofType::try_from(request.proof_type)
+        .map_err(|_| Status::invalid_argument("Unknown proof_type"))?;
+    let (height, namespace, commitment) =
+        blob_from_request(request.height, &request.namespace, request.commitment)?;
+    match proof_type {
+        ProofType::Zkstack => {
+            if request.l2_block_number != 0 {
+                return Err(Status::invalid_argument(
+                    "l2_block_number is for PAYY proofs only",
+                ));
+            }
+            Ok(Job::ZkStack(BlobId::new(
+                height,
+                namespace,
+                commitment,
+                request.chain_id,
+                request.batch_number,
+            )))
+        }
+        ProofType::Payy => {
+            if request.chain_id != 0 || request.batch_number != 0 {
+                return Err(Status::invalid_argument(
+                    "chain_id and batch_number are for ZKSTACK proofs only",
+                ));
+            }
+            Ok(Job::Payy(PayyBlobId::new(
+                height,
+                namespace,
+                commitment,
+                request.l2_block_number,
+            )))
+        }
+    }
+}
+
+/// The fields identifying a blob, common to every kind of request
+fn blob_from_request(
+    height: u64,
+    namespace: &[u8],
+    commitment: Vec<u8>,
+) -> Result<(BlockHeight, Namespace, Commitment), Status> {
+    Ok((
+        height
+            .try_into()
+            .map_err(|_| Status::invalid_argument("Block Height must be u64"))?,
+        // TODO: should we have some handling of versions here?
+        Namespace::new_v0(namespace).map_err(|_| {
+            Status::invalid_argument("Namespace v0 expected! Must be 32 bytes, check encoding")
+        })?,
+        Commitment::new(commitment.try_into().map_err(|_| {
+            Status::invalid_argument("Commitment must be 32 bytes, check encoding")
+        })?),
+    ))
+}
+
+/// Reported when the service has too many jobs in flight to accept another
+fn queue_full_status() -> Status {
+    Status::resource_exhausted("Job queue is full, try again later")
+}
+
+/// Reported when a caller starts new jobs too fast, with how long to wait in it's details
+fn rate_limited_status(client: &ClientId, wait: Duration) -> Status {
+    let mut details = ErrorDetails::new();
+    details
+        .set_retry_info(Some(wait))
+        .add_quota_failure_violation(client.to_string(), "New job rate limit");
+    Status::with_error_details(
+        Code::ResourceExhausted,
+        format!(
+            "Rate limited for {client}, try again in {:.1}s",
+            wait.as_secs_f64()
+        ),
+        details,
+    )
+}
+
+/// Reported when a caller is at a proof quota, with when it resets in it's details
+fn quota_exceeded_status(exceeded: &QuotaExceeded) -> Status {
+    let resets_in =
+        Duration::from_millis((exceeded.resets_at_unix_s * 1000).saturating_sub(unix_ms_now()));
+    let mut details = ErrorDetails::new();
+    details
+        .set_retry_info(Some(resets_in))
+        .add_quota_failure_violation(
+            exceeded.client.to_string(),
+            format!("{} proofs per {}", exceeded.limit, exceeded.period.name()),
+        );
+    Status::with_error_details(
+        Code::ResourceExhausted,
+        format!("Proof quota exceeded: {exceeded}"),
+        details,
+    )
+}
+
+/// Reported when the service is shutting down, jobs already started resume on restart
+fn shutting_down_status() -> Status {
+    Status::unavailable("Service is shutting down, try again later")
+}
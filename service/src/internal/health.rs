@@ -0,0 +1,144 @@
+use crate::internal::grpc::InclusionServiceArc;
+use crate::internal::programs::ProgramKind;
+use crate::internal::store::StoreTree;
+use crate::InclusionService;
+
+use eq_common::eqs::inclusion_server::InclusionServer;
+use log::{debug, info, warn};
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::server::NamedService;
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+
+/// How often the [Readiness] reported by the gRPC health service is checked
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The service name the Inclusion service's health is reported under.
+/// The server overall is reported under `""`, with the same status.
+pub const INCLUSION_SERVICE_NAME: &str =
+    <InclusionServer<InclusionServiceArc> as NamedService>::NAME;
+
+/// Whether the service can make progress on new [Job](crate::Job)s, see
+/// [InclusionService::readiness]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Readiness {
+    /// Connected to the DA node (or fixtures), see [InclusionService::get_da_client]
+    pub da_client: bool,
+    /// The current ZKStack program's proof setup is loaded, see
+    /// [InclusionService::get_proof_setup]
+    pub zk_setup: bool,
+    /// Reading from or flushing the store failed
+    pub store_error: Option<String>,
+    /// Shutting down, see [InclusionService::shutdown]
+    pub draining: bool,
+}
+
+impl Readiness {
+    /// Why the service isn't ready, empty if it is
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.da_client {
+            problems.push("DA client not connected".to_string());
+        }
+        if !self.zk_setup {
+            problems.push("ZK proof setup not loaded".to_string());
+        }
+        if let Some(e) = &self.store_error {
+            problems.push(format!("store unhealthy: {e}"));
+        }
+        if self.draining {
+            problems.push("shutting down".to_string());
+        }
+        problems
+    }
+
+    pub fn status(&self) -> ServingStatus {
+        match self.problems().is_empty() {
+            true => ServingStatus::Serving,
+            false => ServingStatus::NotServing,
+        }
+    }
+}
+
+impl InclusionService {
+    pub fn readiness(&self) -> Readiness {
+        // A read and flush, so a store that can't reach it's disk is caught
+        let store_error = self
+            .store
+            .get(StoreTree::Config, b"")
+            .and_then(|_| self.store.flush())
+            .err()
+            .map(|e| e.to_string());
+        Readiness {
+            da_client: self.da_client_ready(),
+            zk_setup: self
+                .programs
+                .current(ProgramKind::ZkStackInclusion)
+                .setup
+                .initialized(),
+            store_error,
+            draining: self.drain.is_draining(),
+        }
+    }
+
+    /// Keep the `grpc.health.v1.Health` status of the Inclusion service (and the server
+    /// overall) up to date with [Self::readiness], until shut down.
+    pub async fn health_reporter(self: Arc<Self>, mut reporter: HealthReporter) {
+        debug!("Health reporter started");
+        let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+        let mut reported = None;
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = self.drain.draining() => {}
+            }
+            let readiness = self.readiness();
+            let status = readiness.status();
+            if reported != Some(status) {
+                match status {
+                    ServingStatus::Serving => info!("Service ready, health is SERVING"),
+                    _ => warn!(
+                        "Service not ready, health is NOT_SERVING: {}",
+                        readiness.problems().join(", ")
+                    ),
+                }
+                for service in ["", INCLUSION_SERVICE_NAME] {
+                    reporter.set_service_status(service, status).await;
+                }
+                reported = Some(status);
+            }
+            if readiness.draining {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_readiness() {
+        let ready = Readiness {
+            da_client: true,
+            zk_setup: true,
+            store_error: None,
+            draining: false,
+        };
+        assert!(ready.problems().is_empty());
+        assert_eq!(ready.status(), ServingStatus::Serving);
+
+        let not_ready = Readiness {
+            zk_setup: false,
+            store_error: Some("disk full".to_string()),
+            ..ready
+        };
+        assert_eq!(
+            not_ready.problems(),
+            ["ZK proof setup not loaded", "store unhealthy: disk full"]
+        );
+        assert_eq!(not_ready.status(), ServingStatus::NotServing);
+    }
+}
@@ -1,74 +1,100 @@
-use crate::internal::prom_metrics::PromMetrics;
-use crate::{Job, JobStatus, SP1ProofSetup, SuccNetJobId, SuccNetProgramId};
+use crate::internal::artifacts::{ArtifactStore, StoredProof};
+use crate::internal::auth::Authenticator;
+use crate::internal::da::{DaBackend, FixtureDaBackend};
+use crate::internal::da_cache::{with_replay_protection, DaProofCache};
+use crate::internal::drain::{Drain, DrainGuard};
+use crate::internal::limits::{
+    proof_quota_usage, spend_proof_quota, ClientId, LimitConfig, QuotaExceeded, QuotaUsage,
+    RateLimiter,
+};
+use crate::internal::programs::{ProgramRegistry, ZkProgram};
+use crate::internal::prom_metrics::{PromMetrics, QuotaLabels};
+use crate::internal::prover::{build_zk_backend, ZkBackendError, ZkProofMode, ZkProverBackend};
+use crate::internal::queue::{JobQueue, JobReceivers, JobStage};
+use crate::internal::retention::RetentionConfig;
+use crate::internal::retry::{unix_ms_now, RetryPolicy, RetryState, RETRY_POLL_INTERVAL};
+use crate::internal::schema;
+use crate::internal::store::{JobStore, StoreTree, TransactionError};
+use crate::{
+    Job, JobEvent, JobStatus, MultiBlobJob, SP1ProofSetup, SuccNetJobId, SuccNetProgramId,
+};
 
-use celestia_rpc::{BlobClient, Client as CelestiaJSONClient, HeaderClient, ShareClient};
-use eq_common::{ErrorLabels, InclusionServiceError, ZKStackEqProofInput};
-use jsonrpsee::core::ClientError as JsonRpcError;
-use log::{debug, error, info};
-use sha3::Keccak256;
-use sha3::{Digest, Sha3_256};
-use sled::{Transactional, Tree as SledTree};
-use sp1_sdk::{
-    network::Error as SP1NetworkError, NetworkProver as SP1NetworkProver, Prover,
-    SP1ProofWithPublicValues, SP1Stdin,
+use celestia_rpc::Client as CelestiaJSONClient;
+use celestia_types::{blob::Commitment, nmt::Namespace};
+use eq_common::{
+    BlobInclusionInput, ErrorLabels, InclusionServiceError, PayyEqProofInput, ZKStackEqProofInput,
+    ZKStackMultiBlobEqProofInput,
 };
-use std::sync::Arc;
+use eq_sdk::types::{BlobId, PayyBlobId};
+use jsonrpsee::core::ClientError as JsonRpcError;
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use sha3::{Digest, Keccak256};
+use sp1_sdk::{network::Error as SP1NetworkError, SP1ProofWithPublicValues, SP1Stdin};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, OnceCell};
-
-/// Hardcoded ELF binary for the crate `program-keccak-inclusion`
-static KECCAK_INCLUSION_ELF: &[u8] = include_bytes!(
-    "../../../target/elf-compilation/riscv32im-succinct-zkvm-elf/release/eq-program-keccak-inclusion"
-);
-/// Hardcoded ID for the crate `program-keccak-inclusion`
-static KECCAK_INCLUSION_ID: OnceCell<SuccNetProgramId> = OnceCell::const_new();
-
-/// Given a hard coded ELF, get it's ID
-/// TODO: generalize
-pub async fn get_program_id() -> SuccNetProgramId {
-    *KECCAK_INCLUSION_ID
-        .get_or_init(|| async {
-            debug!("Building Program ID");
-            Sha3_256::digest(KECCAK_INCLUSION_ELF).into()
-        })
-        .await
-}
+use tokio::sync::{broadcast, mpsc, Mutex, Notify, OnceCell, Semaphore, SemaphorePermit};
 
-/// Hardcoded setup for the crate `program-keccak-inclusion`
-static KECCAK_INCLUSION_SETUP: OnceCell<Arc<SP1ProofSetup>> = OnceCell::const_new();
+/// [JobEvent]s buffered for each watcher, a slower watcher misses events
+/// (see [tokio::sync::broadcast::error::RecvError::Lagged])
+const JOB_EVENT_CAPACITY: usize = 1024;
 
 /// The main service, depends on external DA and ZK clients internally!
 pub struct InclusionService {
     pub config: InclusionServiceConfig,
-    da_client_handle: OnceCell<Arc<CelestiaJSONClient>>,
-    zk_client_handle: OnceCell<Arc<SP1NetworkProver>>,
+    da_client_handle: OnceCell<Arc<dyn DaBackend>>,
+    zk_client_handle: OnceCell<Arc<dyn ZkProverBackend>>,
     pub metrics: Arc<PromMetrics>,
-    pub config_db: SledTree,
-    pub queue_db: SledTree,
-    pub finished_db: SledTree,
-    pub job_sender: mpsc::UnboundedSender<Option<Job>>,
+    /// Every [Job]'s state, see [StoreTree]
+    pub store: Arc<dyn JobStore>,
+    pub job_queue: JobQueue,
+    pub programs: ProgramRegistry,
+    /// DA proofs shared by [Job]s for the same blob
+    pub da_cache: DaProofCache,
+    job_events: broadcast::Sender<JobEvent>,
+    /// Signals to stop waiting on a ZK proof request, see [Self::cancel_zk_proof]
+    zk_cancellations: StdMutex<HashMap<SuccNetJobId, Arc<Notify>>>,
+    /// Work to finish before shutting down, see [Self::shutdown]
+    pub drain: Drain,
+    /// How fast each client starts new [Job]s
+    pub rate_limiter: RateLimiter,
+    /// One of [InclusionServiceConfig::zk_concurrency] slots, held while requesting or
+    /// awaiting a ZK proof, see [Self::zk_slot]
+    zk_slots: Semaphore,
 }
 
 impl InclusionService {
     pub fn new(
         config: InclusionServiceConfig,
-        da_client_handle: OnceCell<Arc<CelestiaJSONClient>>,
-        zk_client_handle: OnceCell<Arc<SP1NetworkProver>>,
+        da_client_handle: OnceCell<Arc<dyn DaBackend>>,
+        zk_client_handle: OnceCell<Arc<dyn ZkProverBackend>>,
         metrics: Arc<PromMetrics>,
-        config_db: SledTree,
-        queue_db: SledTree,
-        finished_db: SledTree,
-        job_sender: mpsc::UnboundedSender<Option<Job>>,
+        store: Arc<dyn JobStore>,
+        job_queue: JobQueue,
+        programs: ProgramRegistry,
     ) -> Self {
+        let da_cache = DaProofCache::new(
+            config.da_cache_capacity,
+            metrics.da_cache_hits.clone(),
+            metrics.da_cache_misses.clone(),
+        );
+        let zk_slots = Semaphore::new(config.zk_concurrency);
         InclusionService {
             config,
             da_client_handle,
             zk_client_handle,
             metrics,
-            config_db,
-            queue_db,
-            finished_db,
-            job_sender,
+            store,
+            job_queue,
+            programs,
+            da_cache,
+            job_events: broadcast::channel(JOB_EVENT_CAPACITY).0,
+            zk_cancellations: StdMutex::new(HashMap::new()),
+            drain: Drain::default(),
+            rate_limiter: RateLimiter::default(),
+            zk_slots,
         }
     }
 }
@@ -76,7 +102,28 @@ impl InclusionService {
 pub struct InclusionServiceConfig {
     pub da_node_token: String,
     pub da_node_http: String,
+    /// Serve DA data from `blob-tool` fixtures in this directory, rather than a Celestia node
+    pub da_fixture_dir: Option<PathBuf>,
     pub zk_proof_gen_timeout: Duration,
+    pub zk_proof_mode: ZkProofMode,
+    /// Max [Job]s collecting DA data at once
+    pub da_concurrency: usize,
+    /// Max blobs with DA proofs cached for other [Job]s of the same blob, see [DaProofCache]
+    pub da_cache_capacity: usize,
+    /// Max [Job]s requesting or awaiting ZK proofs at once
+    pub zk_concurrency: usize,
+    /// Automatic retries of retryable [JobStatus::Failed] jobs
+    pub retry_policy: RetryPolicy,
+    /// How long finished [Job]s are kept, and if their proofs are compacted
+    pub retention: RetentionConfig,
+    /// Where finished proofs are kept, in the DB if `None`
+    pub artifact_store: Option<ArtifactStore>,
+    /// How long a shutdown waits on in-flight [Job]s to save their progress
+    pub shutdown_deadline: Duration,
+    /// Who may use the Inclusion gRPC service, anyone if `None`
+    pub auth: Option<Authenticator>,
+    /// Rate limits and proof quotas of each tenant
+    pub limits: LimitConfig,
 }
 
 impl InclusionService {
@@ -87,45 +134,94 @@ impl InclusionService {
     /// Once a step is completed, `JobStatus` is recorded into the queue database that
     /// recursively, driving to `Job` completion.
     ///
+    /// Each [JobStage] has it's own bounded queue, worked on by a fixed number of tasks
+    /// (see [InclusionServiceConfig]), so a burst of jobs waits in the queue rather than
+    /// opening many DA and prover connections at once.
+    ///
     /// When a successful or failed state is arrived at,
     /// the job is atomically removed from the queue and added to a results database.
-    pub async fn job_worker(
-        self: Arc<Self>,
-        mut job_receiver: mpsc::UnboundedReceiver<Option<Job>>,
-    ) {
+    ///
+    /// Returns once a [Self::shutdown] has drained in-flight work and flushed the DB.
+    pub async fn job_worker(self: Arc<Self>, job_receivers: JobReceivers) {
         debug!("Job worker started");
-        while let Some(Some(job)) = job_receiver.recv().await {
-            let service = self.clone();
-            let metrics = self.metrics.clone();
-            tokio::spawn(async move {
-                debug!("Job worker received {job:?}",);
-                let _ = service.prove(job).await.map_err(|e| {
-                    debug!("COUNTED ERROR METRIC ---{e:?}");
-                    count_error(&metrics, e)
-                }); //Don't return with "?", we run keep looping
-            });
+        let da_receiver = Arc::new(Mutex::new(job_receivers.da_receiver));
+        for _ in 0..self.config.da_concurrency {
+            tokio::spawn(
+                self.clone()
+                    .stage_worker(da_receiver.clone(), JobStage::DataAvailability),
+            );
         }
+        let zk_receiver = Arc::new(Mutex::new(job_receivers.zk_receiver));
+        for _ in 0..self.config.zk_concurrency {
+            tokio::spawn(
+                self.clone()
+                    .stage_worker(zk_receiver.clone(), JobStage::ZkProof),
+            );
+        }
+
+        self.drain.draining().await;
 
-        info!("Shutting down");
-        let _ = self.queue_db.flush();
-        let _ = self.finished_db.flush();
+        let deadline = self.config.shutdown_deadline;
+        info!("Shutting down, waiting up to {deadline:?} for in-flight jobs to save progress");
+        if !self.drain.wait_idle(deadline).await {
+            warn!("Shutdown deadline passed, unsaved work will be redone on restart");
+        }
+        let _ = self.store.flush();
         info!("Cleanup complete");
+    }
+
+    /// Work on one [Job] at a time from a [JobStage]'s queue, shared with other workers.
+    async fn stage_worker(
+        self: Arc<Self>,
+        job_receiver: Arc<Mutex<mpsc::Receiver<Job>>>,
+        stage: JobStage,
+    ) {
+        let in_flight = match stage {
+            JobStage::DataAvailability => &self.metrics.da_jobs_in_flight,
+            JobStage::ZkProof => &self.metrics.zk_jobs_in_flight,
+        };
+        loop {
+            let Some(job) = job_receiver.lock().await.recv().await else {
+                break;
+            };
+            self.update_queue_depth(stage);
+            // Shared with aggregation proofs, only taken once there is a job so idle workers
+            // don't keep aggregations waiting
+            let _zk_slot = match stage {
+                JobStage::DataAvailability => None,
+                JobStage::ZkProof => Some(self.zk_slot().await),
+            };
+            debug!("{stage:?} worker received {job:?}");
+            in_flight.inc();
+            let _ = self.prove(job).await.map_err(|e| {
+                debug!("COUNTED ERROR METRIC ---{e:?}");
+                count_error(&self.metrics, e)
+            }); //Don't return with "?", we run keep looping
+            in_flight.dec();
+        }
+    }
 
-        std::process::exit(0);
+    /// Wait for one of the [InclusionServiceConfig::zk_concurrency] slots for requesting
+    /// and awaiting ZK proofs, shared by the [JobStage::ZkProof] workers and
+    /// [AggregationJob](crate::internal::aggregation::AggregationJob)s.
+    pub async fn zk_slot(&self) -> SemaphorePermit<'_> {
+        self.zk_slots
+            .acquire()
+            .await
+            .expect("ZK slots are never closed")
     }
 
     /// The main service task: produce a proof based on a [Job] requested.
     pub async fn prove(&self, job: Job) -> Result<(), InclusionServiceError> {
-        let job_key = bincode::serialize(&job)
-            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
-        if let Some(queue_data) = self
-            .queue_db
-            .get(&job_key)
-            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?
-        {
-            let mut job_status: JobStatus = bincode::deserialize(&queue_data)
+        let job_key = job.key()?;
+        if let Some(queue_data) = self.store.get(StoreTree::Queue, &job_key)? {
+            let job_status: JobStatus = schema::decode(&queue_data)
                 .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
             debug!("Job worker processing with starting status: {job_status:?}");
+            let Some(saving) = self.drain.start() else {
+                debug!("Draining, {job:?} is left in the queue to resume on restart");
+                return Ok(());
+            };
             match job_status {
                 JobStatus::DataAvailabilityPending => {
                     let da_client_handle = self.get_da_client().await.clone();
@@ -133,37 +229,23 @@ impl InclusionService {
                         .await?;
                     debug!("DA data -> zk input ready");
                 }
+                // Each kind of job has it's own input, proven by the job's program
                 JobStatus::DataAvailable(proof_input) => {
-                    // TODO handle non-hardcoded ZK programs
-                    match self
-                        .request_zk_proof(&get_program_id().await, &proof_input, &job, &job_key)
-                        .await
-                    {
-                        Ok(zk_job_id) => {
-                            debug!("Proof request {zk_job_id:?} started");
-                            job_status = JobStatus::ZkProofPending(zk_job_id);
-                            self.send_job_with_new_status(job_key, job_status, job)?;
-                        }
-                        Err(e) => {
-                            error!("{job:?} failed progressing DataAvailable: {e}");
-                            // NOTE: we internally finalize the job in `handle_da_client_error`
-                        }
-                    };
+                    self.prove_zk_input(&proof_input, &job, &job_key, saving)
+                        .await?;
+                }
+                JobStatus::DataAvailableMultiBlob(proof_input) => {
+                    self.prove_zk_input(&proof_input, &job, &job_key, saving)
+                        .await?;
+                }
+                JobStatus::DataAvailablePayy(proof_input) => {
+                    self.prove_zk_input(&proof_input, &job, &job_key, saving)
+                        .await?;
                 }
                 JobStatus::ZkProofPending(zk_request_id) => {
-                    debug!("ZK request waiting");
-                    match self.wait_for_zk_proof(&job, &job_key, zk_request_id).await {
-                        Ok(zk_proof) => {
-                            info!("🎉 {job:?} Finished!");
-                            job_status = JobStatus::ZkProofFinished(zk_proof);
-                            self.finalize_job(&job_key, job_status)?;
-                            self.metrics.jobs_finished.inc();
-                        }
-                        Err(e) => {
-                            error!("{job:?} failed progressing ZkProofPending: {e}");
-                            // NOTE: we internally finalize the job in `handle_zk_client_error`
-                        }
-                    }
+                    // Resumed from the stored request ID if shut down while waiting
+                    drop(saving);
+                    self.finish_zk_proof(&job, &job_key, zk_request_id).await?;
                 }
                 _ => error!("Queue has INVALID status! Finished jobs stuck in queue!"),
             }
@@ -171,47 +253,175 @@ impl InclusionService {
         Ok(())
     }
 
-    /// Given a SHA3 hash of a ZK program, get the require setup.
+    /// Request a ZK proof of a [Job]'s input with it's [ZkProgram], wait on it,
+    /// and finalize the `Job` with it's result.
+    /// `saving` is held until the request is stored, so a shutdown can resume it.
+    async fn prove_zk_input(
+        &self,
+        proof_input: &impl Serialize,
+        job: &Job,
+        job_key: &[u8],
+        saving: DrainGuard<'_>,
+    ) -> Result<(), InclusionServiceError> {
+        let program = self.job_program(job, job_key)?;
+        if !self.spend_proof_quota(job, job_key)? {
+            return Ok(());
+        }
+        match self
+            .request_zk_proof(&program, proof_input, job, job_key)
+            .await
+        {
+            Ok(zk_job_id) => {
+                debug!("Proof request {zk_job_id:?} started");
+                // We hold a ZK stage slot already, so we wait on the proof
+                // here rather than sending it back to the queue
+                self.store_job_status(job_key.to_vec(), JobStatus::ZkProofPending(zk_job_id), job)?;
+                drop(saving);
+                self.finish_zk_proof(job, job_key, zk_job_id).await?;
+            }
+            Err(e) => {
+                error!("{job:?} failed progressing DataAvailable: {e}");
+                // NOTE: we internally finalize the job in `handle_zk_client_error`
+            }
+        };
+        Ok(())
+    }
+
+    /// Wait on a ZK proof request, and finalize the [Job] with it's result.
+    async fn finish_zk_proof(
+        &self,
+        job: &Job,
+        job_key: &[u8],
+        zk_request_id: SuccNetJobId,
+    ) -> Result<(), InclusionServiceError> {
+        debug!("ZK request waiting");
+        match self.wait_for_zk_proof(job, job_key, zk_request_id).await {
+            Ok(zk_proof) => {
+                info!("🎉 {job:?} Finished!");
+                let job_status = self.finished_proof_status(job, zk_proof).await;
+                self.finalize_job(job_key, job_status)?;
+                self.metrics.jobs_finished.inc();
+            }
+            Err(e) => {
+                error!("{job:?} failed progressing ZkProofPending: {e}");
+                // NOTE: we internally finalize the job in `handle_zk_client_error`
+            }
+        }
+        Ok(())
+    }
+
+    /// The [JobStatus] to store a finished proof with: a [JobStatus::ZkProofStored] with the
+    /// proof in the [ArtifactStore], if there is one, otherwise the proof itself.
+    ///
+    /// If the proof can't be stored in the `ArtifactStore`, it's kept in the DB instead.
+    /// [Job::AggregationMember] proofs are always kept in the DB, to be read back whole
+    /// by their aggregation.
+    async fn finished_proof_status(
+        &self,
+        job: &Job,
+        zk_proof: SP1ProofWithPublicValues,
+    ) -> JobStatus {
+        let (Some(artifacts), false) = (
+            &self.config.artifact_store,
+            matches!(job, Job::AggregationMember(_)),
+        ) else {
+            return JobStatus::ZkProofFinished(zk_proof);
+        };
+        let proof_data = zk_proof.bytes();
+        let proof_data_len = proof_data.len() as u64;
+        match artifacts.put(proof_data).await {
+            Ok(id) => JobStatus::ZkProofStored(StoredProof {
+                proof_data: id,
+                proof_data_len,
+                public_values: zk_proof.public_values.to_vec(),
+            }),
+            Err(e) => {
+                error!("Failed to store proof artifact, keeping it in the DB: {e}");
+                JobStatus::ZkProofFinished(zk_proof)
+            }
+        }
+    }
+
+    /// The [ZkProgram] to prove a [Job] with.
+    ///
+    /// A `Job` is pinned to the current program of it's kind on it's first proof request,
+    /// so a newly rolled out program is only used by `Job`s that have not got that far yet.
+    fn job_program(
+        &self,
+        job: &Job,
+        job_key: &[u8],
+    ) -> Result<Arc<ZkProgram>, InclusionServiceError> {
+        let kind = job.program_kind();
+        if let Some(program_id) = self.get_job_program_id(job_key)? {
+            match self.programs.get(&program_id) {
+                Some(program) => return Ok(program),
+                None => warn!(
+                    "{job:?} was proven with unknown program 0x{} - using the current {kind:?} program",
+                    hex::encode(program_id)
+                ),
+            }
+        }
+        let program = self.programs.current(kind);
+        let program_id_data = schema::encode(&program.id)
+            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
+        self.store
+            .insert(StoreTree::JobProgram, job_key, &program_id_data)?;
+        Ok(program)
+    }
+
+    /// The ID of the [ZkProgram] a [Job] is proven with, if it has requested a proof
+    pub fn get_job_program_id(
+        &self,
+        job_key: &[u8],
+    ) -> Result<Option<SuccNetProgramId>, InclusionServiceError> {
+        self.store
+            .get(StoreTree::JobProgram, job_key)?
+            .map(|program_id_data| {
+                schema::decode(&program_id_data).map_err(|e| {
+                    InclusionServiceError::InternalError(format!("Invalid program ID in DB: {e}"))
+                })
+            })
+            .transpose()
+    }
+
+    /// Given a [ZkProgram], get the require setup.
     /// The setup is a very heavy task and produces a large output (~200MB),
-    /// fortunately it's identical per ZK program, so we store this in a DB to recall it.
+    /// fortunately it's identical per ZK program, so we store this in a DB
+    /// (keyed by the program's SHA3 hash) to recall it.
     /// We load it and return a pointer to a single instance of this large setup object
     /// to read from for many concurrent [Job]s.
     pub async fn get_proof_setup(
         &self,
-        zk_program_elf_sha3: &[u8; 32],
-        zk_client_handle: Arc<SP1NetworkProver>,
+        program: &ZkProgram,
+        zk_client_handle: Arc<dyn ZkProverBackend>,
     ) -> Result<Arc<SP1ProofSetup>, InclusionServiceError> {
-        debug!("Getting ZK program proof setup");
-        let setup = KECCAK_INCLUSION_SETUP
+        debug!("Getting ZK program proof setup for {:?}", program.kind);
+        let zk_program_elf_sha3 = program.id;
+        let setup = program
+            .setup
             .get_or_try_init(|| async {
                 // Check DB for existing pre-computed setup
-                let precomputed_proof_setup = self
-                    .config_db
-                    .get(zk_program_elf_sha3)
-                    .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
+                let precomputed_proof_setup =
+                    self.store.get(StoreTree::Config, &zk_program_elf_sha3)?;
 
                 let proof_setup = match precomputed_proof_setup { Some(precomputed) => {
-                    bincode::deserialize(&precomputed)
+                    schema::decode(&precomputed)
                         .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?
                 } _ => {
                     info!(
-                        "No ZK proof setup in DB for SHA3_256 = 0x{} -- generation & storing in config DB",
-                        hex::encode(zk_program_elf_sha3)
+                        "No ZK proof setup in DB for SHA3_256 = 0x{} -- generation with {} prover & storing in config DB",
+                        hex::encode(zk_program_elf_sha3),
+                        zk_client_handle.name()
                     );
 
-                    let new_proof_setup: SP1ProofSetup = tokio::task::spawn_blocking(move || {
-                        zk_client_handle.setup(KECCAK_INCLUSION_ELF).into()
-                    })
-                    .await
-                    .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
+                    let new_proof_setup = zk_client_handle.setup(program.elf).await?;
 
-                    self.config_db
-                        .insert(
-                        zk_program_elf_sha3,
-                        bincode::serialize(&new_proof_setup)
+                    self.store.insert(
+                        StoreTree::Config,
+                        &zk_program_elf_sha3,
+                        &schema::encode(&new_proof_setup)
                             .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?,
-                        )
-                        .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
+                    )?;
 
                     new_proof_setup
                 }};
@@ -223,78 +433,31 @@ impl InclusionService {
         Ok(setup)
     }
 
-    /// Connects to a [CelestiaJSONClient] and attempts to get a inclusion proof for a [Job].
-    /// On `Ok(())`, the queue DB contains valid ZKP input inside a new [JobStatus::DataAvailable] on the queue.
+    /// Connects to a [DaBackend] and attempts to get a inclusion proof for a [Job].
+    /// On `Ok(())`, the queue DB contains valid ZKP input inside a new [JobStatus::DataAvailable]
+    /// (or the variant for the kind of `Job`) on the queue.
     async fn get_zk_proof_input_from_da(
         &self,
         job: &Job,
         job_key: &[u8],
-        client: Arc<CelestiaJSONClient>,
+        client: Arc<dyn DaBackend>,
     ) -> Result<(), InclusionServiceError> {
-        debug!("Preparing request to Celestia");
-
-        let header = client
-            .header_get_by_height(job.height.into())
-            .await
-            .map_err(|e| self.handle_da_client_error(e, job, job_key))?;
-
-        let eds_row_roots = header.dah.row_roots();
-        let eds_size: u64 = eds_row_roots.len().try_into().map_err(|_| {
-            InclusionServiceError::InternalError(
-                "Failed to convert eds_row_roots.len() to u64".to_string(),
-            )
-        })?;
-        let ods_size: u64 = eds_size / 2;
-
-        let blob = client
-            .blob_get(job.height.into(), job.namespace, job.commitment)
-            .await
-            .map_err(|e| self.handle_da_client_error(e, job, job_key))?;
-
-        let blob_index = blob
-            .index
-            .ok_or_else(|| InclusionServiceError::MissingBlobIndex)?;
-
-        // https://github.com/celestiaorg/eq-service/issues/65
-        //let first_row_index: u64 = blob_index.div_ceil(eds_size) - 1;
-        let first_row_index: u64 =
-            blob.index.ok_or(InclusionServiceError::MissingBlobIndex)? / eds_size;
-        let ods_index = blob_index - (first_row_index * ods_size);
-
-        let range_response = client
-            .share_get_range(&header, ods_index, ods_index + blob.shares_len() as u64)
-            .await
-            .map_err(|e| self.handle_da_client_error(e, job, job_key))?;
-
-        range_response
-            .proof
-            .verify(header.dah.hash())
-            .map_err(|_| InclusionServiceError::FailedShareRangeProofSanityCheck)?;
-
-        let keccak_hash: [u8; 32] = Keccak256::new().chain_update(&blob.data).finalize().into();
-
-        debug!("Creating ZK Proof input from Celestia Data");
-        let proof_input = ZKStackEqProofInput {
-            data: blob.data,
-            namespace_id: job.namespace,
-            share_proofs: range_response.proof.share_proofs,
-            row_proof: range_response.proof.row_proof,
-            data_root: header.dah.hash().as_bytes().try_into().map_err(|_| {
-                InclusionServiceError::InternalError(
-                    "Failed to convert header.dah.hash().as_bytes() to [u8; 32]".to_string(),
-                )
-            })?,
-            keccak_hash,
-            batch_number: job.batch_number,
-            chain_id: job.l2_chain_id,
-            author: blob.signer,
+        let on_da_error = |e| self.handle_da_client_error(e, job, job_key);
+        let (client, cache) = (client.as_ref(), &self.da_cache);
+        let job_status = match job {
+            Job::ZkStack(blob_id) | Job::AggregationMember(blob_id) => JobStatus::DataAvailable(
+                build_zk_proof_input(blob_id, client, cache, on_da_error).await?,
+            ),
+            Job::ZkStackMultiBlob(multi_blob) => JobStatus::DataAvailableMultiBlob(
+                build_multi_blob_proof_input(multi_blob, client, cache, on_da_error).await?,
+            ),
+            Job::Payy(payy_blob_id) => JobStatus::DataAvailablePayy(
+                build_payy_proof_input(payy_blob_id, client, cache, on_da_error).await?,
+            ),
         };
 
-        self.send_job_with_new_status(
-            job_key.to_vec(),
-            JobStatus::DataAvailable(proof_input),
-            job.clone(),
-        )
+        self.send_job_with_new_status(job_key.to_vec(), job_status, job.clone())
+            .await
     }
 
     /// Helper function to handle error from a [jsonrpsee] based DA client.
@@ -371,16 +534,54 @@ impl InclusionService {
         }
     }
 
-    /// Helper function to handle error from a SP1 NetworkProver Clients.
+    /// Helper function to handle error from a [ZkProverBackend].
     /// Will finalize the job in an [JobStatus::Failed] state,
     /// that may be retryable.
     fn handle_zk_client_error(
         &self,
-        zk_client_error: &SP1NetworkError,
+        zk_client_error: &ZkBackendError,
         job: &Job,
         job_key: &[u8],
     ) -> InclusionServiceError {
-        error!("SP1 Client error: {zk_client_error}");
+        error!("ZK Client error: {zk_client_error}");
+        let (e, job_status);
+        match zk_client_error {
+            ZkBackendError::Network(network_error) => {
+                (e, job_status) = self.network_error_status(network_error, job);
+            }
+            ZkBackendError::LocalExecution(_) => {
+                e = InclusionServiceError::ZkClientError(format!(
+                    "ZKP program critical failure: {zk_client_error} occurred for {job:?} PLEASE REPORT!"
+                ));
+                job_status = JobStatus::Failed(e.clone(), None);
+            }
+            ZkBackendError::UnknownRequest(_) => {
+                e = InclusionServiceError::ZkClientError(format!(
+                    "ZKP prover: {zk_client_error} for {job:?} - callback to start the job over"
+                ));
+                job_status =
+                    JobStatus::Failed(e.clone(), Some(JobStatus::DataAvailabilityPending.into()));
+            }
+            ZkBackendError::Unhandled(_) => {
+                error!("UNHANDLED ZK client error: {zk_client_error}");
+                // NOTE: we don't finalize the job, as we don't know what state it is in
+                return InclusionServiceError::ZkClientError(format!(
+                    "Unhandled Error: {zk_client_error} PLEASE REPORT"
+                ));
+            }
+        }
+        match self.finalize_job(job_key, job_status) {
+            Ok(_) => e,
+            Err(internal_err) => internal_err,
+        }
+    }
+
+    /// Map an error from Succinct's prover network into a [JobStatus::Failed]
+    fn network_error_status(
+        &self,
+        zk_client_error: &SP1NetworkError,
+        job: &Job,
+    ) -> (InclusionServiceError, JobStatus) {
         let (e, job_status);
         match zk_client_error {
             SP1NetworkError::SimulationFailed | SP1NetworkError::RequestUnexecutable { .. } => {
@@ -421,69 +622,69 @@ impl InclusionService {
                 job_status = JobStatus::Failed(e.clone(), None);
             }
         }
-        match self.finalize_job(job_key, job_status) {
-            Ok(_) => e,
-            Err(internal_err) => internal_err,
-        }
+        (e, job_status)
     }
 
-    /// Start a proof request from Succinct's prover network
+    /// Start a proof request with the configured [ZkProverBackend]
     pub async fn request_zk_proof(
         &self,
-        program_id: &SuccNetProgramId,
-        proof_input: &ZKStackEqProofInput,
+        program: &ZkProgram,
+        proof_input: &impl Serialize,
         job: &Job,
         job_key: &[u8],
     ) -> Result<SuccNetJobId, InclusionServiceError> {
-        debug!("Preparing prover network request and starting proving");
-        let zk_client_handle = self.get_zk_client_remote().await;
+        debug!("Preparing prover request and starting proving");
+        let zk_client_handle = self.get_zk_client().await;
         let proof_setup = self
-            .get_proof_setup(program_id, zk_client_handle.clone())
+            .get_proof_setup(program, zk_client_handle.clone())
             .await?;
 
         let mut stdin = SP1Stdin::new();
         stdin.write(&proof_input);
-        let request_id: SuccNetJobId = zk_client_handle
-            .prove(&proof_setup.pk, &stdin)
-            .groth16()
-            .skip_simulation(false)
-            .timeout(self.config.zk_proof_gen_timeout)
-            .request_async()
+        let request_id = zk_client_handle
+            .request_proof(
+                proof_setup,
+                stdin,
+                job.proof_mode(),
+                self.config.zk_proof_gen_timeout,
+            )
             .await
-            // TODO: how to handle errors without a concrete type? Anyhow is not the right thing for us...
-            .map_err(|e| {
-                if let Some(down) = e.downcast_ref::<SP1NetworkError>() {
-                    return self.handle_zk_client_error(down, job, job_key);
-                }
-                error!("UNHANDLED ZK client error: {e:?}");
-                InclusionServiceError::ZkClientError(format!("Unhandled Error: {e} PLEASE REPORT"))
-            })?
-            .into();
+            .map_err(|e| self.handle_zk_client_error(&e, job, job_key))?;
 
         Ok(request_id)
     }
 
-    /// Await a proof request from Succinct's prover network
+    /// Await a proof request from the configured [ZkProverBackend]
     async fn wait_for_zk_proof(
         &self,
         job: &Job,
         job_key: &[u8],
         request_id: SuccNetJobId,
     ) -> Result<SP1ProofWithPublicValues, InclusionServiceError> {
-        debug!("Waiting for proof from prover network");
+        debug!("Waiting for proof from prover");
         let start_time = Instant::now();
-        let zk_client_handle = self.get_zk_client_remote().await;
+        let zk_client_handle = self.get_zk_client().await;
 
-        let proof = zk_client_handle
-            .wait_proof(request_id.into(), None, None)
-            .await
-            .map_err(|e| {
-                if let Some(down) = e.downcast_ref::<SP1NetworkError>() {
-                    return self.handle_zk_client_error(down, job, job_key);
-                }
-                error!("UNHANDLED ZK client error: {e:?}");
-                InclusionServiceError::ZkClientError(format!("Unhandled Error: {e} PLEASE REPORT"))
-            })?;
+        let cancellation = self.zk_cancellation(request_id);
+        let proof = tokio::select! {
+            proof = zk_client_handle.wait_proof(request_id) => proof,
+            _ = cancellation.notified() => {
+                self.remove_zk_cancellation(request_id);
+                info!("{job:?} proof request cancelled");
+                let _ = zk_client_handle
+                    .cancel(request_id)
+                    .await
+                    .map_err(|e| debug!("Prover could not cancel request: {e}"));
+                let e = InclusionServiceError::Cancelled(format!(
+                    "Proof request 0x{}",
+                    hex::encode(request_id)
+                ));
+                self.finalize_job(job_key, JobStatus::Failed(e.clone(), None))?;
+                return Err(e);
+            }
+        };
+        self.remove_zk_cancellation(request_id);
+        let proof = proof.map_err(|e| self.handle_zk_client_error(&e, job, job_key))?;
 
         // Record the time taken to wait for the ZK proof
         let duration = start_time.elapsed();
@@ -494,90 +695,747 @@ impl InclusionService {
         Ok(proof)
     }
 
+    /// Stop waiting on the ZK proof request of a [Job] in [JobStatus::ZkProofPending],
+    /// finalizing it as a permanent [InclusionServiceError::Cancelled] failure.
+    ///
+    /// Only a request a worker is waiting on can be cancelled, returns whether it was.
+    pub fn cancel_zk_proof(
+        &self,
+        job_key: &[u8],
+        request_id: SuccNetJobId,
+    ) -> Result<bool, InclusionServiceError> {
+        let job_status = self
+            .store
+            .get(StoreTree::Queue, job_key)?
+            .map(|v| {
+                schema::decode(&v).map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+            })
+            .transpose()?;
+        if !matches!(job_status, Some(JobStatus::ZkProofPending(id)) if id == request_id) {
+            return Ok(false);
+        }
+        // Never added here, so a cancel no worker sees can't cancel a later wait
+        let cancellation = self
+            .zk_cancellations
+            .lock()
+            .expect("ZK cancellations lock poisoned")
+            .get(&request_id)
+            .cloned();
+        Ok(cancellation.map(|waiter| waiter.notify_one()).is_some())
+    }
+
+    /// Registers a worker waiting on a ZK proof request, until [Self::remove_zk_cancellation]
+    fn zk_cancellation(&self, request_id: SuccNetJobId) -> Arc<Notify> {
+        self.zk_cancellations
+            .lock()
+            .expect("ZK cancellations lock poisoned")
+            .entry(request_id)
+            .or_default()
+            .clone()
+    }
+
+    fn remove_zk_cancellation(&self, request_id: SuccNetJobId) {
+        self.zk_cancellations
+            .lock()
+            .expect("ZK cancellations lock poisoned")
+            .remove(&request_id);
+    }
+
     /// Atomically move a job from the database queue tree to the proof tree.
     /// This removes the job from any further processing by workers.
     /// The [JobStatus] should be success or failure only
     /// (but this is not enforced or checked at this time)
+    ///
+    /// A retryable [JobStatus::Failed] is scheduled for an automatic retry by the
+    /// [Self::retry_scheduler], unless it has no retry attempts left per the [RetryPolicy],
+    /// in which case it is stored (and reported in a [JobEvent]) as a permanent failure.
     fn finalize_job(
         &self,
         job_key: &[u8],
         job_status: JobStatus,
     ) -> Result<(), InclusionServiceError> {
+        let policy = &self.config.retry_policy;
         // TODO: do we want to do a status check here? To prevent accidentally getting into a DB invalid state
-        (&self.queue_db, &self.finished_db)
-            .transaction(|(queue_tx, finished_tx)| {
-                queue_tx.remove(job_key)?;
-                finished_tx.insert(
-                    job_key,
-                    bincode::serialize(&job_status).expect("Always given serializable job status"),
+        let finished_at =
+            schema::encode(&unix_ms_now()).expect("Always given serializable finish time");
+        let retries_exhausted = self.store.transaction(
+            &[
+                StoreTree::Queue,
+                StoreTree::Finished,
+                StoreTree::Retry,
+                StoreTree::FinishedAt,
+            ],
+            |tx| {
+                let previous_retry: RetryState = tx
+                    .get(StoreTree::Retry, job_key)?
+                    .and_then(|v| schema::decode(&v).ok())
+                    .unwrap_or_default();
+                let mut retries_exhausted = false;
+                // No more retries are scheduled, but keep the attempt count for reporting
+                let no_next_retry = schema::encode(&RetryState {
+                    next_retry_unix_ms: 0,
+                    ..previous_retry
+                })
+                .expect("Always given serializable retry state");
+                let final_status = match &job_status {
+                    JobStatus::ZkProofFinished(_) | JobStatus::ZkProofStored(_) => {
+                        tx.remove(StoreTree::Retry, job_key)?;
+                        schema::encode(&job_status)
+                    }
+                    JobStatus::Failed(
+                        InclusionServiceError::QuotaExceeded {
+                            retry_at_unix_ms, ..
+                        },
+                        Some(_),
+                    ) => {
+                        // Retried once the quota resets, without using a retry attempt
+                        let next_retry = RetryState {
+                            next_retry_unix_ms: *retry_at_unix_ms,
+                            ..previous_retry
+                        };
+                        tx.insert(
+                            StoreTree::Retry,
+                            job_key,
+                            &schema::encode(&next_retry)
+                                .expect("Always given serializable retry state"),
+                        )?;
+                        schema::encode(&job_status)
+                    }
+                    JobStatus::Failed(e, Some(_)) => match policy.next_state(previous_retry, e) {
+                        Some(next_retry) => {
+                            tx.insert(
+                                StoreTree::Retry,
+                                job_key,
+                                &schema::encode(&next_retry)
+                                    .expect("Always given serializable retry state"),
+                            )?;
+                            schema::encode(&job_status)
+                        }
+                        None => {
+                            retries_exhausted = true;
+                            tx.insert(StoreTree::Retry, job_key, &no_next_retry)?;
+                            schema::encode(&JobStatus::Failed(e.clone(), None))
+                        }
+                    },
+                    JobStatus::Failed(_, None) if previous_retry.attempts > 0 => {
+                        tx.insert(StoreTree::Retry, job_key, &no_next_retry)?;
+                        schema::encode(&job_status)
+                    }
+                    _ => schema::encode(&job_status),
+                }
+                .expect("Always given serializable job status");
+                tx.remove(StoreTree::Queue, job_key)?;
+                tx.insert(StoreTree::Finished, job_key, &final_status)?;
+                tx.insert(StoreTree::FinishedAt, job_key, &finished_at)?;
+                Ok(retries_exhausted)
+            },
+        )?;
+        let job_status = match job_status {
+            JobStatus::Failed(e, Some(_)) if retries_exhausted => {
+                warn!(
+                    "Job out of retry attempts ({}), now a permanent failure",
+                    policy.max_attempts
+                );
+                self.metrics.jobs_retries_exhausted.inc();
+                JobStatus::Failed(e, None)
+            }
+            job_status => job_status,
+        };
+        self.publish_job_event(job_key.to_vec(), job_status);
+        Ok(())
+    }
+
+    /// Count a [Job]'s proof against it's tenant's quotas, before it is requested.
+    /// If over a quota, the `Job` is failed to retry once the quota resets, and `false` returned.
+    /// A `Job` is counted once, when first requested: retried requests are not counted again.
+    /// `Job`s with no recorded tenant (started before tenants were recorded) are not counted.
+    pub(crate) fn spend_proof_quota(
+        &self,
+        job: &Job,
+        job_key: &[u8],
+    ) -> Result<bool, InclusionServiceError> {
+        let Err(exceeded) = self.spend_proof_quota_once(job_key, job.chain_id())? else {
+            return Ok(true);
+        };
+        warn!("{job:?} not proven yet: {exceeded}");
+        // Retried from it's current status, so the DA data is kept
+        let retry_status = self
+            .store
+            .get(StoreTree::Queue, job_key)?
+            .map(|data| schema::decode::<JobStatus>(&data))
+            .transpose()
+            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?
+            .unwrap_or(JobStatus::DataAvailabilityPending);
+        self.finalize_job(
+            job_key,
+            JobStatus::Failed(exceeded.into(), Some(retry_status.into())),
+        )?;
+        Ok(false)
+    }
+
+    /// Count a proof against the quotas of the tenant recorded for `key` (of a [Job], or an
+    /// [AggregationJob](crate::internal::aggregation::AggregationJob)) and `chain_id`,
+    /// unless already counted for `key` or no tenant is recorded.
+    pub fn spend_proof_quota_once(
+        &self,
+        key: &[u8],
+        chain_id: u64,
+    ) -> Result<Result<(), QuotaExceeded>, InclusionServiceError> {
+        if self.store.get(StoreTree::QuotaSpent, key)?.is_some() {
+            return Ok(Ok(()));
+        }
+        let Some(tenant) = self.job_tenant(key)? else {
+            return Ok(Ok(()));
+        };
+        let client = ClientId { tenant, chain_id };
+        let limits = self.config.limits.for_tenant(&client.tenant);
+        let now_s = unix_ms_now() / 1000;
+        match spend_proof_quota(self.store.as_ref(), &client, limits, now_s)? {
+            Ok(usage) => {
+                self.report_quota_usage(&client, usage);
+                self.store.insert(
+                    StoreTree::QuotaSpent,
+                    key,
+                    &schema::encode(&now_s).expect("Always given serializable time"),
                 )?;
-                Ok::<(), sled::transaction::ConflictableTransactionError<InclusionServiceError>>(())
+                Ok(Ok(()))
+            }
+            Err(exceeded) => {
+                self.metrics
+                    .quota_exceeded
+                    .get_or_create(&(&client).into())
+                    .inc();
+                Ok(Err(exceeded))
+            }
+        }
+    }
+
+    /// If a client is at a proof quota, checked before starting a new [Job] for it
+    pub fn check_proof_quota(
+        &self,
+        client: &ClientId,
+    ) -> Result<Result<(), QuotaExceeded>, InclusionServiceError> {
+        let limits = self.config.limits.for_tenant(&client.tenant);
+        if limits.proofs_per_day.is_none() && limits.proofs_per_month.is_none() {
+            return Ok(Ok(()));
+        }
+        Ok(
+            proof_quota_usage(self.store.as_ref(), client, limits, unix_ms_now() / 1000)?
+                .map(|usage| self.report_quota_usage(client, usage)),
+        )
+    }
+
+    fn report_quota_usage(&self, client: &ClientId, usage: QuotaUsage) {
+        for (period, used) in usage {
+            self.metrics
+                .quota_proofs_used
+                .get_or_create(&QuotaLabels::new(client, period))
+                .set(used as i64);
+        }
+    }
+
+    /// Record the tenant ("" for anonymous clients) that started a [Job], unless one is already.
+    /// Returns `false` if one was.
+    pub fn record_job_tenant(
+        &self,
+        job_key: &[u8],
+        tenant: &str,
+    ) -> Result<bool, InclusionServiceError> {
+        let tenant = schema::encode(tenant).expect("Always given serializable tenant");
+        self.store
+            .compare_and_swap(StoreTree::JobTenant, job_key, None, &tenant)
+    }
+
+    /// The tenant that started a [Job], if recorded
+    fn job_tenant(&self, job_key: &[u8]) -> Result<Option<String>, InclusionServiceError> {
+        self.store
+            .get(StoreTree::JobTenant, job_key)?
+            .map(|data| {
+                schema::decode(&data)
+                    .map_err(|e| InclusionServiceError::InternalError(e.to_string()))
             })
-            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
+            .transpose()
+    }
+
+    /// Receive a [JobEvent] for every [JobStatus] stored from now on
+    pub fn subscribe_job_events(&self) -> broadcast::Receiver<JobEvent> {
+        self.job_events.subscribe()
+    }
+
+    fn publish_job_event(&self, job_key: Vec<u8>, status: JobStatus) {
+        // Only fails if there are no watchers
+        let _ = self.job_events.send(JobEvent {
+            job_key,
+            status: Arc::new(status),
+        });
+    }
+
+    /// The [RetryState] of a [Job], if it ever failed with a retryable error
+    pub fn get_retry_state(
+        &self,
+        job_key: &[u8],
+    ) -> Result<Option<RetryState>, InclusionServiceError> {
+        self.store
+            .get(StoreTree::Retry, job_key)?
+            .map(|v| {
+                schema::decode(&v).map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+            })
+            .transpose()
+    }
+
+    /// Periodically send retryable [JobStatus::Failed] jobs back to the queue,
+    /// once their [RetryState] says they are due.
+    /// On start, any retryable failure without a `RetryState` is first made due, see
+    /// [Self::backfill_retry_states].
+    pub async fn retry_scheduler(self: Arc<Self>) {
+        debug!("Retry scheduler started");
+        match self.backfill_retry_states() {
+            Ok(0) => {}
+            Ok(n) => info!("Scheduled {n} retryable failed jobs with no retry state to retry now"),
+            Err(e) => error!("Failed to schedule retries for existing failed jobs: {e}"),
+        }
+        let mut interval = tokio::time::interval(RETRY_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let now = unix_ms_now();
+            let due_jobs: Vec<_> = self
+                .store
+                .iter(StoreTree::Retry)
+                .flatten()
+                .filter_map(|(job_key, retry_data)| {
+                    let retry_state: RetryState = schema::decode(&retry_data).ok()?;
+                    (retry_state.next_retry_unix_ms != 0 && retry_state.next_retry_unix_ms <= now)
+                        .then_some(job_key)
+                })
+                .collect();
+            for job_key in due_jobs {
+                let _ = self
+                    .retry_job(job_key)
+                    .await
+                    .map_err(|e| error!("Failed to retry job: {e}"));
+            }
+        }
+    }
+
+    /// Give every retryable [JobStatus::Failed] job in the finished DB without a [RetryState]
+    /// (as stored before retries were scheduled) one that is due now, returning how many.
+    /// Otherwise such jobs would only be retried if a user requested them again.
+    pub fn backfill_retry_states(&self) -> Result<usize, InclusionServiceError> {
+        let now = unix_ms_now();
+        let mut backfilled = 0;
+        for entry in self.store.iter(StoreTree::Finished) {
+            let (job_key, _) = entry?;
+            let inserted =
+                self.store
+                    .transaction(&[StoreTree::Finished, StoreTree::Retry], |tx| {
+                        if tx.get(StoreTree::Retry, &job_key)?.is_some() {
+                            return Ok(false);
+                        }
+                        // Re-read, as the job may have been retried since it was iterated over
+                        let retryable = tx
+                            .get(StoreTree::Finished, &job_key)?
+                            .and_then(|v| schema::decode::<JobStatus>(&v).ok())
+                            .is_some_and(|status| matches!(status, JobStatus::Failed(_, Some(_))));
+                        if !retryable {
+                            return Ok(false);
+                        }
+                        let due_now = RetryState {
+                            attempts: 0,
+                            next_retry_unix_ms: now,
+                        };
+                        tx.insert(
+                            StoreTree::Retry,
+                            &job_key,
+                            &schema::encode(&due_now)
+                                .expect("Always given serializable retry state"),
+                        )?;
+                        Ok(true)
+                    })?;
+            backfilled += inserted as usize;
+        }
+        Ok(backfilled)
+    }
+
+    /// Send a [Job] back to the queue, if it is still a retryable failure.
+    async fn retry_job(&self, job_key: Vec<u8>) -> Result<(), InclusionServiceError> {
+        let Some(finished_data) = self.store.get(StoreTree::Finished, &job_key)? else {
+            // Already retried, and in the queue
+            return Ok(());
+        };
+        let JobStatus::Failed(error, Some(retry_status)) = schema::decode(&finished_data)
+            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?
+        else {
+            return Ok(());
+        };
+        let job = Job::from_key(&job_key)?;
+        info!("Retrying {job:?}, previous error: {error}");
+        self.metrics.jobs_attempted.inc();
+        self.metrics.jobs_retried.inc();
+        self.send_job_with_new_status(job_key, *retry_status, job)
+            .await
+    }
+
+    /// Insert a [JobStatus] into the queue database, removing any finished entry.
+    /// This does *not* schedule any work on the [Job], see [Self::send_job_with_new_status].
+    /// Watchers are notified with a [JobEvent].
+    ///
+    /// A retry due for the `Job` is no longer, keeping it's [RetryState] attempts.
+    pub fn store_job_status(
+        &self,
+        job_key: Vec<u8>,
+        update_status: JobStatus,
+        job: &Job,
+    ) -> Result<(), InclusionServiceError> {
+        debug!("Storing {job:?} with updated status: {update_status:?}");
+        let status_data =
+            schema::encode(&update_status).expect("Always given serializable job status");
+        self.store.transaction(
+            &[
+                StoreTree::Queue,
+                StoreTree::Finished,
+                StoreTree::FinishedAt,
+                StoreTree::Retry,
+            ],
+            |tx| {
+                tx.remove(StoreTree::Finished, &job_key)?;
+                tx.remove(StoreTree::FinishedAt, &job_key)?;
+                let due_retry = tx
+                    .get(StoreTree::Retry, &job_key)?
+                    .and_then(|v| schema::decode::<RetryState>(&v).ok())
+                    .filter(|retry_state| retry_state.next_retry_unix_ms != 0);
+                if let Some(retry_state) = due_retry {
+                    let not_due = RetryState {
+                        next_retry_unix_ms: 0,
+                        ..retry_state
+                    };
+                    tx.insert(
+                        StoreTree::Retry,
+                        &job_key,
+                        &schema::encode(&not_due).expect("Always given serializable retry state"),
+                    )?;
+                }
+                tx.insert(StoreTree::Queue, &job_key, &status_data)
+            },
+        )?;
+        self.publish_job_event(job_key, update_status);
         Ok(())
     }
 
-    /// Insert a [JobStatus] into a [SledTree] database
-    /// AND `send()` this job back to the `self.job_sender` to schedule more progress.
-    /// You likely want to pass `self.some_sled_tree` into `data_base` as input.
-    pub fn send_job_with_new_status(
+    /// Insert a [JobStatus] into the queue database
+    /// AND enqueue this job to the [JobStage] that progresses it.
+    /// Waits for room in the stage's queue, see [Self::try_send_job_with_new_status]
+    /// to fail instead.
+    pub async fn send_job_with_new_status(
         &self,
         job_key: Vec<u8>,
         update_status: JobStatus,
         job: Job,
     ) -> Result<(), InclusionServiceError> {
-        debug!("Sending {job:?} back with updated status: {update_status:?}");
-        (&self.queue_db, &self.finished_db)
-            .transaction(|(queue_tx, finished_tx)| {
-                finished_tx.remove(job_key.clone())?;
-                queue_tx.insert(
-                    job_key.clone(),
-                    bincode::serialize(&update_status)
-                        .expect("Always given serializable job status"),
-                )?;
-                Ok::<(), sled::transaction::ConflictableTransactionError<InclusionServiceError>>(())
-            })
-            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
-        self.job_sender
-            .send(Some(job))
-            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+        let stage = JobStage::for_status(&update_status);
+        self.store_job_status(job_key, update_status, &job)?;
+        if let Some(stage) = stage {
+            self.job_queue.enqueue(job, stage).await?;
+            self.update_queue_depth(stage);
+        }
+        Ok(())
+    }
+
+    /// Like [Self::send_job_with_new_status], but nothing is stored and
+    /// [InclusionServiceError::QueueFull] is returned if the stage's queue has no room.
+    pub fn try_send_job_with_new_status(
+        &self,
+        job_key: Vec<u8>,
+        update_status: JobStatus,
+        job: Job,
+    ) -> Result<(), InclusionServiceError> {
+        let Some(stage) = JobStage::for_status(&update_status) else {
+            return self.store_job_status(job_key, update_status, &job);
+        };
+        let permit = self.job_queue.try_reserve(stage).inspect_err(|_| {
+            self.metrics.jobs_rejected.inc();
+        })?;
+        self.store_job_status(job_key, update_status, &job)?;
+        permit.send(job);
+        self.update_queue_depth(stage);
+        Ok(())
+    }
+
+    /// Get the stored [JobStatus] of many [Job]s in one transaction, and queue any
+    /// the service has not seen before with [JobStatus::DataAvailabilityPending].
+    ///
+    /// Statuses are returned in the order of `jobs`, `None` for a newly queued job,
+    /// which is recorded as started by `tenant` (see [Self::record_job_tenant]).
+    /// If the queue can't fit all new jobs, nothing is stored and
+    /// [InclusionServiceError::QueueFull] is returned.
+    pub fn get_or_start_jobs(
+        &self,
+        jobs: Vec<(Vec<u8>, Job)>,
+        tenant: &str,
+    ) -> Result<Vec<Option<JobStatus>>, InclusionServiceError> {
+        let stage = JobStage::DataAvailability;
+        let pending = schema::encode(&JobStatus::DataAvailabilityPending)
+            .expect("Always given serializable job status");
+        let tenant = schema::encode(tenant).expect("Always given serializable tenant");
+        let (statuses, mut permits) = self
+            .store
+            .transaction(
+                &[StoreTree::Queue, StoreTree::Finished, StoreTree::JobTenant],
+                |tx| {
+                    let mut statuses = Vec::with_capacity(jobs.len());
+                    for (job_key, _) in &jobs {
+                        let stored = match tx.get(StoreTree::Finished, job_key)? {
+                            Some(data) => Some(data),
+                            None => tx.get(StoreTree::Queue, job_key)?,
+                        };
+                        match stored {
+                            Some(data) => {
+                                let job_status: JobStatus = schema::decode(&data).map_err(|e| {
+                                    InclusionServiceError::InternalError(e.to_string())
+                                })?;
+                                statuses.push(Some(job_status));
+                            }
+                            None => {
+                                // A repeated job in the batch will read this, and is not queued twice
+                                tx.insert(StoreTree::Queue, job_key, &pending)?;
+                                if tx.get(StoreTree::JobTenant, job_key)?.is_none() {
+                                    tx.insert(StoreTree::JobTenant, job_key, &tenant)?;
+                                }
+                                statuses.push(None);
+                            }
+                        }
+                    }
+                    let new_jobs = statuses.iter().filter(|s| s.is_none()).count();
+                    // Permits reserved by an earlier, conflicted, run were released before this one
+                    let permits = self
+                        .job_queue
+                        .try_reserve_many(stage, new_jobs)
+                        .map_err(TransactionError::Abort)?;
+                    Ok((statuses, permits))
+                },
+            )
+            .inspect_err(|e| {
+                if matches!(e, InclusionServiceError::QueueFull) {
+                    self.metrics.jobs_rejected.inc();
+                }
+            })?;
+
+        for ((job_key, job), job_status) in jobs.into_iter().zip(&statuses) {
+            if job_status.is_none() {
+                debug!("Stored {job:?} with status: DataAvailabilityPending");
+                permits
+                    .next()
+                    .expect("A permit is reserved for each new job")
+                    .send(job);
+                self.metrics.jobs_attempted.inc();
+                self.publish_job_event(job_key, JobStatus::DataAvailabilityPending);
+            }
+        }
+        self.update_queue_depth(stage);
+        Ok(statuses)
+    }
+
+    /// Record the number of [Job]s waiting in a [JobStage]'s queue
+    fn update_queue_depth(&self, stage: JobStage) {
+        let depth = self.job_queue.depth(stage) as i64;
+        match stage {
+            JobStage::DataAvailability => self.metrics.da_queue_depth.set(depth),
+            JobStage::ZkProof => self.metrics.zk_queue_depth.set(depth),
+        };
     }
 
-    pub async fn get_da_client(&self) -> Result<Arc<CelestiaJSONClient>, InclusionServiceError> {
+    pub async fn get_da_client(&self) -> Result<Arc<dyn DaBackend>, InclusionServiceError> {
         let handle = self
             .da_client_handle
-            .get_or_try_init(|| async {
-                debug!("Building DA client");
-                let client = CelestiaJSONClient::new(
-                    self.config.da_node_http.as_str(),
-                    self.config.da_node_token.as_str().into(),
-                )
-                .await
-                .map_err(|e| InclusionServiceError::DaClientError(e.to_string()))?;
-                Ok(Arc::new(client))
-            })
-            .await
-            .map_err(|e: InclusionServiceError| e)?;
+            .get_or_try_init(|| connect_da_backend(&self.config))
+            .await?;
         Ok(handle.clone())
     }
 
-    pub async fn get_zk_client_remote(&self) -> Arc<SP1NetworkProver> {
+    /// Whether [Self::get_da_client] has connected
+    pub fn da_client_ready(&self) -> bool {
+        self.da_client_handle.initialized()
+    }
+
+    pub async fn get_zk_client(&self) -> Arc<dyn ZkProverBackend> {
         self.zk_client_handle
             .get_or_init(|| async {
                 debug!("Building ZK client");
-                let client = sp1_sdk::ProverClient::builder().network().build();
-                Arc::new(client)
+                build_zk_backend(self.config.zk_proof_mode)
             })
             .await
             .clone()
     }
 
+    /// Stop starting work on [Job]s, and have [Self::job_worker] return once the work in
+    /// flight has saved it's progress (or [InclusionServiceConfig::shutdown_deadline] passes).
     pub fn shutdown(&self) {
-        info!("Terminating worker, finishing preexisting jobs");
-        let _ = self.job_sender.send(None); // Break loop in `job_worker`
+        info!("Terminating worker, draining in-flight jobs");
+        self.drain.begin(); // Break out of `job_worker`
     }
 }
 
+/// The [DaBackend] of a config: fixtures if it has a fixture dir, otherwise a Celestia node
+pub async fn connect_da_backend(
+    config: &InclusionServiceConfig,
+) -> Result<Arc<dyn DaBackend>, InclusionServiceError> {
+    if let Some(fixture_dir) = &config.da_fixture_dir {
+        info!("Using DA fixtures from {fixture_dir:?} -- NOT connecting to a Celestia node");
+        return Ok(Arc::new(FixtureDaBackend::from_dir(fixture_dir)?));
+    }
+    debug!("Building DA client");
+    let client = CelestiaJSONClient::new(
+        config.da_node_http.as_str(),
+        config.da_node_token.as_str().into(),
+    )
+    .await
+    .map_err(|e| InclusionServiceError::DaClientError(e.to_string()))?;
+    Ok(Arc::new(client))
+}
+
+/// Collect a blob's data and inclusion proof from a [DaBackend].
+/// Errors from the DA node are mapped by `on_da_error`.
+pub async fn build_blob_inclusion_input(
+    height: u64,
+    namespace: Namespace,
+    commitment: Commitment,
+    client: &dyn DaBackend,
+    on_da_error: impl Fn(JsonRpcError) -> InclusionServiceError,
+) -> Result<BlobInclusionInput, InclusionServiceError> {
+    debug!("Preparing request to Celestia");
+
+    let header = client
+        .header_get_by_height(height)
+        .await
+        .map_err(&on_da_error)?;
+
+    let eds_row_roots = header.dah.row_roots();
+    let eds_size: u64 = eds_row_roots.len().try_into().map_err(|_| {
+        InclusionServiceError::InternalError(
+            "Failed to convert eds_row_roots.len() to u64".to_string(),
+        )
+    })?;
+    let ods_size: u64 = eds_size / 2;
+
+    let blob = client
+        .blob_get(height, namespace, commitment)
+        .await
+        .map_err(&on_da_error)?;
+
+    let blob_index = blob
+        .index
+        .ok_or_else(|| InclusionServiceError::MissingBlobIndex)?;
+
+    // https://github.com/celestiaorg/eq-service/issues/65
+    //let first_row_index: u64 = blob_index.div_ceil(eds_size) - 1;
+    let first_row_index: u64 =
+        blob.index.ok_or(InclusionServiceError::MissingBlobIndex)? / eds_size;
+    let ods_index = blob_index - (first_row_index * ods_size);
+
+    let range_proof = client
+        .share_get_range(&header, ods_index, ods_index + blob.shares_len() as u64)
+        .await
+        .map_err(&on_da_error)?;
+
+    range_proof
+        .verify(header.dah.hash())
+        .map_err(|_| InclusionServiceError::FailedShareRangeProofSanityCheck)?;
+
+    debug!("Creating ZK Proof input from Celestia Data");
+    Ok(BlobInclusionInput {
+        data: blob.data,
+        namespace_id: namespace,
+        share_proofs: range_proof.share_proofs,
+        row_proof: range_proof.row_proof,
+        author: blob.signer,
+        data_root: header.dah.hash().as_bytes().try_into().map_err(|_| {
+            InclusionServiceError::InternalError(
+                "Failed to convert header.dah.hash().as_bytes() to [u8; 32]".to_string(),
+            )
+        })?,
+    })
+}
+
+/// Collect the blob data and inclusion proofs for a [BlobId] from a [DaBackend],
+/// unless in the [DaProofCache]. Errors from the DA node are mapped by `on_da_error`.
+pub async fn build_zk_proof_input(
+    job: &BlobId,
+    client: &dyn DaBackend,
+    cache: &DaProofCache,
+    on_da_error: impl Fn(JsonRpcError) -> InclusionServiceError,
+) -> Result<ZKStackEqProofInput, InclusionServiceError> {
+    let template = cache
+        .get_or_build(
+            job.height.into(),
+            job.namespace,
+            job.commitment,
+            client,
+            on_da_error,
+        )
+        .await?;
+    Ok(with_replay_protection(
+        &template,
+        job.l2_chain_id,
+        job.batch_number,
+    ))
+}
+
+/// Collect the data and inclusion proofs of every blob in a [MultiBlobJob] from a [DaBackend],
+/// in order, unless in the [DaProofCache]. Errors from the DA node are mapped by `on_da_error`.
+pub async fn build_multi_blob_proof_input(
+    job: &MultiBlobJob,
+    client: &dyn DaBackend,
+    cache: &DaProofCache,
+    on_da_error: impl Fn(JsonRpcError) -> InclusionServiceError,
+) -> Result<ZKStackMultiBlobEqProofInput, InclusionServiceError> {
+    let mut hasher = Keccak256::new();
+    let mut blobs = Vec::with_capacity(job.blobs.len());
+    for blob_id in &job.blobs {
+        let template = cache
+            .get_or_build(
+                blob_id.height.into(),
+                blob_id.namespace,
+                blob_id.commitment,
+                client,
+                &on_da_error,
+            )
+            .await?;
+        hasher.update(&template.data);
+        blobs.push(template.as_ref().clone().into());
+    }
+
+    Ok(ZKStackMultiBlobEqProofInput {
+        blobs,
+        keccak_hash: hasher.finalize().into(),
+        batch_number: job.batch_number,
+        chain_id: job.chain_id,
+    })
+}
+
+/// Collect the blob data and inclusion proofs for a [PayyBlobId] from a [DaBackend],
+/// unless in the [DaProofCache]. Errors from the DA node are mapped by `on_da_error`.
+pub async fn build_payy_proof_input(
+    job: &PayyBlobId,
+    client: &dyn DaBackend,
+    cache: &DaProofCache,
+    on_da_error: impl Fn(JsonRpcError) -> InclusionServiceError,
+) -> Result<PayyEqProofInput, InclusionServiceError> {
+    let template = cache
+        .get_or_build(
+            job.height.into(),
+            job.namespace,
+            job.commitment,
+            client,
+            on_da_error,
+        )
+        .await?;
+
+    Ok(PayyEqProofInput {
+        blob: template.as_ref().clone().into(),
+        keccak_hash: template.keccak_hash,
+        l2_block_number: job.l2_block_number,
+    })
+}
+
 /// Helper to count/log the error for Prometheus metrics
 fn count_error(metrics: &PromMetrics, e: InclusionServiceError) {
     let _ = metrics
@@ -585,3 +1443,69 @@ fn count_error(metrics: &PromMetrics, e: InclusionServiceError) {
         .get_or_create(&ErrorLabels { error_type: e })
         .inc();
 }
+
+#[cfg(test)]
+impl InclusionService {
+    /// A service for tests on a temporary DB, proving with SP1's mock prover and collecting
+    /// DA data from `da_client`. Keep the [JobReceivers] alive while jobs are queued.
+    pub(crate) fn for_test(da_client: Arc<dyn DaBackend>) -> (Arc<Self>, JobReceivers) {
+        use crate::internal::config::ServiceConfig;
+        use crate::internal::store::SledStore;
+
+        let mut config = ServiceConfig::default();
+        config.zk.proof_mode = ZkProofMode::Mock;
+        config.zk.proof_gen_timeout_seconds = Some(600);
+        let store: Arc<dyn JobStore> = Arc::new(SledStore::temporary().unwrap());
+        schema::migrate(store.as_ref()).unwrap();
+        let programs = ProgramRegistry::load(store.as_ref(), None).unwrap();
+        let (job_queue, job_receivers) = JobQueue::new(config.jobs.queue_capacity);
+        let service = InclusionService::new(
+            config.inclusion_config().unwrap(),
+            OnceCell::new_with(Some(da_client)),
+            OnceCell::new(),
+            Arc::new(PromMetrics::new(config.zk_proof_gen_timeout())),
+            store,
+            job_queue,
+            programs,
+        );
+        (Arc::new(service), job_receivers)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use celestia_types::block::Height as BlockHeight;
+
+    #[tokio::test]
+    async fn test_cancel_zk_proof() {
+        let (service, _job_receivers) =
+            InclusionService::for_test(Arc::new(FixtureDaBackend::default()));
+        let job = Job::ZkStack(BlobId::new(
+            BlockHeight::from(7u32),
+            Namespace::new_v0(&[1, 2, 3]).unwrap(),
+            Commitment::new([4; 32]),
+            5,
+            6,
+        ));
+        let job_key = job.key().unwrap();
+        let request_id = [9; 32];
+        service
+            .store_job_status(job_key.clone(), JobStatus::ZkProofPending(request_id), &job)
+            .unwrap();
+
+        // Nobody is waiting yet, or it's another request
+        assert!(!service.cancel_zk_proof(&job_key, request_id).unwrap());
+        assert!(!service.cancel_zk_proof(&job_key, [8; 32]).unwrap());
+        let cancellation = service.zk_cancellation(request_id);
+        let notified = tokio::time::timeout(Duration::from_millis(50), cancellation.notified());
+        assert!(notified.await.is_err());
+
+        // A worker waiting on it is told to stop
+        assert!(service.cancel_zk_proof(&job_key, request_id).unwrap());
+        let notified = tokio::time::timeout(Duration::from_millis(50), cancellation.notified());
+        assert!(notified.await.is_ok());
+        service.remove_zk_cancellation(request_id);
+        assert!(!service.cancel_zk_proof(&job_key, request_id).unwrap());
+    }
+}
@@ -1,12 +1,149 @@
-use eq_common::{InclusionServiceError, ZKStackEqProofInput};
-use eq_sdk::types::BlobId;
+use eq_common::{
+    InclusionServiceError, PayyEqProofInput, ZKStackEqProofInput, ZKStackMultiBlobEqProofInput,
+};
+use eq_sdk::types::{BlobId, PayyBlobId};
 use serde::{Deserialize, Serialize};
-use sp1_sdk::SP1ProofWithPublicValues;
+use sp1_sdk::{SP1ProofMode, SP1ProofWithPublicValues};
+use std::sync::Arc;
 
+use crate::internal::artifacts::StoredProof;
+use crate::internal::programs::ProgramKind;
+use crate::internal::schema;
 use crate::SuccNetJobId;
 
-/// A job for the service, mapped to a [BlobId]
-pub type Job = BlobId;
+/// In a [schema] envelope, keys of [Job]s other than [Job::ZkStack] start with this,
+/// followed by a kind byte.
+///
+/// A [Job::ZkStack] key is the bincode [BlobId] (as it was before there were other
+/// kinds of jobs), that can't start with this: no valid block height encodes to it.
+const JOB_KIND_KEY_PREFIX: [u8; 8] = [0xff; 8];
+const AGGREGATION_MEMBER_JOB_KIND: u8 = 1;
+const MULTI_BLOB_JOB_KIND: u8 = 2;
+const PAYY_JOB_KIND: u8 = 3;
+
+/// A job for the service, each kind proven by it's own ZK program
+#[derive(Clone, Debug)]
+pub enum Job {
+    /// A single blob
+    ZkStack(BlobId),
+    /// A single batch, split across many blobs
+    ZkStackMultiBlob(MultiBlobJob),
+    /// A single blob for the Payy integration
+    Payy(PayyBlobId),
+    /// A single blob proven as a compressed proof, to be verified in an
+    /// [AggregationJob](crate::internal::aggregation::AggregationJob)'s aggregation proof
+    AggregationMember(BlobId),
+}
+
+impl Job {
+    /// The kind of program that proves this kind of job
+    pub fn program_kind(&self) -> ProgramKind {
+        match self {
+            Job::ZkStack(_) | Job::AggregationMember(_) => ProgramKind::ZkStackInclusion,
+            Job::ZkStackMultiBlob(_) => ProgramKind::ZkStackMultiBlobInclusion,
+            Job::Payy(_) => ProgramKind::PayyInclusion,
+        }
+    }
+
+    /// The L2 chain this job is for, 0 for Payy jobs which have none
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            Job::ZkStack(blob_id) => blob_id.l2_chain_id,
+            Job::ZkStackMultiBlob(multi_blob) => multi_blob.chain_id,
+            Job::Payy(_) => 0,
+            Job::AggregationMember(blob_id) => blob_id.l2_chain_id,
+        }
+    }
+
+    /// The kind of proof requested for this job: compressed for an aggregation member,
+    /// to be verified in the aggregation program, otherwise Groth16 to be verified on chain
+    pub fn proof_mode(&self) -> SP1ProofMode {
+        match self {
+            Job::AggregationMember(_) => SP1ProofMode::Compressed,
+            _ => SP1ProofMode::Groth16,
+        }
+    }
+
+    /// The key of this job in the queue, finished, and retry DBs
+    pub fn key(&self) -> Result<Vec<u8>, InclusionServiceError> {
+        let key = match self {
+            Job::ZkStack(blob_id) => bincode::serialize(blob_id),
+            Job::ZkStackMultiBlob(multi_blob) => {
+                let mut key = JOB_KIND_KEY_PREFIX.to_vec();
+                key.push(MULTI_BLOB_JOB_KIND);
+                bincode::serialize_into(&mut key, multi_blob).map(|_| key)
+            }
+            Job::Payy(payy_blob_id) => {
+                let mut key = JOB_KIND_KEY_PREFIX.to_vec();
+                key.push(PAYY_JOB_KIND);
+                bincode::serialize_into(&mut key, payy_blob_id).map(|_| key)
+            }
+            Job::AggregationMember(blob_id) => {
+                let mut key = JOB_KIND_KEY_PREFIX.to_vec();
+                key.push(AGGREGATION_MEMBER_JOB_KIND);
+                bincode::serialize_into(&mut key, blob_id).map(|_| key)
+            }
+        };
+        key.map(|key| schema::envelope(&key))
+            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+    }
+
+    /// Decode a key from [Self::key]
+    pub fn from_key(key: &[u8]) -> Result<Self, InclusionServiceError> {
+        let key = schema::open_envelope(key)
+            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
+        let job = match key.strip_prefix(JOB_KIND_KEY_PREFIX.as_slice()) {
+            None => bincode::deserialize(key).map(Job::ZkStack),
+            Some([MULTI_BLOB_JOB_KIND, multi_blob @ ..]) => {
+                bincode::deserialize(multi_blob).map(Job::ZkStackMultiBlob)
+            }
+            Some([PAYY_JOB_KIND, payy_blob_id @ ..]) => {
+                bincode::deserialize(payy_blob_id).map(Job::Payy)
+            }
+            Some([AGGREGATION_MEMBER_JOB_KIND, blob_id @ ..]) => {
+                bincode::deserialize(blob_id).map(Job::AggregationMember)
+            }
+            Some(_) => {
+                return Err(InclusionServiceError::InternalError(
+                    "Unknown job kind in key".to_string(),
+                ))
+            }
+        };
+        job.map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+    }
+}
+
+/// Many blobs holding the data of one L2 batch, in order,
+/// proven together by a [ProgramKind::ZkStackMultiBlobInclusion] program.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MultiBlobJob {
+    pub chain_id: u64,
+    pub batch_number: u32,
+    /// Every blob must have the same `l2_chain_id` and `batch_number`
+    pub blobs: Vec<BlobId>,
+}
+
+impl MultiBlobJob {
+    /// Check there are blobs, and they are all for this batch
+    pub fn validate(&self) -> Result<(), InclusionServiceError> {
+        if self.blobs.is_empty() {
+            return Err(InclusionServiceError::InvalidParameter(
+                "Multi-blob job needs at least one blob".to_string(),
+            ));
+        }
+        if let Some(blob) = self
+            .blobs
+            .iter()
+            .find(|b| b.l2_chain_id != self.chain_id || b.batch_number != self.batch_number)
+        {
+            return Err(InclusionServiceError::InvalidParameter(format!(
+                "Blob {blob:?} is not for chain_id {} batch_number {}",
+                self.chain_id, self.batch_number
+            )));
+        }
+        Ok(())
+    }
+}
 
 /// Used as a [Job] state machine for the eq-service.
 ///
@@ -29,6 +166,77 @@ pub enum JobStatus {
     /// - Option = None                        --> Permanent failure
     /// - Option = Some(\<retry-able status\>) --> Retry is possible, with a JobStatus state to retry with
     Failed(InclusionServiceError, Option<Box<JobStatus>>),
+    /// Like [JobStatus::DataAvailable], for a [Job::ZkStackMultiBlob].
+    // Added last to keep the encoding of existing statuses unchanged
+    DataAvailableMultiBlob(ZKStackMultiBlobEqProofInput),
+    /// Like [JobStatus::DataAvailable], for a [Job::Payy]
+    DataAvailablePayy(PayyEqProofInput),
+    /// A [JobStatus::ZkProofFinished] with only what is reported to clients kept,
+    /// see [retention](crate::internal::retention)
+    ZkProofCompacted(CompactProof),
+    /// A ZK proof is ready, and kept in the service's
+    /// [ArtifactStore](crate::internal::artifacts::ArtifactStore) rather than the DB
+    ZkProofStored(StoredProof),
+}
+
+/// The parts of a [SP1ProofWithPublicValues] needed to verify it on chain
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CompactProof {
+    /// [SP1ProofWithPublicValues::bytes]
+    pub proof_data: Vec<u8>,
+    pub public_values: Vec<u8>,
+}
+
+impl From<&SP1ProofWithPublicValues> for CompactProof {
+    fn from(proof: &SP1ProofWithPublicValues) -> Self {
+        CompactProof {
+            proof_data: proof.bytes(),
+            public_values: proof.public_values.to_vec(),
+        }
+    }
+}
+
+impl JobStatus {
+    /// True once no further work will happen on the [Job]:
+    /// it has a proof or failed permanently.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::ZkProofFinished(_)
+                | JobStatus::ZkProofCompacted(_)
+                | JobStatus::ZkProofStored(_)
+                | JobStatus::Failed(_, None)
+        )
+    }
+
+    /// True if the [Job] belongs in the finished DB rather than the queue DB:
+    /// it has a proof or failed, including retryable failures waiting on a retry.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::ZkProofFinished(_)
+                | JobStatus::ZkProofCompacted(_)
+                | JobStatus::ZkProofStored(_)
+                | JobStatus::Failed(_, _)
+        )
+    }
+
+    /// The proof of a successful [Job] kept in the DB, compacted or not
+    pub fn compact_proof(&self) -> Option<CompactProof> {
+        match self {
+            JobStatus::ZkProofFinished(proof) => Some(proof.into()),
+            JobStatus::ZkProofCompacted(proof) => Some(proof.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// A [JobStatus] change, broadcast to anyone watching [Job]s.
+#[derive(Clone, Debug)]
+pub struct JobEvent {
+    /// The DB key of the [Job]
+    pub job_key: Vec<u8>,
+    pub status: Arc<JobStatus>,
 }
 
 impl std::fmt::Debug for JobStatus {
@@ -39,6 +247,70 @@ impl std::fmt::Debug for JobStatus {
             JobStatus::ZkProofPending(_) => write!(f, "ZkProofPending"),
             JobStatus::ZkProofFinished(_) => write!(f, "ZkProofFinished"),
             JobStatus::Failed(_, _) => write!(f, "Failed"),
+            JobStatus::DataAvailableMultiBlob(_) => write!(f, "DataAvailableMultiBlob"),
+            JobStatus::DataAvailablePayy(_) => write!(f, "DataAvailablePayy"),
+            JobStatus::ZkProofCompacted(_) => write!(f, "ZkProofCompacted"),
+            JobStatus::ZkProofStored(_) => write!(f, "ZkProofStored"),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use celestia_types::{blob::Commitment, block::Height as BlockHeight, nmt::Namespace};
+
+    fn blob_id(height: u32) -> BlobId {
+        BlobId::new(
+            BlockHeight::from(height),
+            Namespace::new_v0(&[1, 2, 3]).unwrap(),
+            Commitment::new([4; 32]),
+            5u64,
+            6u32,
+        )
+    }
+
+    #[test]
+    fn test_job_keys() {
+        // Keys of single blob jobs are unchanged from before there were other kinds,
+        // other than the schema envelope
+        let blob_job = Job::ZkStack(blob_id(7));
+        let blob_key = blob_job.key().unwrap();
+        assert_eq!(
+            blob_key,
+            schema::envelope(&bincode::serialize(&blob_id(7)).unwrap())
+        );
+        assert!(matches!(Job::from_key(&blob_key), Ok(Job::ZkStack(b)) if b == blob_id(7)));
+
+        let multi_blob_job = Job::ZkStackMultiBlob(MultiBlobJob {
+            chain_id: 5,
+            batch_number: 6,
+            blobs: vec![blob_id(7), blob_id(8)],
+        });
+        let multi_blob_key = multi_blob_job.key().unwrap();
+        assert!(schema::open_envelope(&multi_blob_key)
+            .unwrap()
+            .starts_with(&JOB_KIND_KEY_PREFIX));
+        let Ok(Job::ZkStackMultiBlob(decoded)) = Job::from_key(&multi_blob_key) else {
+            panic!("Expected a multi-blob job");
+        };
+        assert!(decoded.blobs == vec![blob_id(7), blob_id(8)]);
+
+        let payy_blob_id = PayyBlobId::new(
+            BlockHeight::from(7u32),
+            Namespace::new_v0(&[1, 2, 3]).unwrap(),
+            Commitment::new([4; 32]),
+            9u64,
+        );
+        let payy_key = Job::Payy(payy_blob_id.clone()).key().unwrap();
+        assert_ne!(payy_key, multi_blob_key);
+        assert!(matches!(Job::from_key(&payy_key), Ok(Job::Payy(p)) if p == payy_blob_id));
+
+        // Distinct from the job proving the same blob on it's own
+        let member_key = Job::AggregationMember(blob_id(7)).key().unwrap();
+        assert_ne!(member_key, blob_key);
+        assert!(
+            matches!(Job::from_key(&member_key), Ok(Job::AggregationMember(b)) if b == blob_id(7))
+        );
+    }
+}
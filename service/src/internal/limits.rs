@@ -0,0 +1,499 @@
+use crate::internal::schema;
+use crate::internal::store::{JobStore, StoreTree};
+
+use eq_common::InclusionServiceError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex as StdMutex;
+use std::time::{Duration, Instant};
+
+/// Buckets kept by a [RateLimiter] before those that refilled are dropped
+const MAX_RATE_BUCKETS: usize = 10_000;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// How much a client (a tenant, or an anonymous client without authentication) may use
+/// the service for each chain ID. Unset limits are unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// New [Job](crate::Job)s started per second, see [RateLimiter]
+    pub jobs_per_second: Option<f64>,
+    /// New jobs that may be started at once, after being idle
+    pub burst: u32,
+    /// ZK proofs requested per UTC day
+    pub proofs_per_day: Option<u64>,
+    /// ZK proofs requested per UTC calendar month
+    pub proofs_per_month: Option<u64>,
+}
+
+impl Limits {
+    fn proofs_per(&self, period: QuotaPeriod) -> Option<u64> {
+        match period {
+            QuotaPeriod::Day => self.proofs_per_day,
+            QuotaPeriod::Month => self.proofs_per_month,
+        }
+    }
+}
+
+/// The [Limits] of every tenant
+#[derive(Debug, Clone, Default)]
+pub struct LimitConfig {
+    /// For tenants without their own limits, and for anonymous clients
+    pub default: Limits,
+    pub tenants: HashMap<String, Limits>,
+}
+
+impl LimitConfig {
+    /// Limits of a tenant, by name ("" for anonymous clients)
+    pub fn for_tenant(&self, tenant: &str) -> &Limits {
+        self.tenants.get(tenant).unwrap_or(&self.default)
+    }
+}
+
+/// Who a [Job](crate::Job) counts against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClientId {
+    /// Tenant name, "" for anonymous clients
+    pub tenant: String,
+    pub chain_id: u64,
+}
+
+impl fmt::Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tenant.as_str() {
+            "" => write!(f, "anonymous/{}", self.chain_id),
+            tenant => write!(f, "{tenant}/{}", self.chain_id),
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// When the bucket is full again, and can be dropped
+    full_at: Instant,
+}
+
+/// Token buckets limiting how fast clients start new [Job](crate::Job)s, kept in memory.
+/// Each bucket holds [Limits::burst] tokens, refilled at [Limits::jobs_per_second].
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: StdMutex<HashMap<(String, u64), Bucket>>,
+}
+
+impl RateLimiter {
+    /// Take a token for each of `jobs` new jobs from the bucket of a client
+    /// (a tenant name, or client IP without authentication) and chain ID.
+    /// If there are not enough, nothing is taken and the wait until there are is returned.
+    /// More jobs than the burst need (and take) a full bucket.
+    pub fn try_acquire(
+        &self,
+        client: &str,
+        chain_id: u64,
+        limits: &Limits,
+        jobs: usize,
+    ) -> Result<(), Duration> {
+        let Some(rate) = limits.jobs_per_second else {
+            return Ok(());
+        };
+        let burst = limits.burst.max(1) as f64;
+        let cost = (jobs as f64).min(burst);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().expect("Rate limiter lock poisoned");
+        if buckets.len() >= MAX_RATE_BUCKETS {
+            buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+        let bucket = buckets
+            .entry((client.to_string(), chain_id))
+            .or_insert(Bucket {
+                tokens: burst,
+                updated: now,
+                full_at: now,
+            });
+        let refilled = now.duration_since(bucket.updated).as_secs_f64() * rate;
+        bucket.tokens = (bucket.tokens + refilled).min(burst);
+        bucket.updated = now;
+        if bucket.tokens < cost {
+            return Err(Duration::from_secs_f64((cost - bucket.tokens) / rate));
+        }
+        bucket.tokens -= cost;
+        bucket.full_at = now + Duration::from_secs_f64((burst - bucket.tokens) / rate);
+        Ok(())
+    }
+}
+
+/// A span of time a proof quota counts over, in UTC
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuotaPeriod {
+    Day,
+    Month,
+}
+
+impl QuotaPeriod {
+    pub const ALL: [QuotaPeriod; 2] = [QuotaPeriod::Day, QuotaPeriod::Month];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QuotaPeriod::Day => "day",
+            QuotaPeriod::Month => "month",
+        }
+    }
+
+    /// The index of the period a time is in: days, or months, since the unix epoch
+    fn index(self, unix_s: u64) -> u64 {
+        let days = unix_s / SECONDS_PER_DAY;
+        match self {
+            QuotaPeriod::Day => days,
+            QuotaPeriod::Month => {
+                let (year, month) = civil_from_days(days);
+                (year - 1970) * 12 + (month - 1)
+            }
+        }
+    }
+
+    /// When the period after the one a time is in starts, in unix seconds
+    fn next_start(self, unix_s: u64) -> u64 {
+        let days = unix_s / SECONDS_PER_DAY;
+        let next_days = match self {
+            QuotaPeriod::Day => days + 1,
+            QuotaPeriod::Month => match civil_from_days(days) {
+                (year, 12) => days_from_civil(year + 1, 1),
+                (year, month) => days_from_civil(year, month + 1),
+            },
+        };
+        next_days * SECONDS_PER_DAY
+    }
+}
+
+/// A proof quota a client is at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub client: ClientId,
+    pub period: QuotaPeriod,
+    pub limit: u64,
+    /// When the next period starts, in unix seconds
+    pub resets_at_unix_s: u64,
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requested it's {} proofs per {}",
+            self.client,
+            self.limit,
+            self.period.name()
+        )
+    }
+}
+
+impl From<QuotaExceeded> for InclusionServiceError {
+    fn from(exceeded: QuotaExceeded) -> Self {
+        InclusionServiceError::QuotaExceeded {
+            message: exceeded.to_string(),
+            retry_at_unix_ms: exceeded.resets_at_unix_s * 1000,
+        }
+    }
+}
+
+/// Proofs requested by a client in the current day and month
+pub type QuotaUsage = [(QuotaPeriod, u64); 2];
+
+/// Proofs requested by a client in the current day and month.
+/// If it is at a quota, that is returned as an error.
+pub fn proof_quota_usage(
+    store: &dyn JobStore,
+    client: &ClientId,
+    limits: &Limits,
+    now_unix_s: u64,
+) -> Result<Result<QuotaUsage, QuotaExceeded>, InclusionServiceError> {
+    let mut usage = QuotaPeriod::ALL.map(|period| (period, 0));
+    for (period, used) in &mut usage {
+        *used = store
+            .get(StoreTree::Quota, &quota_key(client, *period, now_unix_s)?)?
+            .map(|data| decode_count(&data))
+            .transpose()?
+            .unwrap_or_default();
+        if let Err(exceeded) = check_quota(client, limits, *period, *used, now_unix_s) {
+            return Ok(Err(exceeded));
+        }
+    }
+    Ok(Ok(usage))
+}
+
+/// Count a requested proof against a client's quotas, unless it is at one.
+/// Returns the proofs requested in the current day and month, including this one.
+pub fn spend_proof_quota(
+    store: &dyn JobStore,
+    client: &ClientId,
+    limits: &Limits,
+    now_unix_s: u64,
+) -> Result<Result<QuotaUsage, QuotaExceeded>, InclusionServiceError> {
+    let keys = QuotaPeriod::ALL
+        .map(|period| quota_key(client, period, now_unix_s).map(|key| (period, key)));
+    let keys = keys.into_iter().collect::<Result<Vec<_>, _>>()?;
+    let spent = store.transaction(&[StoreTree::Quota], |tx| {
+        let mut usage = QuotaPeriod::ALL.map(|period| (period, 0));
+        for ((period, key), (_, used)) in keys.iter().zip(&mut usage) {
+            *used = tx
+                .get(StoreTree::Quota, key)?
+                .map(|data| decode_count(&data))
+                .transpose()?
+                .unwrap_or_default();
+            if let Err(exceeded) = check_quota(client, limits, *period, *used, now_unix_s) {
+                return Ok(Err(exceeded));
+            }
+        }
+        for ((_, key), (_, used)) in keys.iter().zip(&mut usage) {
+            *used += 1;
+            let count = schema::encode(used).expect("Always given serializable count");
+            tx.insert(StoreTree::Quota, key, &count)?;
+        }
+        Ok(Ok(usage))
+    })?;
+
+    // Counts of past periods are no longer needed
+    if spent.is_ok() {
+        for (period, key) in &keys {
+            let prefix = quota_key_prefix(client, *period)?;
+            for entry in store.scan_prefix(StoreTree::Quota, &prefix) {
+                let (past_key, _) = entry?;
+                if past_key != *key {
+                    store.remove(StoreTree::Quota, &past_key)?;
+                }
+            }
+        }
+    }
+    Ok(spent)
+}
+
+fn check_quota(
+    client: &ClientId,
+    limits: &Limits,
+    period: QuotaPeriod,
+    used: u64,
+    now_unix_s: u64,
+) -> Result<(), QuotaExceeded> {
+    match limits.proofs_per(period) {
+        Some(limit) if used >= limit => Err(QuotaExceeded {
+            client: client.clone(),
+            period,
+            limit,
+            resets_at_unix_s: period.next_start(now_unix_s),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Key in the [StoreTree::Quota] tree of a client's proof count for a period,
+/// sorted after the [quota_key_prefix] by period index
+fn quota_key(
+    client: &ClientId,
+    period: QuotaPeriod,
+    now_unix_s: u64,
+) -> Result<Vec<u8>, InclusionServiceError> {
+    let mut key = quota_key_prefix(client, period)?;
+    key.extend_from_slice(&period.index(now_unix_s).to_be_bytes());
+    Ok(key)
+}
+
+fn quota_key_prefix(
+    client: &ClientId,
+    period: QuotaPeriod,
+) -> Result<Vec<u8>, InclusionServiceError> {
+    schema::encode(&(&client.tenant, client.chain_id, period))
+        .map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+}
+
+fn decode_count(data: &[u8]) -> Result<u64, InclusionServiceError> {
+    schema::decode(data).map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+}
+
+/// The UTC (year, month) of a day since the unix epoch
+fn civil_from_days(days: u64) -> (u64, u64) {
+    // See http://howardhinnant.github.io/date_algorithms.html, from 0000-03-01
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month)
+}
+
+/// Days since the unix epoch of the first of a UTC month
+fn days_from_civil(year: u64, month: u64) -> u64 {
+    let year = year - u64::from(month <= 2);
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::aggregation::AggregationJob;
+    use crate::internal::da::FixtureDaBackend;
+    use crate::internal::retry::unix_ms_now;
+    use crate::internal::store::SledStore;
+    use crate::{InclusionService, Job};
+    use celestia_types::{blob::Commitment, block::Height as BlockHeight, nmt::Namespace};
+    use eq_sdk::types::BlobId;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_proof_quota() {
+        // 2024-02-29, a leap day
+        let now = 19_782 * SECONDS_PER_DAY + 60;
+        assert_eq!(civil_from_days(19_782), (2024, 2));
+        assert_eq!(QuotaPeriod::Day.next_start(now), 19_783 * SECONDS_PER_DAY);
+        assert_eq!(QuotaPeriod::Month.next_start(now), 19_783 * SECONDS_PER_DAY);
+        // 2024-12 rolls over to 2025-01-01
+        assert_eq!(
+            QuotaPeriod::Month.next_start(20_088 * SECONDS_PER_DAY),
+            20_089 * SECONDS_PER_DAY
+        );
+
+        let store = SledStore::temporary().unwrap();
+        let client = ClientId {
+            tenant: "rollup".to_string(),
+            chain_id: 7,
+        };
+        let limits = Limits {
+            proofs_per_day: Some(2),
+            proofs_per_month: Some(3),
+            ..Default::default()
+        };
+        for used in 1..=2 {
+            let usage = spend_proof_quota(&store, &client, &limits, now)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                usage,
+                [(QuotaPeriod::Day, used), (QuotaPeriod::Month, used)]
+            );
+        }
+        let exceeded = spend_proof_quota(&store, &client, &limits, now)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(exceeded.period, QuotaPeriod::Day);
+        assert!(proof_quota_usage(&store, &client, &limits, now)
+            .unwrap()
+            .is_err());
+        // Other chains have their own quota
+        let other_chain = ClientId {
+            chain_id: 8,
+            ..client.clone()
+        };
+        assert!(spend_proof_quota(&store, &other_chain, &limits, now)
+            .unwrap()
+            .is_ok());
+
+        // The next day (and month) the day quota is reset, but not the month's
+        let tomorrow = now + SECONDS_PER_DAY;
+        let usage = spend_proof_quota(&store, &client, &limits, tomorrow)
+            .unwrap()
+            .unwrap();
+        assert_eq!(usage, [(QuotaPeriod::Day, 1), (QuotaPeriod::Month, 1)]);
+        let next_week = now + 7 * SECONDS_PER_DAY;
+        spend_proof_quota(&store, &client, &limits, next_week)
+            .unwrap()
+            .unwrap();
+        spend_proof_quota(&store, &client, &limits, next_week + SECONDS_PER_DAY)
+            .unwrap()
+            .unwrap();
+        let exceeded = spend_proof_quota(&store, &client, &limits, next_week + 2 * SECONDS_PER_DAY)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(exceeded.period, QuotaPeriod::Month);
+        // Only the current periods are kept
+        assert_eq!(store.len(StoreTree::Quota).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_proof_counted_once() {
+        let (service, _job_receivers) =
+            InclusionService::for_test(Arc::new(FixtureDaBackend::default()));
+        let job = Job::ZkStack(BlobId::new(
+            BlockHeight::from(7u32),
+            Namespace::new_v0(&[1, 2, 3]).unwrap(),
+            Commitment::new([4; 32]),
+            5,
+            6,
+        ));
+        let job_key = job.key().unwrap();
+        service.record_job_tenant(&job_key, "rollup").unwrap();
+
+        // As if the proof request failed, and was retried
+        for _ in 0..3 {
+            assert!(service.spend_proof_quota(&job, &job_key).unwrap());
+        }
+        let client = ClientId {
+            tenant: "rollup".to_string(),
+            chain_id: 5,
+        };
+        let usage = proof_quota_usage(
+            service.store.as_ref(),
+            &client,
+            &Limits::default(),
+            unix_ms_now() / 1000,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(usage, [(QuotaPeriod::Day, 1), (QuotaPeriod::Month, 1)]);
+
+        // Aggregation proofs are counted once too, by aggregation key
+        let aggregation_key = schema::encode(&AggregationJob {
+            chain_id: 5,
+            batch_number: 6,
+            members: vec![],
+        })
+        .unwrap();
+        service
+            .record_job_tenant(&aggregation_key, "rollup")
+            .unwrap();
+        for _ in 0..2 {
+            service
+                .spend_proof_quota_once(&aggregation_key, 5)
+                .unwrap()
+                .unwrap();
+        }
+        let usage = proof_quota_usage(
+            service.store.as_ref(),
+            &client,
+            &Limits::default(),
+            unix_ms_now() / 1000,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(usage, [(QuotaPeriod::Day, 2), (QuotaPeriod::Month, 2)]);
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::default();
+        let limits = Limits {
+            jobs_per_second: Some(1.0),
+            burst: 2,
+            ..Default::default()
+        };
+        assert!(limiter.try_acquire("rollup", 7, &limits, 2).is_ok());
+        let wait = limiter.try_acquire("rollup", 7, &limits, 1).unwrap_err();
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+        assert!(limiter.try_acquire("rollup", 8, &limits, 1).is_ok());
+        assert!(limiter
+            .try_acquire("rollup", 7, &Limits::default(), 100)
+            .is_ok());
+    }
+}
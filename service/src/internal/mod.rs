@@ -1,5 +1,23 @@
+pub mod admin;
+pub mod aggregation;
+pub mod artifacts;
+pub mod auth;
+pub mod config;
+pub mod da;
+pub mod da_cache;
+pub mod drain;
 pub mod grpc;
+pub mod health;
 pub mod inclusion;
 pub mod job;
+pub mod limits;
+pub mod programs;
 pub mod prom_metrics;
+pub mod prover;
+pub mod queue;
+pub mod rest;
+pub mod retention;
+pub mod retry;
+pub mod schema;
+pub mod store;
 pub mod util;
@@ -0,0 +1,217 @@
+use crate::internal::schema;
+use crate::internal::store::{JobStore, StoreTree};
+use crate::{SP1ProofSetup, SuccNetProgramId};
+
+use eq_common::InclusionServiceError;
+use log::info;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// Config DB keys of [ProgramRecord]s start with this, followed by the [SuccNetProgramId].
+/// (Proof setups are keyed by the bare `SuccNetProgramId`)
+const PROGRAM_KEY_PREFIX: &[u8] = b"program:";
+
+/// What a zkVM program proves.
+/// Each kind has a current program, used for [Job](crate::Job)s not yet pinned to one.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProgramKind {
+    /// The crate `program-keccak-inclusion`, proving a single blob's inclusion
+    ZkStackInclusion,
+    /// The crate `program-keccak-multi-inclusion`, proving a batch split across many blobs
+    ZkStackMultiBlobInclusion,
+    /// The crate `program-payy-inclusion`, proving a single blob's inclusion for the Payy integration
+    PayyInclusion,
+    /// The crate `program-aggregation`, verifying many [ProgramKind::ZkStackInclusion] proofs
+    Aggregation,
+}
+
+impl ProgramKind {
+    pub const ALL: [ProgramKind; 4] = [
+        ProgramKind::ZkStackInclusion,
+        ProgramKind::ZkStackMultiBlobInclusion,
+        ProgramKind::PayyInclusion,
+        ProgramKind::Aggregation,
+    ];
+
+    /// File name of the ELF, as built by `cargo prove build`
+    pub fn elf_name(&self) -> &'static str {
+        match self {
+            ProgramKind::ZkStackInclusion => "eq-program-keccak-inclusion",
+            ProgramKind::ZkStackMultiBlobInclusion => "eq-program-keccak-multi-inclusion",
+            ProgramKind::PayyInclusion => "eq-program-payy-inclusion",
+            ProgramKind::Aggregation => "eq-program-aggregation",
+        }
+    }
+
+    /// The ELF built into the service
+    fn embedded_elf(&self) -> &'static [u8] {
+        match self {
+            ProgramKind::ZkStackInclusion => include_bytes!(
+                "../../../target/elf-compilation/riscv32im-succinct-zkvm-elf/release/eq-program-keccak-inclusion"
+            ),
+            ProgramKind::ZkStackMultiBlobInclusion => include_bytes!(
+                "../../../target/elf-compilation/riscv32im-succinct-zkvm-elf/release/eq-program-keccak-multi-inclusion"
+            ),
+            ProgramKind::PayyInclusion => include_bytes!(
+                "../../../target/elf-compilation/riscv32im-succinct-zkvm-elf/release/eq-program-payy-inclusion"
+            ),
+            ProgramKind::Aggregation => include_bytes!(
+                "../../../target/elf-compilation/riscv32im-succinct-zkvm-elf/release/eq-program-aggregation"
+            ),
+        }
+    }
+}
+
+/// A zkVM program ELF, with it's setup computed once on first use.
+pub struct ZkProgram {
+    pub kind: ProgramKind,
+    pub elf: &'static [u8],
+    /// SHA3 hash of the ELF
+    pub id: SuccNetProgramId,
+    pub(crate) setup: OnceCell<Arc<SP1ProofSetup>>,
+}
+
+/// A program as stored in the config DB, so it can still prove the [Job](crate::Job)s
+/// pinned to it after a newer program of the same kind is rolled out.
+#[derive(Serialize, Deserialize)]
+struct ProgramRecord {
+    kind: ProgramKind,
+    elf: Vec<u8>,
+}
+
+/// Every zkVM program the service can prove with, keyed by [SuccNetProgramId].
+///
+/// Loaded once on startup, programs live as long as the service.
+pub struct ProgramRegistry {
+    programs: HashMap<SuccNetProgramId, Arc<ZkProgram>>,
+    current: HashMap<ProgramKind, Arc<ZkProgram>>,
+}
+
+impl ProgramRegistry {
+    /// Load programs registered in the config DB by earlier runs, and the current program
+    /// of each [ProgramKind]: it's ELF in `program_dir` if there is one, or else the embedded ELF.
+    /// Current programs are registered in the config DB.
+    pub fn load(
+        store: &dyn JobStore,
+        program_dir: Option<&Path>,
+    ) -> Result<Self, InclusionServiceError> {
+        let mut registry = ProgramRegistry {
+            programs: HashMap::new(),
+            current: HashMap::new(),
+        };
+        for entry in store.scan_prefix(StoreTree::Config, PROGRAM_KEY_PREFIX) {
+            let (_, record_data) = entry?;
+            let record: ProgramRecord = schema::decode(&record_data)
+                .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
+            registry.register(record.kind, Cow::Owned(record.elf));
+        }
+
+        for kind in ProgramKind::ALL {
+            let elf = match program_dir
+                .map(|dir| dir.join(kind.elf_name()))
+                .filter(|path| path.exists())
+            {
+                Some(path) => {
+                    info!("Loading {kind:?} program from {path:?}");
+                    Cow::Owned(std::fs::read(&path).map_err(|e| {
+                        InclusionServiceError::InternalError(format!("Reading {path:?}: {e}"))
+                    })?)
+                }
+                None => Cow::Borrowed(kind.embedded_elf()),
+            };
+            let program = registry.register(kind, elf);
+            let key = program_key(&program.id);
+            let registered = store.contains_key(StoreTree::Config, &key)?;
+            if !registered {
+                let record = ProgramRecord {
+                    kind,
+                    elf: program.elf.to_vec(),
+                };
+                store.insert(
+                    StoreTree::Config,
+                    &key,
+                    &schema::encode(&record)
+                        .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?,
+                )?;
+            }
+            info!("Current {kind:?} program id: 0x{}", hex::encode(program.id));
+            registry.current.insert(kind, program);
+        }
+        Ok(registry)
+    }
+
+    /// Add a program, if not already known
+    fn register(&mut self, kind: ProgramKind, elf: Cow<'static, [u8]>) -> Arc<ZkProgram> {
+        let id: SuccNetProgramId = Sha3_256::digest(&elf).into();
+        self.programs
+            .entry(id)
+            .or_insert_with(|| {
+                let elf = match elf {
+                    Cow::Borrowed(elf) => elf,
+                    // Programs are never unloaded
+                    Cow::Owned(elf) => elf.leak(),
+                };
+                Arc::new(ZkProgram {
+                    kind,
+                    elf,
+                    id,
+                    setup: OnceCell::new(),
+                })
+            })
+            .clone()
+    }
+
+    /// The program new [Job](crate::Job)s of a [ProgramKind] are proven with
+    pub fn current(&self, kind: ProgramKind) -> Arc<ZkProgram> {
+        self.current
+            .get(&kind)
+            .expect("Every program kind has a current program")
+            .clone()
+    }
+
+    pub fn get(&self, id: &SuccNetProgramId) -> Option<Arc<ZkProgram>> {
+        self.programs.get(id).cloned()
+    }
+}
+
+fn program_key(id: &SuccNetProgramId) -> Vec<u8> {
+    [PROGRAM_KEY_PREFIX, id.as_slice()].concat()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::store::SledStore;
+
+    #[test]
+    fn test_rollout_keeps_old_programs() {
+        let store = SledStore::temporary().unwrap();
+        let embedded = ProgramRegistry::load(&store, None).unwrap();
+        let old_id = embedded.current(ProgramKind::ZkStackInclusion).id;
+
+        let program_dir =
+            std::env::temp_dir().join(format!("eq-service-programs-{}", std::process::id()));
+        std::fs::create_dir_all(&program_dir).unwrap();
+        std::fs::write(
+            program_dir.join(ProgramKind::ZkStackInclusion.elf_name()),
+            b"a new circuit",
+        )
+        .unwrap();
+        let rolled_out = ProgramRegistry::load(&store, Some(&program_dir)).unwrap();
+        std::fs::remove_dir_all(&program_dir).unwrap();
+
+        let new_id = rolled_out.current(ProgramKind::ZkStackInclusion).id;
+        assert_ne!(new_id, old_id);
+        assert!(rolled_out.get(&old_id).is_some());
+
+        // After the ELF is gone, the program is still known from the config DB
+        let restarted = ProgramRegistry::load(&store, None).unwrap();
+        assert_eq!(restarted.current(ProgramKind::ZkStackInclusion).id, old_id);
+        assert!(restarted.get(&new_id).is_some());
+    }
+}
@@ -3,17 +3,57 @@ use http_body_util::{BodyExt, Full};
 use hyper::{body::Bytes, server::conn::http1, service::service_fn, Request, Response};
 use hyper_util::rt::TokioIo;
 use jsonrpsee::tracing::info;
+use prometheus_client::encoding::EncodeLabelSet;
 use prometheus_client::metrics::family::Family;
 use prometheus_client::{
-    encoding::text::encode, metrics::counter::Counter, metrics::histogram::Histogram,
-    registry::Registry,
+    encoding::text::encode, metrics::counter::Counter, metrics::gauge::Gauge,
+    metrics::histogram::Histogram, registry::Registry,
 };
 use std::time::Duration;
 use std::{net::SocketAddr, sync::Arc};
 use tokio::net::TcpListener;
 
+use crate::internal::limits::{ClientId, QuotaPeriod};
 use eq_common::{ErrorLabels, InclusionServiceError};
 
+/// A tenant ("anonymous" without authentication) and chain ID
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ClientLabels {
+    pub tenant: String,
+    pub chain_id: String,
+}
+
+impl From<&ClientId> for ClientLabels {
+    fn from(client: &ClientId) -> Self {
+        ClientLabels {
+            tenant: match client.tenant.as_str() {
+                "" => "anonymous".to_string(),
+                tenant => tenant.to_string(),
+            },
+            chain_id: client.chain_id.to_string(),
+        }
+    }
+}
+
+/// [ClientLabels] and a [QuotaPeriod]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct QuotaLabels {
+    pub tenant: String,
+    pub chain_id: String,
+    pub period: String,
+}
+
+impl QuotaLabels {
+    pub fn new(client: &ClientId, period: QuotaPeriod) -> Self {
+        let ClientLabels { tenant, chain_id } = client.into();
+        QuotaLabels {
+            tenant,
+            chain_id,
+            period: period.name().to_string(),
+        }
+    }
+}
+
 /// All Service's Prometheus metrics in a single object
 pub struct PromMetrics {
     /// Shared registry for encoding
@@ -28,11 +68,46 @@ pub struct PromMetrics {
     pub jobs_errors: Family<ErrorLabels, Counter>,
     /// Histogram for ZK proof wait times
     pub zk_proof_wait_time: Histogram,
+    /// Counter for new jobs & retries rejected as the queue is full
+    pub jobs_rejected: Counter<u64>,
+    /// Gauge for jobs waiting to collect DA data
+    pub da_queue_depth: Gauge,
+    /// Gauge for jobs waiting to request or await a ZK proof
+    pub zk_queue_depth: Gauge,
+    /// Gauge for jobs collecting DA data
+    pub da_jobs_in_flight: Gauge,
+    /// Gauge for jobs requesting or awaiting a ZK proof
+    pub zk_jobs_in_flight: Gauge,
+    /// Counter for automatic retries of failed jobs
+    pub jobs_retried: Counter<u64>,
+    /// Counter for jobs that failed after using all retry attempts
+    pub jobs_retries_exhausted: Counter<u64>,
+    /// Counter for completed aggregations
+    pub aggregations_finished: Counter<u64>,
+    /// Counter for failed aggregations
+    pub aggregations_failed: Counter<u64>,
+    /// Counter for successful jobs evicted from the finished DB
+    pub proofs_evicted: Counter<u64>,
+    /// Counter for permanently failed jobs evicted from the finished DB
+    pub failures_evicted: Counter<u64>,
+    /// Counter for proofs compacted in the finished DB
+    pub proofs_compacted: Counter<u64>,
+    /// Counter for DA proofs reused from the cache, or another job's fetch
+    pub da_cache_hits: Counter<u64>,
+    /// Counter for DA proofs fetched from the DA node
+    pub da_cache_misses: Counter<u64>,
+    /// Counter for new jobs rejected by a rate limit
+    pub jobs_rate_limited: Family<ClientLabels, Counter>,
+    /// Counter for new jobs and proof requests rejected by a proof quota
+    pub quota_exceeded: Family<ClientLabels, Counter>,
+    /// Gauge for proofs requested in the current quota period
+    pub quota_proofs_used: Family<QuotaLabels, Gauge>,
 }
 
 impl PromMetrics {
-    /// Create a new registry and register default metrics
-    pub fn new() -> Self {
+    /// Create a new registry and register default metrics,
+    /// with proof wait times bucketed by the `zk_proof_gen_timeout`
+    pub fn new(zk_proof_gen_timeout: Duration) -> Self {
         let mut registry = <Registry>::with_prefix("eqs");
         let grpc_req = Counter::default();
         registry.register(
@@ -62,13 +137,7 @@ impl PromMetrics {
             jobs_errors.clone(),
         );
 
-        let zk_proof_gen_timeout_float = Duration::from_secs(
-            std::env::var("PROOF_GEN_TIMEOUT_SECONDS")
-                .expect("PROOF_GEN_TIMEOUT_SECONDS env var required")
-                .parse()
-                .expect("PROOF_GEN_TIMEOUT_SECONDS must be integer"),
-        )
-        .as_secs_f64();
+        let zk_proof_gen_timeout_float = zk_proof_gen_timeout.as_secs_f64();
 
         let zk_proof_wait_time = Histogram::new(
             // 5% of timeout seconds buckets from 0 to 110% (assuming we may sometimes blow past timeout)
@@ -76,10 +145,129 @@ impl PromMetrics {
         );
         registry.register(
             "zk_proof_wait_time",
-            "Time taken to wait for ZK proof completion in seconds (Buckets of 5% PROOF_GEN_TIMEOUT_SECONDS env var)",
+            "Time taken to wait for ZK proof completion in seconds (Buckets of 5% zk.proof_gen_timeout_seconds)",
             zk_proof_wait_time.clone(),
         );
 
+        let jobs_rejected = Counter::default();
+        registry.register(
+            "jobs_rejected",
+            "Total number of new jobs and retries rejected as the job queue was full",
+            jobs_rejected.clone(),
+        );
+
+        let da_queue_depth = Gauge::default();
+        registry.register(
+            "da_queue_depth",
+            "Jobs waiting for a worker to collect DA data",
+            da_queue_depth.clone(),
+        );
+
+        let zk_queue_depth = Gauge::default();
+        registry.register(
+            "zk_queue_depth",
+            "Jobs waiting for a worker to request or await a ZK proof",
+            zk_queue_depth.clone(),
+        );
+
+        let da_jobs_in_flight = Gauge::default();
+        registry.register(
+            "da_jobs_in_flight",
+            "Jobs currently collecting DA data",
+            da_jobs_in_flight.clone(),
+        );
+
+        let zk_jobs_in_flight = Gauge::default();
+        registry.register(
+            "zk_jobs_in_flight",
+            "Jobs currently requesting or awaiting a ZK proof",
+            zk_jobs_in_flight.clone(),
+        );
+
+        let jobs_retried = Counter::default();
+        registry.register(
+            "jobs_retried",
+            "Total number of automatic retries of jobs with a retryable failure",
+            jobs_retried.clone(),
+        );
+
+        let jobs_retries_exhausted = Counter::default();
+        registry.register(
+            "jobs_retries_exhausted",
+            "Total number of jobs that became permanent failures after using all retry attempts",
+            jobs_retries_exhausted.clone(),
+        );
+
+        let aggregations_finished = Counter::default();
+        registry.register(
+            "aggregations_finished",
+            "Total number of aggregations of many jobs completed successfully",
+            aggregations_finished.clone(),
+        );
+
+        let aggregations_failed = Counter::default();
+        registry.register(
+            "aggregations_failed",
+            "Total number of aggregations of many jobs failed",
+            aggregations_failed.clone(),
+        );
+
+        let proofs_evicted = Counter::default();
+        registry.register(
+            "proofs_evicted",
+            "Total number of successful jobs evicted from the finished DB by the retention policy",
+            proofs_evicted.clone(),
+        );
+
+        let failures_evicted = Counter::default();
+        registry.register(
+            "failures_evicted",
+            "Total number of permanently failed jobs evicted from the finished DB by the retention policy",
+            failures_evicted.clone(),
+        );
+
+        let proofs_compacted = Counter::default();
+        registry.register(
+            "proofs_compacted",
+            "Total number of proofs compacted to only their proof bytes and public values",
+            proofs_compacted.clone(),
+        );
+
+        let da_cache_hits = Counter::default();
+        registry.register(
+            "da_cache_hits",
+            "Total number of DA proofs reused for a job from one of the same blob",
+            da_cache_hits.clone(),
+        );
+
+        let da_cache_misses = Counter::default();
+        registry.register(
+            "da_cache_misses",
+            "Total number of DA proofs fetched from the DA node for a job",
+            da_cache_misses.clone(),
+        );
+
+        let jobs_rate_limited = Family::<ClientLabels, Counter>::default();
+        registry.register(
+            "jobs_rate_limited",
+            "New jobs rejected by a rate limit, labeled by tenant and chain ID",
+            jobs_rate_limited.clone(),
+        );
+
+        let quota_exceeded = Family::<ClientLabels, Counter>::default();
+        registry.register(
+            "quota_exceeded",
+            "New jobs and proof requests rejected by a proof quota, labeled by tenant and chain ID",
+            quota_exceeded.clone(),
+        );
+
+        let quota_proofs_used = Family::<QuotaLabels, Gauge>::default();
+        registry.register(
+            "quota_proofs_used",
+            "Proofs requested in the current quota period, labeled by tenant, chain ID and period",
+            quota_proofs_used.clone(),
+        );
+
         PromMetrics {
             registry: Arc::new(registry),
             grpc_req,
@@ -87,6 +275,23 @@ impl PromMetrics {
             jobs_finished,
             jobs_errors,
             zk_proof_wait_time,
+            jobs_rejected,
+            da_queue_depth,
+            zk_queue_depth,
+            da_jobs_in_flight,
+            zk_jobs_in_flight,
+            jobs_retried,
+            jobs_retries_exhausted,
+            aggregations_finished,
+            aggregations_failed,
+            proofs_evicted,
+            failures_evicted,
+            proofs_compacted,
+            da_cache_hits,
+            da_cache_misses,
+            jobs_rate_limited,
+            quota_exceeded,
+            quota_proofs_used,
         }
     }
 
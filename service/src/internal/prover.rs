@@ -0,0 +1,426 @@
+use crate::{SP1ProofSetup, SuccNetJobId};
+
+use async_trait::async_trait;
+use eq_common::InclusionServiceError;
+use log::{debug, warn};
+use sha3::{Digest, Sha3_256};
+use sp1_sdk::{
+    network::Error as SP1NetworkError, CpuProver, NetworkProver as SP1NetworkProver, Prover,
+    SP1ProofMode, SP1ProofWithPublicValues, SP1Stdin,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Which [ZkProverBackend] the service drives [Job](crate::Job)s with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZkProofMode {
+    /// Succinct's prover network, requires `NETWORK_PRIVATE_KEY`
+    Network,
+    /// Local CPU prover, very slow but needs no network access
+    Cpu,
+    /// SP1's mock prover, produces proofs that are NOT valid on chain
+    Mock,
+}
+
+impl FromStr for ZkProofMode {
+    type Err = InclusionServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "network" => Ok(Self::Network),
+            "cpu" => Ok(Self::Cpu),
+            "mock" => Ok(Self::Mock),
+            _ => Err(InclusionServiceError::InvalidParameter(format!(
+                "Unknown ZK proof mode '{s}', expected one of: network, cpu, mock"
+            ))),
+        }
+    }
+}
+
+/// Errors from a [ZkProverBackend], mapped into a [JobStatus](crate::JobStatus) by the service.
+#[derive(Debug)]
+pub enum ZkBackendError {
+    /// A known failure reported by Succinct's prover network
+    Network(SP1NetworkError),
+    /// A local prover failed executing or proving the program, retrying will not help
+    LocalExecution(String),
+    /// The backend has no record of this request (e.g. a local prover was restarted)
+    UnknownRequest(SuccNetJobId),
+    /// Anything we don't have a concrete type for
+    Unhandled(String),
+}
+
+impl std::fmt::Display for ZkBackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ZkBackendError::Network(e) => write!(f, "{e}"),
+            ZkBackendError::LocalExecution(e) => write!(f, "Local prover failure: {e}"),
+            ZkBackendError::UnknownRequest(id) => {
+                write!(f, "Unknown proof request 0x{}", hex::encode(id))
+            }
+            ZkBackendError::Unhandled(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// A provider of SP1 proofs, used by the service for any [Job](crate::Job) in a ZK state.
+///
+/// Proofs are requested and awaited in two steps, so that a request ID can be
+/// stored in the queue DB and awaited again after a restart.
+#[async_trait]
+pub trait ZkProverBackend: Send + Sync {
+    /// A short name for logs
+    fn name(&self) -> &'static str;
+
+    /// Generate the proving and verifying keys for a zkVM program ELF.
+    /// This is a heavy task, callers should cache the result.
+    async fn setup(&self, elf: &'static [u8]) -> Result<SP1ProofSetup, InclusionServiceError>;
+
+    /// Start a proof request, returning an ID to [wait](Self::wait_proof) on.
+    ///
+    /// Proofs verified on chain are [SP1ProofMode::Groth16], proofs to be verified
+    /// by another zkVM program are [SP1ProofMode::Compressed].
+    ///
+    /// `timeout` is the deadline given to the prover network. Local backends ignore it,
+    /// as they can't stop a proof once it's started.
+    async fn request_proof(
+        &self,
+        setup: Arc<SP1ProofSetup>,
+        stdin: SP1Stdin,
+        mode: SP1ProofMode,
+        timeout: Duration,
+    ) -> Result<SuccNetJobId, ZkBackendError>;
+
+    /// Await a proof previously started with [request_proof](Self::request_proof).
+    async fn wait_proof(
+        &self,
+        request_id: SuccNetJobId,
+    ) -> Result<SP1ProofWithPublicValues, ZkBackendError>;
+
+    /// Stop waiting on, and if possible stop working on, a proof request.
+    async fn cancel(&self, request_id: SuccNetJobId) -> Result<(), ZkBackendError>;
+
+    /// Check the prover can be reached, without requesting a proof
+    async fn check(&self) -> Result<(), ZkBackendError>;
+}
+
+/// Build the [ZkProverBackend] for a [ZkProofMode]
+pub fn build_zk_backend(mode: ZkProofMode) -> Arc<dyn ZkProverBackend> {
+    match mode {
+        ZkProofMode::Network => Arc::new(NetworkProverBackend::new()),
+        ZkProofMode::Cpu => Arc::new(LocalProverBackend::cpu()),
+        ZkProofMode::Mock => Arc::new(LocalProverBackend::mock()),
+    }
+}
+
+/// Proofs from Succinct's prover network.
+/// See: <https://docs.succinct.xyz/docs/sp1/generating-proofs/prover-network/usage>
+pub struct NetworkProverBackend {
+    client: Arc<SP1NetworkProver>,
+}
+
+impl NetworkProverBackend {
+    pub fn new() -> Self {
+        debug!("Building ZK network client");
+        Self {
+            client: Arc::new(sp1_sdk::ProverClient::builder().network().build()),
+        }
+    }
+}
+
+impl Default for NetworkProverBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ZkProverBackend for NetworkProverBackend {
+    fn name(&self) -> &'static str {
+        "network"
+    }
+
+    async fn setup(&self, elf: &'static [u8]) -> Result<SP1ProofSetup, InclusionServiceError> {
+        let client = self.client.clone();
+        tokio::task::spawn_blocking(move || client.setup(elf).into())
+            .await
+            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+    }
+
+    async fn request_proof(
+        &self,
+        setup: Arc<SP1ProofSetup>,
+        stdin: SP1Stdin,
+        mode: SP1ProofMode,
+        timeout: Duration,
+    ) -> Result<SuccNetJobId, ZkBackendError> {
+        let request_id = self
+            .client
+            .prove(&setup.pk, &stdin)
+            .mode(mode)
+            .skip_simulation(false)
+            .timeout(timeout)
+            .request_async()
+            .await
+            .map_err(network_error)?;
+        Ok(request_id.into())
+    }
+
+    async fn wait_proof(
+        &self,
+        request_id: SuccNetJobId,
+    ) -> Result<SP1ProofWithPublicValues, ZkBackendError> {
+        self.client
+            .wait_proof(request_id.into(), None, None)
+            .await
+            .map_err(network_error)
+    }
+
+    async fn cancel(&self, request_id: SuccNetJobId) -> Result<(), ZkBackendError> {
+        // The network has no cancel API, the request will expire at it's deadline
+        warn!(
+            "Prover network request 0x{} abandoned, it will expire on the network",
+            hex::encode(request_id)
+        );
+        Ok(())
+    }
+
+    async fn check(&self) -> Result<(), ZkBackendError> {
+        // A reachable network reports an unknown request as not found
+        match self.client.get_proof_status([0; 32].into()).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.to_string().to_lowercase().contains("not found") => Ok(()),
+            Err(e) => Err(network_error(e)),
+        }
+    }
+}
+
+// TODO: how to handle errors without a concrete type? Anyhow is not the right thing for us...
+fn network_error(e: anyhow::Error) -> ZkBackendError {
+    match e.downcast::<SP1NetworkError>() {
+        Ok(down) => ZkBackendError::Network(down),
+        Err(e) => ZkBackendError::Unhandled(format!("{e:?}")),
+    }
+}
+
+/// Proofs generated in-process, with SP1's CPU or mock prover.
+///
+/// Requests are tracked in memory only, so a restart loses them and
+/// [wait_proof](ZkProverBackend::wait_proof) reports [ZkBackendError::UnknownRequest].
+pub struct LocalProverBackend {
+    name: &'static str,
+    client: Arc<CpuProver>,
+    /// Proofs running (or waiting to run) on the blocking thread pool, until awaited
+    requests: Mutex<HashMap<SuccNetJobId, JoinHandle<LocalProofResult>>>,
+    /// Set on [cancel](ZkProverBackend::cancel), checked before proving starts.
+    /// Kept until the proof is done, so a request being awaited can still be cancelled.
+    cancellations: Arc<Mutex<HashMap<SuccNetJobId, Arc<AtomicBool>>>>,
+    nonce: AtomicU64,
+}
+
+type LocalProofResult = Result<SP1ProofWithPublicValues, String>;
+
+impl LocalProverBackend {
+    pub fn cpu() -> Self {
+        debug!("Building ZK local CPU client");
+        Self::with_client("cpu", sp1_sdk::ProverClient::builder().cpu().build())
+    }
+
+    pub fn mock() -> Self {
+        debug!("Building ZK mock client");
+        Self::with_client("mock", sp1_sdk::ProverClient::builder().mock().build())
+    }
+
+    fn with_client(name: &'static str, client: CpuProver) -> Self {
+        Self {
+            name,
+            client: Arc::new(client),
+            requests: Mutex::new(HashMap::new()),
+            cancellations: Arc::new(Mutex::new(HashMap::new())),
+            nonce: AtomicU64::new(0),
+        }
+    }
+
+    /// Unique per process, the network would assign this for us otherwise
+    fn next_request_id(&self) -> SuccNetJobId {
+        let nonce = self.nonce.fetch_add(1, Ordering::Relaxed);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Sha3_256::new()
+            .chain_update(self.name.as_bytes())
+            .chain_update(now.to_le_bytes())
+            .chain_update(nonce.to_le_bytes())
+            .finalize()
+            .into()
+    }
+
+    /// Run `prove` on the blocking thread pool, unless cancelled before it starts
+    fn start_proof(
+        &self,
+        prove: impl FnOnce() -> LocalProofResult + Send + 'static,
+    ) -> SuccNetJobId {
+        let request_id = self.next_request_id();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.cancellations
+            .lock()
+            .expect("Local prover cancellations lock poisoned")
+            .insert(request_id, cancelled.clone());
+        let cancellations = self.cancellations.clone();
+        let handle = tokio::task::spawn_blocking(move || {
+            // Waiting on a free blocking thread may take a while, don't start if cancelled
+            let result = match cancelled.load(Ordering::SeqCst) {
+                true => Err("Cancelled before proving started".to_string()),
+                false => prove(),
+            };
+            cancellations
+                .lock()
+                .expect("Local prover cancellations lock poisoned")
+                .remove(&request_id);
+            result
+        });
+        self.requests
+            .lock()
+            .expect("Local prover request lock poisoned")
+            .insert(request_id, handle);
+        request_id
+    }
+}
+
+#[async_trait]
+impl ZkProverBackend for LocalProverBackend {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn setup(&self, elf: &'static [u8]) -> Result<SP1ProofSetup, InclusionServiceError> {
+        let client = self.client.clone();
+        tokio::task::spawn_blocking(move || client.setup(elf).into())
+            .await
+            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+    }
+
+    async fn request_proof(
+        &self,
+        setup: Arc<SP1ProofSetup>,
+        stdin: SP1Stdin,
+        mode: SP1ProofMode,
+        _timeout: Duration,
+    ) -> Result<SuccNetJobId, ZkBackendError> {
+        let client = self.client.clone();
+        Ok(self.start_proof(move || {
+            client
+                .prove(&setup.pk, &stdin)
+                .mode(mode)
+                .run()
+                .map_err(|e| e.to_string())
+        }))
+    }
+
+    async fn wait_proof(
+        &self,
+        request_id: SuccNetJobId,
+    ) -> Result<SP1ProofWithPublicValues, ZkBackendError> {
+        let handle = self
+            .requests
+            .lock()
+            .expect("Local prover request lock poisoned")
+            .remove(&request_id)
+            .ok_or(ZkBackendError::UnknownRequest(request_id))?;
+        handle
+            .await
+            .map_err(|e| ZkBackendError::LocalExecution(e.to_string()))?
+            .map_err(ZkBackendError::LocalExecution)
+    }
+
+    async fn cancel(&self, request_id: SuccNetJobId) -> Result<(), ZkBackendError> {
+        let cancelled = self
+            .cancellations
+            .lock()
+            .expect("Local prover cancellations lock poisoned")
+            .get(&request_id)
+            .cloned()
+            .ok_or(ZkBackendError::UnknownRequest(request_id))?;
+        cancelled.store(true, Ordering::SeqCst);
+        // Nobody waits on it's result any more
+        self.requests
+            .lock()
+            .expect("Local prover request lock poisoned")
+            .remove(&request_id);
+        // NOTE: SP1 can't interrupt a proof once started, it runs to completion on it's
+        // blocking thread and the result is dropped
+        warn!(
+            "Local proof request 0x{} cancelled, if already proving it runs until done",
+            hex::encode(request_id)
+        );
+        Ok(())
+    }
+
+    async fn check(&self) -> Result<(), ZkBackendError> {
+        // Runs in-process, nothing to reach
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cancel_before_proving() {
+        // A single blocking thread, kept busy so the proof waits for it
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .max_blocking_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let backend = Arc::new(LocalProverBackend::mock());
+            let (release, busy) = std::sync::mpsc::channel::<()>();
+            let blocker = tokio::task::spawn_blocking(move || busy.recv());
+            let proven = Arc::new(AtomicBool::new(false));
+            let request_id = backend.start_proof({
+                let proven = proven.clone();
+                move || {
+                    proven.store(true, Ordering::SeqCst);
+                    Err("Proven".to_string())
+                }
+            });
+
+            // Cancelled while being awaited
+            let wait = tokio::spawn({
+                let backend = backend.clone();
+                async move { backend.wait_proof(request_id).await }
+            });
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            wait.abort();
+            backend.cancel(request_id).await.unwrap();
+
+            release.send(()).unwrap();
+            blocker.await.unwrap().unwrap();
+            tokio::time::timeout(Duration::from_secs(10), async {
+                while backend
+                    .cancellations
+                    .lock()
+                    .unwrap()
+                    .contains_key(&request_id)
+                {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .unwrap();
+            assert!(!proven.load(Ordering::SeqCst));
+            // Forgotten once skipped
+            assert!(matches!(
+                backend.cancel(request_id).await,
+                Err(ZkBackendError::UnknownRequest(_))
+            ));
+        });
+    }
+}
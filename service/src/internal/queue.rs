@@ -0,0 +1,109 @@
+use crate::{Job, JobStatus};
+
+use eq_common::InclusionServiceError;
+use tokio::sync::mpsc::{self, error::TrySendError, Permit, PermitIterator};
+
+/// The pipeline stages a [Job] is worked on in, each with it's own queue & concurrency limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStage {
+    /// Collecting inclusion proofs from the DA node
+    DataAvailability,
+    /// Requesting and awaiting a ZK proof
+    ZkProof,
+}
+
+impl JobStage {
+    /// The stage that progresses a [JobStatus], if any.
+    /// Finished and failed jobs have no further work.
+    pub fn for_status(status: &JobStatus) -> Option<Self> {
+        match status {
+            JobStatus::DataAvailabilityPending => Some(JobStage::DataAvailability),
+            JobStatus::DataAvailable(_)
+            | JobStatus::DataAvailableMultiBlob(_)
+            | JobStatus::DataAvailablePayy(_)
+            | JobStatus::ZkProofPending(_) => Some(JobStage::ZkProof),
+            JobStatus::ZkProofFinished(_)
+            | JobStatus::ZkProofCompacted(_)
+            | JobStatus::ZkProofStored(_)
+            | JobStatus::Failed(_, _) => None,
+        }
+    }
+}
+
+/// Bounded queues of [Job]s, one per [JobStage].
+#[derive(Clone)]
+pub struct JobQueue {
+    da_sender: mpsc::Sender<Job>,
+    zk_sender: mpsc::Sender<Job>,
+}
+
+/// The receiving ends of a [JobQueue], consumed by
+/// [InclusionService::job_worker](crate::InclusionService::job_worker).
+pub struct JobReceivers {
+    pub da_receiver: mpsc::Receiver<Job>,
+    pub zk_receiver: mpsc::Receiver<Job>,
+}
+
+impl JobQueue {
+    /// Create queues holding at most `capacity` jobs *per stage*
+    pub fn new(capacity: usize) -> (Self, JobReceivers) {
+        let (da_sender, da_receiver) = mpsc::channel(capacity);
+        let (zk_sender, zk_receiver) = mpsc::channel(capacity);
+        (
+            Self {
+                da_sender,
+                zk_sender,
+            },
+            JobReceivers {
+                da_receiver,
+                zk_receiver,
+            },
+        )
+    }
+
+    fn sender(&self, stage: JobStage) -> &mpsc::Sender<Job> {
+        match stage {
+            JobStage::DataAvailability => &self.da_sender,
+            JobStage::ZkProof => &self.zk_sender,
+        }
+    }
+
+    /// Add a job to a stage, waiting for room in the queue if needed.
+    pub async fn enqueue(&self, job: Job, stage: JobStage) -> Result<(), InclusionServiceError> {
+        self.sender(stage)
+            .send(job)
+            .await
+            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))
+    }
+
+    /// Reserve room for a job in a stage, failing with [InclusionServiceError::QueueFull]
+    /// rather than waiting.
+    pub fn try_reserve(&self, stage: JobStage) -> Result<Permit<'_, Job>, InclusionServiceError> {
+        self.sender(stage).try_reserve().map_err(|e| match e {
+            TrySendError::Full(_) => InclusionServiceError::QueueFull,
+            TrySendError::Closed(_) => {
+                InclusionServiceError::InternalError("Job queue closed".to_string())
+            }
+        })
+    }
+
+    /// Like [Self::try_reserve], but for `n` jobs at once: all are reserved, or none are.
+    pub fn try_reserve_many(
+        &self,
+        stage: JobStage,
+        n: usize,
+    ) -> Result<PermitIterator<'_, Job>, InclusionServiceError> {
+        self.sender(stage).try_reserve_many(n).map_err(|e| match e {
+            TrySendError::Full(_) => InclusionServiceError::QueueFull,
+            TrySendError::Closed(_) => {
+                InclusionServiceError::InternalError("Job queue closed".to_string())
+            }
+        })
+    }
+
+    /// Number of jobs waiting in a stage's queue
+    pub fn depth(&self, stage: JobStage) -> usize {
+        let sender = self.sender(stage);
+        sender.max_capacity() - sender.capacity()
+    }
+}
@@ -0,0 +1,380 @@
+use crate::internal::auth::{
+    PeerCertificate, AUTHORIZATION_HEADER, HMAC_KEY_ID_HEADER, HMAC_SIGNATURE_HEADER,
+    HMAC_TIMESTAMP_HEADER,
+};
+use crate::internal::grpc::InclusionServiceArc;
+use crate::InclusionService;
+
+use base64::Engine;
+use eq_common::eqs::get_zk_stack_response::ResponseValue;
+use eq_common::eqs::inclusion_server::Inclusion;
+use eq_common::eqs::{GetZkStackRequest, GetZkStackResponse};
+use eq_common::InclusionServiceError;
+use eq_sdk::types::BlobId;
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::{header, server::conn::http1, service::service_fn};
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use log::{debug, info};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tonic::transport::server::TcpConnectInfo;
+use tonic::{Code, Status};
+use tonic_types::StatusExt;
+
+/// Path of the HTTP equivalent of GetZKStack, followed by a [BlobId] string
+pub const ZKSTACK_PATH: &str = "/v1/zkstack/";
+
+/// HTTP headers passed on as gRPC metadata, for the caller to authenticate with
+const CREDENTIAL_HEADERS: [&str; 4] = [
+    AUTHORIZATION_HEADER,
+    HMAC_KEY_ID_HEADER,
+    HMAC_TIMESTAMP_HEADER,
+    HMAC_SIGNATURE_HEADER,
+];
+
+/// How bytes are encoded in JSON responses, by the `encoding` query parameter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BytesEncoding {
+    /// `0x` prefixed hex
+    #[default]
+    Hex,
+    /// Standard base64, with padding
+    Base64,
+}
+
+impl BytesEncoding {
+    fn from_query(query: Option<&str>) -> Result<Self, String> {
+        let encoding = query
+            .unwrap_or_default()
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("encoding="));
+        match encoding {
+            None | Some("hex") => Ok(BytesEncoding::Hex),
+            Some("base64") => Ok(BytesEncoding::Base64),
+            Some(other) => Err(format!("Unknown encoding {other}, expected hex or base64")),
+        }
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            BytesEncoding::Hex => format!("0x{}", hex::encode(bytes)),
+            BytesEncoding::Base64 => base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+}
+
+impl InclusionService {
+    /// Serve the HTTP/JSON gateway, for clients that can't speak gRPC.
+    ///
+    /// `GET /v1/zkstack/{blob_id}` is a GetZKStack request for a [BlobId], formatted as
+    /// `height:namespace:commitment:chain_id:batch_number` (URL encoded if need be).
+    /// It has the same auth and limits as gRPC, with credentials sent as HTTP headers,
+    /// or as a TLS client certificate when served over `tls`.
+    pub async fn serve_rest(
+        self: Arc<Self>,
+        addr: SocketAddr,
+        tls: Option<TlsAcceptor>,
+    ) -> Result<(), InclusionServiceError> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
+        let server = http1::Builder::new();
+
+        match tls {
+            Some(_) => info!("HTTP gateway serving on {addr}, over TLS"),
+            None => info!("HTTP gateway serving on {addr}"),
+        }
+
+        loop {
+            let (stream, remote_addr) = listener
+                .accept()
+                .await
+                .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?;
+            let service = self.clone();
+            let builder = server.clone();
+            let tls = tls.clone();
+
+            // The handshake is in the connection's task, so a slow client can't stall accepts
+            tokio::spawn(async move {
+                let Some(tls) = tls else {
+                    return serve_connection(service, builder, stream, remote_addr, None).await;
+                };
+                match tls.accept(stream).await {
+                    Ok(stream) => {
+                        let peer_cert = stream
+                            .get_ref()
+                            .1
+                            .peer_certificates()
+                            .and_then(|certs| certs.first())
+                            .map(|leaf| PeerCertificate(leaf.to_vec()));
+                        serve_connection(service, builder, stream, remote_addr, peer_cert).await
+                    }
+                    Err(e) => debug!("HTTP gateway TLS handshake with {remote_addr} failed: {e}"),
+                }
+            });
+        }
+    }
+}
+
+async fn serve_connection<S>(
+    service: Arc<InclusionService>,
+    builder: http1::Builder,
+    stream: S,
+    remote_addr: SocketAddr,
+    peer_cert: Option<PeerCertificate>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let handler = service_fn(move |request| {
+        let service = service.clone();
+        let peer_cert = peer_cert.clone();
+        async move {
+            Ok::<_, Infallible>(handle_request(service, request, remote_addr, peer_cert).await)
+        }
+    });
+    if let Err(e) = builder
+        .serve_connection(TokioIo::new(stream), handler)
+        .await
+    {
+        debug!("HTTP gateway connection error: {e:?}");
+    }
+}
+
+async fn handle_request(
+    service: Arc<InclusionService>,
+    request: Request<Incoming>,
+    remote_addr: SocketAddr,
+    peer_cert: Option<PeerCertificate>,
+) -> Response<Full<Bytes>> {
+    let Some(blob_id) = request.uri().path().strip_prefix(ZKSTACK_PATH) else {
+        return error_response(StatusCode::NOT_FOUND, "NOT_FOUND", "Unknown path");
+    };
+    if request.method() != Method::GET {
+        return error_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "METHOD_NOT_ALLOWED",
+            "Only GET is allowed",
+        );
+    }
+    let encoding = match BytesEncoding::from_query(request.uri().query()) {
+        Ok(encoding) => encoding,
+        Err(e) => return status_response(&Status::invalid_argument(e)),
+    };
+    let blob_id = match parse_blob_id(blob_id) {
+        Ok(blob_id) => blob_id,
+        Err(e) => return status_response(&Status::invalid_argument(e)),
+    };
+
+    let mut grpc_request = tonic::Request::new(GetZkStackRequest {
+        height: blob_id.height.value(),
+        namespace: blob_id
+            .namespace
+            .id_v0()
+            .map(|id| id.to_vec())
+            .unwrap_or_default(),
+        commitment: blob_id.commitment.hash().to_vec(),
+        batch_number: blob_id.batch_number,
+        chain_id: blob_id.l2_chain_id,
+    });
+    for name in CREDENTIAL_HEADERS {
+        let value = request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        if let Some(value) = value {
+            grpc_request.metadata_mut().insert(name, value);
+        }
+    }
+    // For rate limits of anonymous callers, by IP address
+    grpc_request.extensions_mut().insert(TcpConnectInfo {
+        local_addr: None,
+        remote_addr: Some(remote_addr),
+    });
+    if let Some(peer_cert) = peer_cert {
+        grpc_request.extensions_mut().insert(peer_cert);
+    }
+
+    match InclusionServiceArc(service)
+        .get_zk_stack(grpc_request)
+        .await
+    {
+        Ok(response) => json_response(
+            StatusCode::OK,
+            &response_json(&response.into_inner(), encoding),
+        ),
+        Err(status) => status_response(&status),
+    }
+}
+
+/// A [BlobId] from a URL path segment, that may have it's `:`, `/`, `+` and `=` URL encoded
+fn parse_blob_id(segment: &str) -> Result<BlobId, String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let byte = tail
+                .get(..2)
+                .and_then(|escape| hex::decode(escape).ok())
+                .ok_or("Invalid URL encoding of blob ID")?;
+            bytes.extend(byte);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    let blob_id = String::from_utf8(bytes).map_err(|_| "Blob ID must be UTF-8")?;
+    BlobId::from_str(&blob_id).map_err(|e| {
+        format!("Invalid blob ID ({e}), expected height:namespace:commitment:chain_id:batch_number")
+    })
+}
+
+/// The JSON of a GetZKStack response, fields unset in the response are left out
+pub fn response_json(response: &GetZkStackResponse, encoding: BytesEncoding) -> Value {
+    let mut json = json!({
+        "status": response.status().as_str_name(),
+        "retry_attempts": response.retry_attempts,
+    });
+    if response.next_retry_unix_ms != 0 {
+        json["next_retry_unix_ms"] = response.next_retry_unix_ms.into();
+    }
+    if !response.program_id.is_empty() {
+        json["program_id"] = encoding.encode(&response.program_id).into();
+    }
+    match &response.response_value {
+        Some(ResponseValue::ProofId(proof_id)) => {
+            json["proof_id"] = encoding.encode(proof_id).into();
+        }
+        Some(ResponseValue::Proof(proof)) => {
+            json["proof"] = json!({
+                "proof_data": encoding.encode(&proof.proof_data),
+                "public_values": encoding.encode(&proof.public_values),
+            });
+        }
+        Some(ResponseValue::ErrorMessage(message)) => {
+            json["error_message"] = message.clone().into();
+        }
+        Some(ResponseValue::StatusMessage(message)) => {
+            json["status_message"] = message.clone().into();
+        }
+        None => {}
+    }
+    json
+}
+
+/// A gRPC error as it's HTTP equivalent, with a `Retry-After` header if it has `RetryInfo`
+fn status_response(status: &Status) -> Response<Full<Bytes>> {
+    let (http_status, name) = match status.code() {
+        Code::Ok => (StatusCode::OK, "OK"),
+        Code::Cancelled => (StatusCode::from_u16(499).expect("Valid"), "CANCELLED"),
+        Code::Unknown => (StatusCode::INTERNAL_SERVER_ERROR, "UNKNOWN"),
+        Code::InvalidArgument => (StatusCode::BAD_REQUEST, "INVALID_ARGUMENT"),
+        Code::DeadlineExceeded => (StatusCode::GATEWAY_TIMEOUT, "DEADLINE_EXCEEDED"),
+        Code::NotFound => (StatusCode::NOT_FOUND, "NOT_FOUND"),
+        Code::AlreadyExists => (StatusCode::CONFLICT, "ALREADY_EXISTS"),
+        Code::PermissionDenied => (StatusCode::FORBIDDEN, "PERMISSION_DENIED"),
+        Code::ResourceExhausted => (StatusCode::TOO_MANY_REQUESTS, "RESOURCE_EXHAUSTED"),
+        Code::FailedPrecondition => (StatusCode::BAD_REQUEST, "FAILED_PRECONDITION"),
+        Code::Aborted => (StatusCode::CONFLICT, "ABORTED"),
+        Code::OutOfRange => (StatusCode::BAD_REQUEST, "OUT_OF_RANGE"),
+        Code::Unimplemented => (StatusCode::NOT_IMPLEMENTED, "UNIMPLEMENTED"),
+        Code::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL"),
+        Code::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "UNAVAILABLE"),
+        Code::DataLoss => (StatusCode::INTERNAL_SERVER_ERROR, "DATA_LOSS"),
+        Code::Unauthenticated => (StatusCode::UNAUTHORIZED, "UNAUTHENTICATED"),
+    };
+    let mut response = error_response(http_status, name, status.message());
+    let retry_after = status
+        .get_details_retry_info()
+        .and_then(|retry_info| retry_info.retry_delay);
+    if let Some(retry_after) = retry_after {
+        // Whole seconds, rounded up so a client doesn't retry too soon
+        let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, seconds.into());
+    }
+    response
+}
+
+fn error_response(status: StatusCode, name: &str, message: &str) -> Response<Full<Bytes>> {
+    json_response(
+        status,
+        &json!({
+            "error": {
+                "code": status.as_u16(),
+                "status": name,
+                "message": message,
+            }
+        }),
+    )
+}
+
+fn json_response(status: StatusCode, json: &Value) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(json.to_string())))
+        .expect("Response is malformed")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use eq_common::eqs::get_zk_stack_response::Status as ResponseStatus;
+    use eq_common::eqs::ProofWithPublicValues;
+
+    #[test]
+    fn test_rest_gateway() {
+        let blob_id = "6952283:c292LW1pbmktYQ==:JkVWHw0eLp6eeCEG28rLwF1xwUWGDI3+DbEyNNKq9fE=:0:0";
+        let url_encoded = blob_id
+            .replace(':', "%3A")
+            .replace('+', "%2B")
+            .replace('=', "%3d");
+        assert_eq!(
+            parse_blob_id(&url_encoded).unwrap(),
+            BlobId::from_str(blob_id).unwrap()
+        );
+        assert!(parse_blob_id("6952283:c292LW1pbmktYQ%3").is_err());
+        assert!(parse_blob_id("6952283").is_err());
+
+        assert_eq!(BytesEncoding::from_query(None), Ok(BytesEncoding::Hex));
+        assert_eq!(
+            BytesEncoding::from_query(Some("a=b&encoding=base64")),
+            Ok(BytesEncoding::Base64)
+        );
+        assert!(BytesEncoding::from_query(Some("encoding=base58")).is_err());
+
+        let response = GetZkStackResponse {
+            status: ResponseStatus::ZkpFinished as i32,
+            response_value: Some(ResponseValue::Proof(ProofWithPublicValues {
+                proof_data: vec![0xab, 0xcd],
+                public_values: vec![0xff],
+            })),
+            program_id: vec![0x01],
+            ..Default::default()
+        };
+        assert_eq!(
+            response_json(&response, BytesEncoding::Hex),
+            json!({
+                "status": "ZKP_FINISHED",
+                "retry_attempts": 0,
+                "program_id": "0x01",
+                "proof": { "proof_data": "0xabcd", "public_values": "0xff" },
+            })
+        );
+        assert_eq!(
+            response_json(&response, BytesEncoding::Base64)["proof"]["proof_data"],
+            "q80="
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use crate::internal::aggregation::{AggregationJob, AggregationStatus};
+use crate::internal::retry::unix_ms_now;
+use crate::internal::schema;
+use crate::internal::store::StoreTree;
+use crate::{InclusionService, JobStatus};
+
+use eq_common::InclusionServiceError;
+use log::{debug, error, info, warn};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the finished DB is checked against the [RetentionConfig]
+pub const RETENTION_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Limits on the finished [Job](crate::Job)s of one kind that are kept, `None` for no limit.
+///
+/// Once over a limit, the oldest `Job`s (by when they finished) are evicted first.
+/// An evicted `Job` is unknown to the service, and is started again if requested.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_age: Option<Duration>,
+    pub max_count: Option<usize>,
+    /// Total size of the stored [JobStatus]es
+    pub max_bytes: Option<u64>,
+}
+
+/// A finished [Job](crate::Job) subject to a [RetentionPolicy]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinishedRecord {
+    pub job_key: Vec<u8>,
+    pub finished_unix_ms: u64,
+    /// Size of the stored [JobStatus]
+    pub size: u64,
+}
+
+impl RetentionPolicy {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_age.is_none() && self.max_count.is_none() && self.max_bytes.is_none()
+    }
+
+    /// The records to evict, oldest first, to be within this policy at `now_unix_ms`
+    pub fn evictions(
+        &self,
+        mut records: Vec<FinishedRecord>,
+        now_unix_ms: u64,
+    ) -> Vec<FinishedRecord> {
+        records.sort_by_key(|r| r.finished_unix_ms);
+        let oldest_kept = self
+            .max_age
+            .map(|age| now_unix_ms.saturating_sub(age.as_millis() as u64));
+        let mut count = records.len();
+        let mut bytes: u64 = records.iter().map(|r| r.size).sum();
+        let evicted = records
+            .iter()
+            .take_while(|r| {
+                let evict = oldest_kept.is_some_and(|oldest| r.finished_unix_ms < oldest)
+                    || self.max_count.is_some_and(|max| count > max)
+                    || self.max_bytes.is_some_and(|max| bytes > max);
+                if evict {
+                    count -= 1;
+                    bytes -= r.size;
+                }
+                evict
+            })
+            .count();
+        records.truncate(evicted);
+        records
+    }
+}
+
+/// How long finished [Job](crate::Job)s are kept in the finished DB.
+///
+/// Retryable failures are never evicted, they are retried or become permanent failures.
+#[derive(Debug, Clone, Default)]
+pub struct RetentionConfig {
+    /// For [JobStatus::ZkProofFinished], [JobStatus::ZkProofCompacted] and
+    /// [JobStatus::ZkProofStored], including the size of a proof in the artifact store
+    pub proofs: RetentionPolicy,
+    /// For permanent [JobStatus::Failed] failures
+    pub failures: RetentionPolicy,
+    /// Replace every [JobStatus::ZkProofFinished] with a [JobStatus::ZkProofCompacted],
+    /// dropping everything but what is reported to clients (e.g. the proof's stdin)
+    pub compact_proofs: bool,
+}
+
+impl RetentionConfig {
+    /// True if nothing is ever evicted or compacted
+    pub fn keeps_everything(&self) -> bool {
+        self.proofs.is_unlimited() && self.failures.is_unlimited() && !self.compact_proofs
+    }
+}
+
+impl InclusionService {
+    /// Periodically compact and evict finished [Job](crate::Job)s per the [RetentionConfig],
+    /// and remove artifacts no longer referenced from the
+    /// [ArtifactStore](crate::internal::artifacts::ArtifactStore)
+    pub async fn retention_sweeper(self: Arc<Self>) {
+        if self.config.retention.keeps_everything() && self.config.artifact_store.is_none() {
+            debug!("No retention limits, finished jobs are kept forever");
+            return;
+        }
+        debug!("Retention sweeper started");
+        let mut interval = tokio::time::interval(RETENTION_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let _ = self
+                .sweep_finished_jobs()
+                .await
+                .map_err(|e| error!("Failed to apply retention to finished jobs: {e}"));
+        }
+    }
+
+    /// Compact and evict finished [Job](crate::Job)s, once, per the [RetentionConfig].
+    ///
+    /// Artifacts of [Job](crate::Job)s evicted or deleted before this sweep are removed from
+    /// the artifact store, those of `Job`s evicted by this sweep are removed by the next.
+    pub async fn sweep_finished_jobs(&self) -> Result<(), InclusionServiceError> {
+        let retention = &self.config.retention;
+        let now = unix_ms_now();
+        // Evicting or compacting these would leave their aggregation without their proofs
+        let aggregation_members = self.pending_aggregation_members()?;
+        let mut proofs = Vec::new();
+        let mut failures = Vec::new();
+        let mut artifacts = HashSet::new();
+        for entry in self.store.iter(StoreTree::Finished) {
+            let (job_key, mut status_data) = entry?;
+            let job_status: JobStatus = match schema::decode(&status_data) {
+                Ok(job_status) => job_status,
+                Err(e) => {
+                    warn!("Skipping undecodable finished job: {e}");
+                    continue;
+                }
+            };
+            if aggregation_members.contains(&job_key) {
+                continue;
+            }
+            if let (true, JobStatus::ZkProofFinished(proof)) =
+                (retention.compact_proofs, &job_status)
+            {
+                let compacted = schema::encode(&JobStatus::ZkProofCompacted(proof.into()))
+                    .expect("Always given serializable job status");
+                // Unless retried or compacted since it was read
+                if self.store.compare_and_swap(
+                    StoreTree::Finished,
+                    &job_key,
+                    Some(status_data.as_slice()),
+                    &compacted,
+                )? {
+                    self.metrics.proofs_compacted.inc();
+                    status_data = compacted;
+                }
+            }
+            let mut size = status_data.len() as u64;
+            let records = match job_status {
+                JobStatus::ZkProofFinished(_) | JobStatus::ZkProofCompacted(_) => &mut proofs,
+                JobStatus::ZkProofStored(stored) => {
+                    artifacts.insert(stored.proof_data);
+                    size += stored.proof_data_len;
+                    &mut proofs
+                }
+                JobStatus::Failed(_, None) => &mut failures,
+                _ => continue,
+            };
+            let finished_unix_ms = match self.store.get(StoreTree::FinishedAt, &job_key)? {
+                Some(data) => schema::decode(&data)
+                    .map_err(|e| InclusionServiceError::InternalError(e.to_string()))?,
+                // Finished before retention was tracked, so it's retained from now
+                None => {
+                    let data = schema::encode(&now).expect("Always given serializable finish time");
+                    self.store.insert(StoreTree::FinishedAt, &job_key, &data)?;
+                    now
+                }
+            };
+            records.push(FinishedRecord {
+                job_key,
+                finished_unix_ms,
+                size,
+            });
+        }
+
+        for (policy, records, evicted_counter) in [
+            (&retention.proofs, proofs, &self.metrics.proofs_evicted),
+            (
+                &retention.failures,
+                failures,
+                &self.metrics.failures_evicted,
+            ),
+        ] {
+            let mut evicted = 0;
+            for record in policy.evictions(records, now) {
+                if self.remove_finished_job(&record.job_key, Some(record.finished_unix_ms))? {
+                    evicted_counter.inc();
+                    evicted += 1;
+                }
+            }
+            if evicted > 0 {
+                info!("Evicted {evicted} finished jobs over the retention policy {policy:?}");
+            }
+        }
+
+        if let Some(artifact_store) = &self.config.artifact_store {
+            let removed = artifact_store.remove_unreferenced(&artifacts, now).await?;
+            if removed > 0 {
+                info!("Removed {removed} unreferenced artifacts");
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a [Job](crate::Job) from the finished DB, with everything else stored for it.
+    ///
+    /// With `finished_unix_ms`, it's only removed if it is the time the `Job` finished,
+    /// and so not removed if it has been retried since.
+    /// Returns false if there was nothing to remove.
+    pub fn remove_finished_job(
+        &self,
+        job_key: &[u8],
+        finished_unix_ms: Option<u64>,
+    ) -> Result<bool, InclusionServiceError> {
+        self.store.transaction(
+            &[
+                StoreTree::Finished,
+                StoreTree::Retry,
+                StoreTree::JobProgram,
+                StoreTree::FinishedAt,
+                StoreTree::JobTenant,
+                StoreTree::QuotaSpent,
+            ],
+            |tx| {
+                if let Some(finished_unix_ms) = finished_unix_ms {
+                    let stored_finished_unix_ms = tx
+                        .get(StoreTree::FinishedAt, job_key)?
+                        .and_then(|data| schema::decode::<u64>(&data).ok());
+                    if stored_finished_unix_ms != Some(finished_unix_ms) {
+                        return Ok(false);
+                    }
+                }
+                if tx.remove(StoreTree::Finished, job_key)?.is_none() {
+                    return Ok(false);
+                }
+                tx.remove(StoreTree::FinishedAt, job_key)?;
+                tx.remove(StoreTree::Retry, job_key)?;
+                tx.remove(StoreTree::JobProgram, job_key)?;
+                tx.remove(StoreTree::JobTenant, job_key)?;
+                tx.remove(StoreTree::QuotaSpent, job_key)?;
+                Ok(true)
+            },
+        )
+    }
+
+    /// Keys of the member [Job](crate::Job)s of every [AggregationJob] yet to request it's proof
+    fn pending_aggregation_members(&self) -> Result<HashSet<Vec<u8>>, InclusionServiceError> {
+        let mut members = HashSet::new();
+        for entry in self.store.iter(StoreTree::Aggregation) {
+            let (aggregation_key, status_data) = entry?;
+            let (
+                Ok(aggregation),
+                Ok(AggregationStatus::MembersPending | AggregationStatus::RequestingProof),
+            ) = (
+                schema::decode::<AggregationJob>(&aggregation_key),
+                schema::decode::<AggregationStatus>(&status_data),
+            )
+            else {
+                continue;
+            };
+            for (job_key, _) in aggregation.member_jobs()? {
+                members.insert(job_key);
+            }
+        }
+        Ok(members)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(key: u8, finished_unix_ms: u64, size: u64) -> FinishedRecord {
+        FinishedRecord {
+            job_key: vec![key],
+            finished_unix_ms,
+            size,
+        }
+    }
+
+    fn evicted_keys(policy: &RetentionPolicy, now_unix_ms: u64) -> Vec<u8> {
+        let records = vec![
+            record(3, 3_000, 30),
+            record(1, 1_000, 10),
+            record(2, 2_000, 20),
+        ];
+        policy
+            .evictions(records, now_unix_ms)
+            .into_iter()
+            .map(|r| r.job_key[0])
+            .collect()
+    }
+
+    #[test]
+    fn test_evictions() {
+        assert!(evicted_keys(&RetentionPolicy::default(), u64::MAX).is_empty());
+
+        let by_age = RetentionPolicy {
+            max_age: Some(Duration::from_secs(2)),
+            ..Default::default()
+        };
+        assert_eq!(evicted_keys(&by_age, 3_500), vec![1]);
+        assert_eq!(evicted_keys(&by_age, 10_000), vec![1, 2, 3]);
+
+        let by_count = RetentionPolicy {
+            max_count: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(evicted_keys(&by_count, 0), vec![1, 2]);
+
+        // The oldest go first, even if smaller
+        let by_bytes = RetentionPolicy {
+            max_bytes: Some(45),
+            ..Default::default()
+        };
+        assert_eq!(evicted_keys(&by_bytes, 0), vec![1, 2]);
+
+        let combined = RetentionPolicy {
+            max_age: Some(Duration::from_secs(10)),
+            max_count: Some(2),
+            max_bytes: Some(1_000),
+        };
+        assert_eq!(evicted_keys(&combined, 3_000), vec![1]);
+    }
+}
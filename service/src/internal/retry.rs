@@ -0,0 +1,279 @@
+use eq_common::InclusionServiceError;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often the retry scheduler checks for [Job](crate::Job)s due to retry
+pub const RETRY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Retry bookkeeping for a [Job](crate::Job), stored in the retry DB under the same key as the job.
+///
+/// Kept beside the [JobStatus](crate::JobStatus), rather than inside it,
+/// so that the job status encoding is unchanged.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryState {
+    /// Automatic retries scheduled so far
+    pub attempts: u32,
+    /// Unix time (ms) the next retry is due at, `0` if none is scheduled
+    pub next_retry_unix_ms: u64,
+}
+
+/// Groups of errors that get their own backoff timing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The DA node is likely syncing or unreachable
+    DaClient,
+    /// The prover network is likely busy or unreachable
+    ZkClient,
+    /// Anything else
+    Other,
+}
+
+impl From<&InclusionServiceError> for ErrorClass {
+    fn from(e: &InclusionServiceError) -> Self {
+        match e {
+            InclusionServiceError::DaClientError(_) => ErrorClass::DaClient,
+            InclusionServiceError::ZkClientError(_) => ErrorClass::ZkClient,
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+/// When, and how many times, to automatically retry a retryable
+/// [JobStatus::Failed](crate::JobStatus::Failed) job.
+///
+/// The delay for a retry is `base_delay * 2^(attempt - 1)` for the [ErrorClass],
+/// capped at `max_delay`, with up to ±`jitter` of that delay added at random.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries allowed before a job is considered a permanent failure
+    pub max_attempts: u32,
+    pub da_base_delay: Duration,
+    pub zk_base_delay: Duration,
+    pub other_base_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of the delay to randomize by, in `[0, 1]`
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            da_base_delay: Duration::from_secs(30),
+            zk_base_delay: Duration::from_secs(60),
+            other_base_delay: Duration::from_secs(30),
+            max_delay: Duration::from_secs(60 * 60),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (starting at 1), without jitter
+    pub fn base_backoff(&self, class: ErrorClass, attempt: u32) -> Duration {
+        let base = match class {
+            ErrorClass::DaClient => self.da_base_delay,
+            ErrorClass::ZkClient => self.zk_base_delay,
+            ErrorClass::Other => self.other_base_delay,
+        };
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        base.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// Delay before retry number `attempt` (starting at 1), with jitter
+    pub fn backoff(&self, class: ErrorClass, attempt: u32) -> Duration {
+        let delay = self.base_backoff(class, attempt).as_secs_f64();
+        // Uniform in [-1, 1]
+        let random = RandomState::new().hash_one(attempt) as f64 / u64::MAX as f64 * 2.0 - 1.0;
+        Duration::from_secs_f64((delay + delay * self.jitter * random).max(0.0))
+    }
+
+    /// The [RetryState] after another failure, or `None` if no attempts are left.
+    pub fn next_state(
+        &self,
+        previous: RetryState,
+        error: &InclusionServiceError,
+    ) -> Option<RetryState> {
+        let attempts = previous.attempts + 1;
+        if attempts > self.max_attempts {
+            return None;
+        }
+        let delay = self.backoff(error.into(), attempts);
+        Some(RetryState {
+            attempts,
+            next_retry_unix_ms: unix_ms_now().saturating_add(delay.as_millis() as u64),
+        })
+    }
+}
+
+/// Current unix time in milliseconds
+pub fn unix_ms_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::da::FixtureDaBackend;
+    use crate::internal::schema;
+    use crate::internal::store::StoreTree;
+    use crate::{InclusionService, Job, JobStatus};
+    use celestia_types::{blob::Commitment, block::Height as BlockHeight, nmt::Namespace};
+    use eq_sdk::types::BlobId;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = RetryPolicy::default();
+        assert_eq!(
+            policy.base_backoff(ErrorClass::DaClient, 1),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            policy.base_backoff(ErrorClass::DaClient, 3),
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            policy.base_backoff(ErrorClass::ZkClient, 2),
+            Duration::from_secs(120)
+        );
+        assert_eq!(policy.base_backoff(ErrorClass::Other, 50), policy.max_delay);
+    }
+
+    #[test]
+    fn test_backoff_jitter_bounds() {
+        let policy = RetryPolicy::default();
+        for attempt in 1..=policy.max_attempts {
+            let base = policy
+                .base_backoff(ErrorClass::ZkClient, attempt)
+                .as_secs_f64();
+            let delay = policy.backoff(ErrorClass::ZkClient, attempt).as_secs_f64();
+            assert!(delay >= base * (1.0 - policy.jitter) - 1e-6);
+            assert!(delay <= base * (1.0 + policy.jitter) + 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_attempts_budget() {
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            ..Default::default()
+        };
+        let e = InclusionServiceError::DaClientError("syncing".to_string());
+        let first = policy.next_state(RetryState::default(), &e).unwrap();
+        assert_eq!(first.attempts, 1);
+        assert!(first.next_retry_unix_ms > unix_ms_now());
+        let second = policy.next_state(first, &e).unwrap();
+        assert_eq!(second.attempts, 2);
+        assert!(policy.next_state(second, &e).is_none());
+    }
+
+    #[test]
+    fn test_backfill_retry_states() {
+        let (service, _job_receivers) =
+            InclusionService::for_test(Arc::new(FixtureDaBackend::default()));
+        let e = InclusionServiceError::DaClientError("syncing".to_string());
+        let scheduled = RetryState {
+            attempts: 2,
+            next_retry_unix_ms: unix_ms_now() + 60_000,
+        };
+        for (job_key, status) in [
+            (
+                b"retryable".as_slice(),
+                JobStatus::Failed(
+                    e.clone(),
+                    Some(Box::new(JobStatus::DataAvailabilityPending)),
+                ),
+            ),
+            (
+                b"scheduled",
+                JobStatus::Failed(
+                    e.clone(),
+                    Some(Box::new(JobStatus::DataAvailabilityPending)),
+                ),
+            ),
+            (b"permanent", JobStatus::Failed(e, None)),
+        ] {
+            service
+                .store
+                .insert(
+                    StoreTree::Finished,
+                    job_key,
+                    &schema::encode(&status).unwrap(),
+                )
+                .unwrap();
+        }
+        service
+            .store
+            .insert(
+                StoreTree::Retry,
+                b"scheduled",
+                &schema::encode(&scheduled).unwrap(),
+            )
+            .unwrap();
+
+        let before = unix_ms_now();
+        assert_eq!(service.backfill_retry_states().unwrap(), 1);
+        let backfilled = service.get_retry_state(b"retryable").unwrap().unwrap();
+        assert_eq!(backfilled.attempts, 0);
+        assert!(backfilled.next_retry_unix_ms >= before);
+        assert!(backfilled.next_retry_unix_ms <= unix_ms_now());
+        assert_eq!(
+            service.get_retry_state(b"scheduled").unwrap(),
+            Some(scheduled)
+        );
+        assert!(service.get_retry_state(b"permanent").unwrap().is_none());
+
+        // Nothing left to backfill
+        assert_eq!(service.backfill_retry_states().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_retried_job_not_due() {
+        let (service, _job_receivers) =
+            InclusionService::for_test(Arc::new(FixtureDaBackend::default()));
+        let job = Job::ZkStack(BlobId::new(
+            BlockHeight::from(7u32),
+            Namespace::new_v0(&[1, 2, 3]).unwrap(),
+            Commitment::new([4; 32]),
+            5,
+            6,
+        ));
+        let job_key = job.key().unwrap();
+        let e = InclusionServiceError::DaClientError("syncing".to_string());
+        let failed = JobStatus::Failed(e, Some(Box::new(JobStatus::DataAvailabilityPending)));
+        let due = RetryState {
+            attempts: 2,
+            next_retry_unix_ms: unix_ms_now(),
+        };
+        service
+            .store
+            .insert(
+                StoreTree::Finished,
+                &job_key,
+                &schema::encode(&failed).unwrap(),
+            )
+            .unwrap();
+        service
+            .store
+            .insert(StoreTree::Retry, &job_key, &schema::encode(&due).unwrap())
+            .unwrap();
+
+        // As the retry scheduler sends it back to the queue
+        service
+            .store_job_status(job_key.clone(), JobStatus::DataAvailabilityPending, &job)
+            .unwrap();
+        assert_eq!(
+            service.get_retry_state(&job_key).unwrap(),
+            Some(RetryState {
+                attempts: 2,
+                next_retry_unix_ms: 0,
+            })
+        );
+    }
+}
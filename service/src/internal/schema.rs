@@ -0,0 +1,269 @@
+use crate::internal::store::{JobStore, StoreTree};
+
+use eq_common::InclusionServiceError;
+use log::info;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Version of the encoding of the DB records written by this build.
+///
+/// Bump it, and add a [Migration] from the previous version, whenever the encoding
+/// of anything stored changes (e.g. a field or enum variant of a [JobStatus](crate::JobStatus)).
+pub const SCHEMA_VERSION: u16 = 1;
+
+/// Every stored key and value starts with the (big-endian) [SCHEMA_VERSION] it was written with,
+/// other than keys of the [StoreTree::Config] tree: program IDs and fixed strings.
+const VERSION_LEN: usize = 2;
+
+/// Key in the [StoreTree::Schema] tree of the store's [SCHEMA_VERSION].
+/// The rest of the tree is the progress of a migration.
+const SCHEMA_VERSION_KEY: &[u8] = b"version";
+const MIGRATION_KEY_PREFIX: &[u8] = b"migration:";
+
+/// Every tree migrated
+const TREES: [StoreTree; 10] = [
+    StoreTree::Queue,
+    StoreTree::Finished,
+    StoreTree::Retry,
+    StoreTree::Aggregation,
+    StoreTree::JobProgram,
+    StoreTree::FinishedAt,
+    StoreTree::Config,
+    StoreTree::JobTenant,
+    StoreTree::Quota,
+    StoreTree::QuotaSpent,
+];
+
+/// bincode of a value, in an envelope of the current [SCHEMA_VERSION]
+pub fn encode<T: Serialize + ?Sized>(value: &T) -> bincode::Result<Vec<u8>> {
+    let mut data = envelope(&[]);
+    bincode::serialize_into(&mut data, value)?;
+    Ok(data)
+}
+
+/// Decode a value written by [encode], only if of the current [SCHEMA_VERSION]
+pub fn decode<T: DeserializeOwned>(data: &[u8]) -> bincode::Result<T> {
+    bincode::deserialize(open_envelope(data)?)
+}
+
+/// Data in an envelope of the current [SCHEMA_VERSION]
+pub fn envelope(payload: &[u8]) -> Vec<u8> {
+    [&SCHEMA_VERSION.to_be_bytes(), payload].concat()
+}
+
+/// The data in an envelope, only if of the current [SCHEMA_VERSION]
+pub fn open_envelope(data: &[u8]) -> bincode::Result<&[u8]> {
+    let (version, payload) = data
+        .split_first_chunk::<VERSION_LEN>()
+        .ok_or_else(|| bincode_error("Record too short for a schema version".to_string()))?;
+    let version = u16::from_be_bytes(*version);
+    if version != SCHEMA_VERSION {
+        return Err(bincode_error(format!(
+            "Record has schema version {version}, expected {SCHEMA_VERSION}"
+        )));
+    }
+    Ok(payload)
+}
+
+fn bincode_error(e: String) -> bincode::Error {
+    Box::new(bincode::ErrorKind::Custom(e))
+}
+
+/// Upgrades the records of every tree from one schema version to the next.
+struct Migration {
+    /// Version migrated from, to `from + 1`
+    from: u16,
+    description: &'static str,
+    /// Re-encode a record of a tree, or drop it with `None`
+    migrate_record: fn(
+        tree: StoreTree,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Option<(Vec<u8>, Vec<u8>)>, InclusionServiceError>,
+}
+
+/// Every migration, in order
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "Version envelopes around all keys and values",
+    migrate_record: migrate_v0_envelopes,
+}];
+
+/// Version 0 is the same bincode encoding, without envelopes
+fn migrate_v0_envelopes(
+    tree: StoreTree,
+    key: &[u8],
+    value: &[u8],
+) -> Result<Option<(Vec<u8>, Vec<u8>)>, InclusionServiceError> {
+    let key = match tree {
+        StoreTree::Config => key.to_vec(),
+        _ => envelope(key),
+    };
+    Ok(Some((key, envelope(value))))
+}
+
+/// Bring the store to the [SCHEMA_VERSION] of this build, migrating older records.
+/// A store written by a newer build (of an unknown future version) is refused.
+///
+/// Each tree is migrated into the [StoreTree::Schema] tree before replacing the original,
+/// so a migration interrupted at any point continues on the next start.
+pub fn migrate(store: &dyn JobStore) -> Result<(), InclusionServiceError> {
+    let mut version = match store.get(StoreTree::Schema, SCHEMA_VERSION_KEY)? {
+        Some(version) => u16::from_be_bytes(version.as_slice().try_into().map_err(|_| {
+            InclusionServiceError::InternalError("Invalid schema version in DB".to_string())
+        })?),
+        None => {
+            let mut is_empty = true;
+            for tree in TREES {
+                is_empty &= store.is_empty(tree)?;
+            }
+            // Records from before there were schema versions, if any
+            match is_empty {
+                true => SCHEMA_VERSION,
+                false => 0,
+            }
+        }
+    };
+    if version > SCHEMA_VERSION {
+        return Err(InclusionServiceError::InternalError(format!(
+            "DB has schema version {version}, newer than {SCHEMA_VERSION} of this build. Refusing to start!"
+        )));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= version) {
+        info!(
+            "Migrating DB from schema version {} to {}: {}",
+            migration.from,
+            migration.from + 1,
+            migration.description
+        );
+        for tree in TREES {
+            migrate_tree(store, migration, tree)?;
+        }
+        version = migration.from + 1;
+        store.insert(
+            StoreTree::Schema,
+            SCHEMA_VERSION_KEY,
+            &version.to_be_bytes(),
+        )?;
+        remove_prefix(store, MIGRATION_KEY_PREFIX)?;
+        store.flush()?;
+    }
+    store.insert(
+        StoreTree::Schema,
+        SCHEMA_VERSION_KEY,
+        &version.to_be_bytes(),
+    )?;
+    store.flush()?;
+    Ok(())
+}
+
+/// Progress of migrating a tree, recorded in the schema tree
+const STAGED: &[u8] = b"staged";
+const DONE: &[u8] = b"done";
+
+fn migrate_tree(
+    store: &dyn JobStore,
+    migration: &Migration,
+    tree: StoreTree,
+) -> Result<(), InclusionServiceError> {
+    let progress_key = format!("migration:{}:{}", migration.from, tree.name()).into_bytes();
+    // Migrated records are staged under the progress key
+    let staging_prefix = [progress_key.as_slice(), b":"].concat();
+    let progress = store.get(StoreTree::Schema, &progress_key)?;
+    if progress.as_deref() == Some(DONE) {
+        return Ok(());
+    }
+    if progress.as_deref() != Some(STAGED) {
+        remove_prefix(store, &staging_prefix)?;
+        for entry in store.iter(tree) {
+            let (key, value) = entry?;
+            if let Some((key, value)) = (migration.migrate_record)(tree, &key, &value)? {
+                let staging_key = [staging_prefix.as_slice(), &key].concat();
+                store.insert(StoreTree::Schema, &staging_key, &value)?;
+            }
+        }
+        store.insert(StoreTree::Schema, &progress_key, STAGED)?;
+        store.flush()?;
+    }
+
+    // The staged records are complete, so the original tree can be replaced
+    store.clear(tree)?;
+    for entry in store.scan_prefix(StoreTree::Schema, &staging_prefix) {
+        let (staging_key, value) = entry?;
+        store.insert(tree, &staging_key[staging_prefix.len()..], &value)?;
+    }
+    store.insert(StoreTree::Schema, &progress_key, DONE)?;
+    store.flush()?;
+    remove_prefix(store, &staging_prefix)?;
+    Ok(())
+}
+
+fn remove_prefix(store: &dyn JobStore, prefix: &[u8]) -> Result<(), InclusionServiceError> {
+    let keys = store
+        .scan_prefix(StoreTree::Schema, prefix)
+        .map(|entry| entry.map(|(key, _)| key))
+        .collect::<Result<Vec<_>, _>>()?;
+    for key in keys {
+        store.remove(StoreTree::Schema, &key)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::store::SledStore;
+
+    #[test]
+    fn test_migrate_v0() {
+        let store = SledStore::temporary().unwrap();
+        store
+            .insert(
+                StoreTree::Queue,
+                b"job",
+                &bincode::serialize(&7u32).unwrap(),
+            )
+            .unwrap();
+        store
+            .insert(
+                StoreTree::Config,
+                b"setup",
+                &bincode::serialize(&8u32).unwrap(),
+            )
+            .unwrap();
+
+        migrate(&store).unwrap();
+        let value = store
+            .get(StoreTree::Queue, &envelope(b"job"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decode::<u32>(&value).unwrap(), 7);
+        // Config keys are not encoded
+        let value = store.get(StoreTree::Config, b"setup").unwrap().unwrap();
+        assert_eq!(decode::<u32>(&value).unwrap(), 8);
+        // Only the version is left in the schema tree
+        assert_eq!(store.len(StoreTree::Schema).unwrap(), 1);
+
+        // Nothing left to migrate
+        migrate(&store).unwrap();
+        assert_eq!(store.len(StoreTree::Queue).unwrap(), 1);
+
+        store
+            .insert(
+                StoreTree::Schema,
+                SCHEMA_VERSION_KEY,
+                &(SCHEMA_VERSION + 1).to_be_bytes(),
+            )
+            .unwrap();
+        assert!(migrate(&store).is_err());
+    }
+
+    #[test]
+    fn test_future_records_refused() {
+        let data = encode(&7u32).unwrap();
+        assert_eq!(decode::<u32>(&data).unwrap(), 7);
+        let mut future_data = data.clone();
+        future_data[..VERSION_LEN].copy_from_slice(&(SCHEMA_VERSION + 1).to_be_bytes());
+        assert!(decode::<u32>(&future_data).is_err());
+    }
+}
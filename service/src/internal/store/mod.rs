@@ -0,0 +1,340 @@
+//! Storage of the service's [Job](crate::Job)s and config, behind the [JobStore] trait.
+
+mod sled_store;
+mod sqlite_store;
+
+pub use sled_store::SledStore;
+pub use sqlite_store::SqliteStore;
+
+use eq_common::InclusionServiceError;
+use std::ops::Bound;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// A keyspace of a [JobStore], with keys sorted bytewise
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StoreTree {
+    /// [JobStatus](crate::JobStatus) of unfinished jobs, by [Job::key](crate::Job::key)
+    Queue,
+    /// [JobStatus](crate::JobStatus) of finished (successful or failed) jobs
+    Finished,
+    /// [RetryState](crate::internal::retry::RetryState) of jobs that failed with a retryable error
+    Retry,
+    /// [AggregationStatus](crate::internal::aggregation::AggregationStatus), by aggregation key
+    Aggregation,
+    /// The [SuccNetProgramId](crate::SuccNetProgramId) each job is proven with
+    JobProgram,
+    /// Unix time (ms) each job in the [StoreTree::Finished] tree finished at,
+    /// for the [retention](crate::internal::retention) of finished jobs
+    FinishedAt,
+    /// Program ELFs and proof setups, by program ID
+    Config,
+    /// The schema version of the store, see [schema](crate::internal::schema)
+    Schema,
+    /// The tenant that started each job (or aggregation, by aggregation key),
+    /// that it's proofs count against
+    JobTenant,
+    /// Proofs requested by each tenant and chain per period, see [limits](crate::internal::limits)
+    Quota,
+    /// Unix time (s) each job's (or aggregation's) proof was counted in the [StoreTree::Quota]
+    /// tree, so it's counted once however many times it is requested
+    QuotaSpent,
+}
+
+impl StoreTree {
+    pub const ALL: [StoreTree; 11] = [
+        StoreTree::Queue,
+        StoreTree::Finished,
+        StoreTree::Retry,
+        StoreTree::Aggregation,
+        StoreTree::JobProgram,
+        StoreTree::FinishedAt,
+        StoreTree::Config,
+        StoreTree::Schema,
+        StoreTree::JobTenant,
+        StoreTree::Quota,
+        StoreTree::QuotaSpent,
+    ];
+
+    /// Name of the tree (a sled tree, or SQLite `tree` column)
+    pub fn name(self) -> &'static str {
+        match self {
+            StoreTree::Queue => "queue",
+            StoreTree::Finished => "finished",
+            StoreTree::Retry => "retry",
+            StoreTree::Aggregation => "aggregation",
+            StoreTree::JobProgram => "job_program",
+            StoreTree::FinishedAt => "finished_at",
+            StoreTree::Config => "config",
+            StoreTree::Schema => "schema",
+            StoreTree::JobTenant => "job_tenant",
+            StoreTree::Quota => "quota",
+            StoreTree::QuotaSpent => "quota_spent",
+        }
+    }
+}
+
+/// Records of a tree in key order
+pub type StoreIter<'a> =
+    Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>), InclusionServiceError>> + Send + 'a>;
+
+/// Error in a [JobStore] transaction closure
+#[derive(Debug)]
+pub enum TransactionError {
+    /// Another transaction wrote the records read, the closure is run again
+    Conflict,
+    /// Nothing is written, and the error is returned
+    Abort(InclusionServiceError),
+}
+
+impl From<InclusionServiceError> for TransactionError {
+    fn from(e: InclusionServiceError) -> Self {
+        TransactionError::Abort(e)
+    }
+}
+
+/// The trees of a [JobStore] in a transaction.
+/// Only the trees the transaction was started with are accessible.
+pub trait StoreTransaction {
+    fn get(&self, tree: StoreTree, key: &[u8]) -> Result<Option<Vec<u8>>, TransactionError>;
+    fn insert(&self, tree: StoreTree, key: &[u8], value: &[u8]) -> Result<(), TransactionError>;
+    /// Remove a record, returning it's value if there was one
+    fn remove(&self, tree: StoreTree, key: &[u8]) -> Result<Option<Vec<u8>>, TransactionError>;
+}
+
+/// An embedded key-value store, holding every [StoreTree].
+///
+/// Single record operations are atomic, many records across trees are updated
+/// atomically with [JobStore::run_transaction] (see [transaction](#method.transaction)).
+pub trait JobStore: Send + Sync {
+    fn backend(&self) -> StoreBackend;
+    fn get(&self, tree: StoreTree, key: &[u8]) -> Result<Option<Vec<u8>>, InclusionServiceError>;
+    fn insert(
+        &self,
+        tree: StoreTree,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), InclusionServiceError>;
+    /// Remove a record, returning it's value if there was one
+    fn remove(&self, tree: StoreTree, key: &[u8])
+        -> Result<Option<Vec<u8>>, InclusionServiceError>;
+    /// Records from `start` on, in key order
+    fn iter_from(&self, tree: StoreTree, start: Bound<&[u8]>) -> StoreIter<'_>;
+    fn len(&self, tree: StoreTree) -> Result<usize, InclusionServiceError>;
+    /// Remove every record of a tree
+    fn clear(&self, tree: StoreTree) -> Result<(), InclusionServiceError>;
+    /// Persist every write made so far
+    fn flush(&self) -> Result<(), InclusionServiceError>;
+    /// Run a closure with the `trees` in a transaction: all or none of it's writes are applied.
+    /// The closure may run more than once on a [TransactionError::Conflict].
+    fn run_transaction(
+        &self,
+        trees: &[StoreTree],
+        f: &mut dyn FnMut(&dyn StoreTransaction) -> Result<(), TransactionError>,
+    ) -> Result<(), InclusionServiceError>;
+
+    fn contains_key(&self, tree: StoreTree, key: &[u8]) -> Result<bool, InclusionServiceError> {
+        Ok(self.get(tree, key)?.is_some())
+    }
+
+    fn is_empty(&self, tree: StoreTree) -> Result<bool, InclusionServiceError> {
+        Ok(self.iter(tree).next().transpose()?.is_none())
+    }
+
+    fn iter(&self, tree: StoreTree) -> StoreIter<'_> {
+        self.iter_from(tree, Bound::Unbounded)
+    }
+
+    /// Records with keys starting with `prefix`, in key order
+    fn scan_prefix(&self, tree: StoreTree, prefix: &[u8]) -> StoreIter<'_> {
+        let prefix = prefix.to_vec();
+        Box::new(
+            self.iter_from(tree, Bound::Included(prefix.as_slice()))
+                .take_while(move |entry| match entry {
+                    Ok((key, _)) => key.starts_with(&prefix),
+                    Err(_) => true,
+                }),
+        )
+    }
+
+    /// Replace the value of a record only if it is still `old` (`None` for no record).
+    /// Returns `false` if the value was different, and nothing was written.
+    fn compare_and_swap(
+        &self,
+        tree: StoreTree,
+        key: &[u8],
+        old: Option<&[u8]>,
+        new: &[u8],
+    ) -> Result<bool, InclusionServiceError> {
+        let mut swapped = false;
+        self.run_transaction(&[tree], &mut |tx| {
+            swapped = tx.get(tree, key)?.as_deref() == old;
+            if swapped {
+                tx.insert(tree, key, new)?;
+            }
+            Ok(())
+        })?;
+        Ok(swapped)
+    }
+}
+
+impl<'a> dyn JobStore + 'a {
+    /// [JobStore::run_transaction] returning the closure's result.
+    ///
+    /// If the closure is run again after a conflict, the result of the earlier run is
+    /// dropped first, so anything it holds (like queue permits) is released before rerunning.
+    pub fn transaction<T>(
+        &self,
+        trees: &[StoreTree],
+        mut f: impl FnMut(&dyn StoreTransaction) -> Result<T, TransactionError>,
+    ) -> Result<T, InclusionServiceError> {
+        let mut result = None;
+        self.run_transaction(trees, &mut |tx| {
+            result = None;
+            result = Some(f(tx)?);
+            Ok(())
+        })?;
+        Ok(result.expect("Transaction closure ran"))
+    }
+}
+
+/// The embedded store a service DB is kept in, set by `EQ_DB_BACKEND`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum StoreBackend {
+    /// A sled DB in the `EQ_DB_PATH` directory
+    Sled,
+    /// A SQLite DB, file [SqliteStore::FILE_NAME] in the `EQ_DB_PATH` directory
+    Sqlite,
+}
+
+impl FromStr for StoreBackend {
+    type Err = InclusionServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sled" => Ok(Self::Sled),
+            "sqlite" => Ok(Self::Sqlite),
+            _ => Err(InclusionServiceError::InvalidParameter(format!(
+                "Unknown DB backend '{s}', expected one of: sled, sqlite"
+            ))),
+        }
+    }
+}
+
+/// Open (or create) the store in a directory
+pub fn open_store(
+    backend: StoreBackend,
+    dir: &Path,
+) -> Result<Arc<dyn JobStore>, InclusionServiceError> {
+    Ok(match backend {
+        StoreBackend::Sled => Arc::new(SledStore::open(dir)?),
+        StoreBackend::Sqlite => Arc::new(SqliteStore::open(dir)?),
+    })
+}
+
+/// Copy every record of every tree to an empty store, e.g. to switch [StoreBackend]s
+pub fn copy_store(from: &dyn JobStore, to: &dyn JobStore) -> Result<usize, InclusionServiceError> {
+    for tree in StoreTree::ALL {
+        if !to.is_empty(tree)? {
+            return Err(InclusionServiceError::InvalidParameter(format!(
+                "Can only copy to an empty store, the {} tree has records",
+                tree.name()
+            )));
+        }
+    }
+    let mut copied = 0;
+    for tree in StoreTree::ALL {
+        for entry in from.iter(tree) {
+            let (key, value) = entry?;
+            to.insert(tree, &key, &value)?;
+            copied += 1;
+        }
+    }
+    to.flush()?;
+    Ok(copied)
+}
+
+fn internal_error(e: impl ToString) -> InclusionServiceError {
+    InclusionServiceError::InternalError(e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keys(iter: StoreIter) -> Vec<Vec<u8>> {
+        iter.map(|entry| entry.unwrap().0).collect()
+    }
+
+    /// Every backend behaves the same
+    fn check_store(store: &dyn JobStore) {
+        let tree = StoreTree::Queue;
+        for key in [b"b1".as_slice(), b"a", b"b0", b"c"] {
+            store.insert(tree, key, key).unwrap();
+        }
+        store.insert(StoreTree::Finished, b"b0", b"x").unwrap();
+        assert_eq!(store.len(tree).unwrap(), 4);
+        assert_eq!(
+            keys(store.iter(tree)),
+            [b"a".to_vec(), b"b0".to_vec(), b"b1".to_vec(), b"c".to_vec()]
+        );
+        assert_eq!(
+            keys(store.scan_prefix(tree, b"b")),
+            [b"b0".to_vec(), b"b1".to_vec()]
+        );
+        assert_eq!(
+            keys(store.iter_from(tree, Bound::Excluded(b"b0"))),
+            [b"b1".to_vec(), b"c".to_vec()]
+        );
+
+        // An aborted transaction writes nothing
+        let aborted = store.transaction(&[StoreTree::Queue, StoreTree::Finished], |tx| {
+            tx.remove(StoreTree::Queue, b"a")?;
+            tx.insert(StoreTree::Finished, b"a", b"a")?;
+            Err::<(), _>(InclusionServiceError::QueueFull.into())
+        });
+        assert!(matches!(aborted, Err(InclusionServiceError::QueueFull)));
+        assert!(store.contains_key(StoreTree::Queue, b"a").unwrap());
+        let moved = store
+            .transaction(&[StoreTree::Queue, StoreTree::Finished], |tx| {
+                let value = tx.remove(StoreTree::Queue, b"a")?.unwrap();
+                tx.insert(StoreTree::Finished, b"a", &value)?;
+                Ok(value)
+            })
+            .unwrap();
+        assert_eq!(moved, b"a");
+        assert!(!store.contains_key(StoreTree::Queue, b"a").unwrap());
+        assert_eq!(store.get(StoreTree::Finished, b"a").unwrap().unwrap(), b"a");
+
+        assert!(!store
+            .compare_and_swap(tree, b"c", Some(b"x".as_slice()), b"y")
+            .unwrap());
+        assert!(store
+            .compare_and_swap(tree, b"c", Some(b"c".as_slice()), b"y")
+            .unwrap());
+        assert_eq!(store.get(tree, b"c").unwrap().unwrap(), b"y");
+
+        store.clear(tree).unwrap();
+        assert!(store.is_empty(tree).unwrap());
+        assert!(!store.is_empty(StoreTree::Finished).unwrap());
+    }
+
+    #[test]
+    fn test_backends() {
+        let sled_store: Arc<dyn JobStore> = Arc::new(SledStore::temporary().unwrap());
+        check_store(sled_store.as_ref());
+
+        let dir = std::env::temp_dir().join(format!("eq-service-store-{}", std::process::id()));
+        let sqlite_store = open_store(StoreBackend::Sqlite, &dir).unwrap();
+        check_store(sqlite_store.as_ref());
+
+        // Switching backends
+        let copy: Arc<dyn JobStore> = Arc::new(SledStore::temporary().unwrap());
+        assert_eq!(copy_store(sqlite_store.as_ref(), copy.as_ref()).unwrap(), 2);
+        assert_eq!(copy.get(StoreTree::Finished, b"b0").unwrap().unwrap(), b"x");
+        assert!(copy_store(sqlite_store.as_ref(), copy.as_ref()).is_err());
+        drop(sqlite_store);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use super::{
+    internal_error, JobStore, StoreBackend, StoreIter, StoreTransaction, StoreTree,
+    TransactionError,
+};
+
+use eq_common::InclusionServiceError;
+use sled::transaction::{
+    ConflictableTransactionError, TransactionalTree, UnabortableTransactionError,
+};
+use sled::{Db, Transactional, Tree};
+use std::cell::RefCell;
+use std::ops::Bound;
+use std::path::Path;
+
+/// A [JobStore] with each [StoreTree] in a sled tree of the same name
+pub struct SledStore {
+    db: Db,
+    /// In the order of [StoreTree::ALL]
+    trees: Vec<Tree>,
+}
+
+impl SledStore {
+    pub fn open(dir: &Path) -> Result<Self, InclusionServiceError> {
+        Self::from_db(sled::open(dir).map_err(internal_error)?)
+    }
+
+    /// A store deleted when dropped, for tests
+    pub fn temporary() -> Result<Self, InclusionServiceError> {
+        Self::from_db(
+            sled::Config::new()
+                .temporary(true)
+                .open()
+                .map_err(internal_error)?,
+        )
+    }
+
+    fn from_db(db: Db) -> Result<Self, InclusionServiceError> {
+        let trees = StoreTree::ALL
+            .iter()
+            .map(|tree| db.open_tree(tree.name()))
+            .collect::<Result<_, _>>()
+            .map_err(internal_error)?;
+        Ok(SledStore { db, trees })
+    }
+
+    fn tree(&self, tree: StoreTree) -> &Tree {
+        &self.trees[tree_index(&StoreTree::ALL, tree).expect("Every tree is opened")]
+    }
+}
+
+fn tree_index(trees: &[StoreTree], tree: StoreTree) -> Option<usize> {
+    trees.iter().position(|t| *t == tree)
+}
+
+impl JobStore for SledStore {
+    fn backend(&self) -> StoreBackend {
+        StoreBackend::Sled
+    }
+
+    fn get(&self, tree: StoreTree, key: &[u8]) -> Result<Option<Vec<u8>>, InclusionServiceError> {
+        Ok(self
+            .tree(tree)
+            .get(key)
+            .map_err(internal_error)?
+            .map(|v| v.to_vec()))
+    }
+
+    fn insert(
+        &self,
+        tree: StoreTree,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), InclusionServiceError> {
+        self.tree(tree).insert(key, value).map_err(internal_error)?;
+        Ok(())
+    }
+
+    fn remove(
+        &self,
+        tree: StoreTree,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, InclusionServiceError> {
+        Ok(self
+            .tree(tree)
+            .remove(key)
+            .map_err(internal_error)?
+            .map(|v| v.to_vec()))
+    }
+
+    fn iter_from(&self, tree: StoreTree, start: Bound<&[u8]>) -> StoreIter<'_> {
+        Box::new(
+            self.tree(tree)
+                .range::<&[u8], _>((start, Bound::Unbounded))
+                .map(|entry| {
+                    entry
+                        .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                        .map_err(internal_error)
+                }),
+        )
+    }
+
+    fn len(&self, tree: StoreTree) -> Result<usize, InclusionServiceError> {
+        Ok(self.tree(tree).len())
+    }
+
+    fn clear(&self, tree: StoreTree) -> Result<(), InclusionServiceError> {
+        self.tree(tree).clear().map_err(internal_error)
+    }
+
+    fn flush(&self) -> Result<(), InclusionServiceError> {
+        self.db.flush().map_err(internal_error)?;
+        Ok(())
+    }
+
+    fn run_transaction(
+        &self,
+        trees: &[StoreTree],
+        f: &mut dyn FnMut(&dyn StoreTransaction) -> Result<(), TransactionError>,
+    ) -> Result<(), InclusionServiceError> {
+        let sled_trees: Vec<&Tree> = trees.iter().map(|tree| self.tree(*tree)).collect();
+        // sled may run the closure again, but never concurrently
+        let f = RefCell::new(f);
+        sled_trees
+            .as_slice()
+            .transaction(|tx_trees| {
+                let tx = SledTransaction { trees, tx_trees };
+                let mut run = f.borrow_mut();
+                (*run)(&tx).map_err(|e| match e {
+                    TransactionError::Conflict => ConflictableTransactionError::Conflict,
+                    TransactionError::Abort(e) => ConflictableTransactionError::Abort(e),
+                })
+            })
+            .map_err(|e| match e {
+                sled::transaction::TransactionError::Abort(e) => e,
+                sled::transaction::TransactionError::Storage(e) => internal_error(e),
+            })
+    }
+}
+
+struct SledTransaction<'a> {
+    trees: &'a [StoreTree],
+    tx_trees: &'a [TransactionalTree],
+}
+
+impl SledTransaction<'_> {
+    fn tree(&self, tree: StoreTree) -> Result<&TransactionalTree, TransactionError> {
+        tree_index(self.trees, tree)
+            .map(|i| &self.tx_trees[i])
+            .ok_or_else(|| {
+                TransactionError::Abort(internal_error(format!(
+                    "The {} tree is not in the transaction",
+                    tree.name()
+                )))
+            })
+    }
+}
+
+fn tx_error(e: UnabortableTransactionError) -> TransactionError {
+    match e {
+        UnabortableTransactionError::Conflict => TransactionError::Conflict,
+        UnabortableTransactionError::Storage(e) => TransactionError::Abort(internal_error(e)),
+    }
+}
+
+impl StoreTransaction for SledTransaction<'_> {
+    fn get(&self, tree: StoreTree, key: &[u8]) -> Result<Option<Vec<u8>>, TransactionError> {
+        Ok(self
+            .tree(tree)?
+            .get(key)
+            .map_err(tx_error)?
+            .map(|v| v.to_vec()))
+    }
+
+    fn insert(&self, tree: StoreTree, key: &[u8], value: &[u8]) -> Result<(), TransactionError> {
+        self.tree(tree)?.insert(key, value).map_err(tx_error)?;
+        Ok(())
+    }
+
+    fn remove(&self, tree: StoreTree, key: &[u8]) -> Result<Option<Vec<u8>>, TransactionError> {
+        Ok(self
+            .tree(tree)?
+            .remove(key)
+            .map_err(tx_error)?
+            .map(|v| v.to_vec()))
+    }
+}
@@ -0,0 +1,246 @@
+use super::{
+    internal_error, JobStore, StoreBackend, StoreIter, StoreTransaction, StoreTree,
+    TransactionError,
+};
+
+use eq_common::InclusionServiceError;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::VecDeque;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+/// Records read at once when iterating
+const PAGE_SIZE: usize = 64;
+
+/// A [JobStore] in a single SQLite table, keyed by [StoreTree] name and key.
+///
+/// SQLite compares BLOBs bytewise, so records are in the same order as with sled.
+/// Every write is committed durably, a single connection serializes them.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Name of the DB file in the store's directory
+    pub const FILE_NAME: &'static str = "eq-service.sqlite3";
+
+    pub fn open(dir: &Path) -> Result<Self, InclusionServiceError> {
+        std::fs::create_dir_all(dir).map_err(internal_error)?;
+        let conn = Connection::open(dir.join(Self::FILE_NAME)).map_err(internal_error)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+            .map_err(internal_error)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS records (
+                tree TEXT NOT NULL,
+                key BLOB NOT NULL,
+                value BLOB NOT NULL,
+                PRIMARY KEY (tree, key)
+            ) WITHOUT ROWID;",
+        )
+        .map_err(internal_error)?;
+        Ok(SqliteStore {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().expect("SQLite connection lock poisoned")
+    }
+}
+
+fn get(conn: &Connection, tree: StoreTree, key: &[u8]) -> rusqlite::Result<Option<Vec<u8>>> {
+    conn.query_row(
+        "SELECT value FROM records WHERE tree = ?1 AND key = ?2",
+        params![tree.name(), key],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+fn insert(conn: &Connection, tree: StoreTree, key: &[u8], value: &[u8]) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO records (tree, key, value) VALUES (?1, ?2, ?3)",
+        params![tree.name(), key, value],
+    )?;
+    Ok(())
+}
+
+fn remove(conn: &Connection, tree: StoreTree, key: &[u8]) -> rusqlite::Result<Option<Vec<u8>>> {
+    let value = get(conn, tree, key)?;
+    if value.is_some() {
+        conn.execute(
+            "DELETE FROM records WHERE tree = ?1 AND key = ?2",
+            params![tree.name(), key],
+        )?;
+    }
+    Ok(value)
+}
+
+impl JobStore for SqliteStore {
+    fn backend(&self) -> StoreBackend {
+        StoreBackend::Sqlite
+    }
+
+    fn get(&self, tree: StoreTree, key: &[u8]) -> Result<Option<Vec<u8>>, InclusionServiceError> {
+        get(&self.conn(), tree, key).map_err(internal_error)
+    }
+
+    fn insert(
+        &self,
+        tree: StoreTree,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), InclusionServiceError> {
+        insert(&self.conn(), tree, key, value).map_err(internal_error)
+    }
+
+    fn remove(
+        &self,
+        tree: StoreTree,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, InclusionServiceError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(internal_error)?;
+        let value = remove(&tx, tree, key).map_err(internal_error)?;
+        tx.commit().map_err(internal_error)?;
+        Ok(value)
+    }
+
+    fn iter_from(&self, tree: StoreTree, start: Bound<&[u8]>) -> StoreIter<'_> {
+        Box::new(SqliteIter {
+            store: self,
+            tree,
+            start: start.map(|key| key.to_vec()),
+            page: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    fn len(&self, tree: StoreTree) -> Result<usize, InclusionServiceError> {
+        self.conn()
+            .query_row(
+                "SELECT COUNT(*) FROM records WHERE tree = ?1",
+                params![tree.name()],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|len| len as usize)
+            .map_err(internal_error)
+    }
+
+    fn clear(&self, tree: StoreTree) -> Result<(), InclusionServiceError> {
+        self.conn()
+            .execute("DELETE FROM records WHERE tree = ?1", params![tree.name()])
+            .map_err(internal_error)?;
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), InclusionServiceError> {
+        // Already durable once committed
+        Ok(())
+    }
+
+    fn run_transaction(
+        &self,
+        trees: &[StoreTree],
+        f: &mut dyn FnMut(&dyn StoreTransaction) -> Result<(), TransactionError>,
+    ) -> Result<(), InclusionServiceError> {
+        let mut conn = self.conn();
+        loop {
+            let tx = conn.transaction().map_err(internal_error)?;
+            // Dropping the transaction rolls it back
+            match f(&SqliteTransaction { tx: &tx, trees }) {
+                Ok(()) => return tx.commit().map_err(internal_error),
+                Err(TransactionError::Abort(e)) => return Err(e),
+                Err(TransactionError::Conflict) => continue,
+            }
+        }
+    }
+}
+
+/// Pages through a tree, without holding the connection between pages
+struct SqliteIter<'a> {
+    store: &'a SqliteStore,
+    tree: StoreTree,
+    /// Where the next page starts
+    start: Bound<Vec<u8>>,
+    page: VecDeque<(Vec<u8>, Vec<u8>)>,
+    done: bool,
+}
+
+impl SqliteIter<'_> {
+    fn next_page(&mut self) -> rusqlite::Result<()> {
+        let (condition, start) = match &self.start {
+            Bound::Included(key) => ("AND key >= ?2", key.clone()),
+            Bound::Excluded(key) => ("AND key > ?2", key.clone()),
+            // Every key is at least empty
+            Bound::Unbounded => ("AND key >= ?2", Vec::new()),
+        };
+        let conn = self.store.conn();
+        let mut statement = conn.prepare_cached(&format!(
+            "SELECT key, value FROM records WHERE tree = ?1 {condition} ORDER BY key LIMIT ?3"
+        ))?;
+        let rows = statement
+            .query_map(params![self.tree.name(), start, PAGE_SIZE as i64], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?;
+        self.page = rows.collect::<rusqlite::Result<_>>()?;
+        self.done = self.page.len() < PAGE_SIZE;
+        if let Some((key, _)) = self.page.back() {
+            self.start = Bound::Excluded(key.clone());
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for SqliteIter<'_> {
+    type Item = Result<(Vec<u8>, Vec<u8>), InclusionServiceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.page.is_empty() && !self.done {
+            if let Err(e) = self.next_page() {
+                self.done = true;
+                return Some(Err(internal_error(e)));
+            }
+        }
+        self.page.pop_front().map(Ok)
+    }
+}
+
+struct SqliteTransaction<'a> {
+    tx: &'a rusqlite::Transaction<'a>,
+    trees: &'a [StoreTree],
+}
+
+impl SqliteTransaction<'_> {
+    fn check_tree(&self, tree: StoreTree) -> Result<(), TransactionError> {
+        match self.trees.contains(&tree) {
+            true => Ok(()),
+            false => Err(TransactionError::Abort(internal_error(format!(
+                "The {} tree is not in the transaction",
+                tree.name()
+            )))),
+        }
+    }
+}
+
+fn tx_error(e: rusqlite::Error) -> TransactionError {
+    TransactionError::Abort(internal_error(e))
+}
+
+impl StoreTransaction for SqliteTransaction<'_> {
+    fn get(&self, tree: StoreTree, key: &[u8]) -> Result<Option<Vec<u8>>, TransactionError> {
+        self.check_tree(tree)?;
+        get(self.tx, tree, key).map_err(tx_error)
+    }
+
+    fn insert(&self, tree: StoreTree, key: &[u8], value: &[u8]) -> Result<(), TransactionError> {
+        self.check_tree(tree)?;
+        insert(self.tx, tree, key, value).map_err(tx_error)
+    }
+
+    fn remove(&self, tree: StoreTree, key: &[u8]) -> Result<Option<Vec<u8>>, TransactionError> {
+        self.check_tree(tree)?;
+        remove(self.tx, tree, key).map_err(tx_error)
+    }
+}
@@ -0,0 +1,6 @@
+//! The internals of the eq-service, shared by the `eq-service` and `eq-admin` binaries.
+
+pub mod internal;
+pub use internal::inclusion::*;
+pub use internal::job::*;
+pub use internal::util::*;
@@ -1,66 +1,193 @@
 #![doc = include_str!("../../README.md")]
 
-mod internal;
+use eq_common::eqs::admin_server::AdminServer;
 use eq_common::eqs::inclusion_server::InclusionServer;
-use internal::grpc::InclusionServiceArc;
-use internal::inclusion::*;
-use internal::job::*;
-use internal::prom_metrics::PromMetrics;
-use internal::util::*;
+use eq_service::internal::admin::AdminServiceArc;
+use eq_service::internal::config::ServiceConfig;
+use eq_service::internal::grpc::InclusionServiceArc;
+use eq_service::internal::inclusion::connect_da_backend;
+use eq_service::internal::programs::{ProgramKind, ProgramRegistry};
+use eq_service::internal::prom_metrics::PromMetrics;
+use eq_service::internal::prover::build_zk_backend;
+use eq_service::internal::queue::{JobQueue, JobReceivers, JobStage};
+use eq_service::internal::schema;
+use eq_service::internal::store::{open_store, StoreTree};
+use eq_service::*;
 
+use clap::{Parser, Subcommand};
 use log::{debug, error, info};
+use serde_json::json;
+use sp1_sdk::HashableKey;
+use std::error::Error;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::{mpsc, OnceCell};
+use tokio::sync::OnceCell;
 use tonic::transport::Server;
+use tonic_reflection::server::{ServerReflection, ServerReflectionServer};
+
+/// Settings are layered: defaults, then the config file, then env vars, then `--set`
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// TOML config file, see `example.toml`
+    #[arg(long, env = "EQ_CONFIG", global = true)]
+    config: Option<PathBuf>,
+    /// Override a setting of the config file, e.g. `--set zk.concurrency=4`
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    overrides: Vec<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Default)]
+enum Command {
+    /// Run the service, the default
+    #[default]
+    Serve,
+    /// Validate the config, and check the DA node and prover can be reached
+    CheckConfig,
+    /// Compute the proof setup of every current program into the DB, then exit.
+    /// Opens the DB, so the service must be stopped
+    WarmSetup,
+    /// Print the program ID and verifying key of every current program, as JSON lines,
+    /// to register them in verifier contracts. Opens the DB, so the service must be stopped
+    ExportVk,
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<(), Box<dyn Error>> {
     env_logger::init();
+    let args = Args::parse();
+
+    let config = match ServiceConfig::load(args.config.as_deref(), &args.overrides) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    match args.command.unwrap_or_default() {
+        Command::Serve => serve(config).await,
+        Command::CheckConfig => check_config(config).await,
+        Command::WarmSetup => warm_setup(config).await,
+        Command::ExportVk => export_vk(config).await,
+    }
+}
+
+/// Open the DB and build the service, without starting any of it's tasks
+fn build_service(
+    config: &ServiceConfig,
+) -> Result<(Arc<InclusionService>, JobReceivers), Box<dyn Error>> {
+    let db_path = config.db.path.as_deref().expect("Checked by validate");
+    let db_backend = config.db.backend;
+    info!("Opening {db_backend:?} DB at {}", db_path.display());
+    let store = open_store(db_backend, db_path)?;
+    schema::migrate(store.as_ref())?;
 
-    std::env::var("NETWORK_PRIVATE_KEY")
-        .expect("NETWORK_PRIVATE_KEY for Succinct Prover env var required");
-    let da_node_token = std::env::var("CELESTIA_NODE_AUTH_TOKEN")
-        .expect("CELESTIA_NODE_AUTH_TOKEN env var required");
-    let zk_proof_gen_timeout = Duration::from_secs(
-        std::env::var("PROOF_GEN_TIMEOUT_SECONDS")
-            .expect("PROOF_GEN_TIMEOUT_SECONDS env var required")
-            .parse()
-            .expect("PROOF_GEN_TIMEOUT_SECONDS must be integer"),
-    );
-    let da_node_http =
-        std::env::var("CELESTIA_NODE_HTTP").expect("CELESTIA_NODE_HTTP env var required");
-    let db_path = std::env::var("EQ_DB_PATH").expect("EQ_DB_PATH env var required");
-    let service_socket: std::net::SocketAddr = std::env::var("EQ_SOCKET")
-        .expect("EQ_SOCKET env var required")
-        .parse()
-        .expect("EQ_SOCKET parse");
-    let service_prometheus_socket: std::net::SocketAddr = std::env::var("EQ_PROMETHEUS_SOCKET")
-        .expect("EQ_PROMETHEUS_SOCKET env var required")
-        .parse()
-        .expect("EQ_PROMETHEUS_SOCKET parse");
-
-    let db = sled::open(db_path.clone())?;
-    let queue_db = db.open_tree("queue")?;
-    let finished_db = db.open_tree("finished")?;
-    let config_db = db.open_tree("config")?;
+    info!("Loading ZK programs");
+    let programs = ProgramRegistry::load(store.as_ref(), config.zk.program_dir.as_deref())?;
 
     info!("Building clients and service setup");
-    let (job_sender, job_receiver) = mpsc::unbounded_channel::<Option<Job>>();
+    let (job_queue, job_receivers) = JobQueue::new(config.jobs.queue_capacity);
     let inclusion_service = Arc::new(InclusionService::new(
-        InclusionServiceConfig {
-            da_node_token,
-            da_node_http,
-            zk_proof_gen_timeout,
-        },
+        config.inclusion_config()?,
         OnceCell::new(),
         OnceCell::new(),
-        Arc::new(PromMetrics::new()),
-        config_db.clone(),
-        queue_db.clone(),
-        finished_db.clone(),
-        job_sender.clone(),
+        Arc::new(PromMetrics::new(config.zk_proof_gen_timeout())),
+        store,
+        job_queue,
+        programs,
     ));
+    Ok((inclusion_service, job_receivers))
+}
+
+/// gRPC server reflection of `eqservice.proto` and the health service
+fn reflection_service(
+) -> Result<ServerReflectionServer<impl ServerReflection>, tonic_reflection::server::Error> {
+    tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(eq_common::eqs::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build()
+}
+
+async fn check_config(config: ServiceConfig) -> Result<(), Box<dyn Error>> {
+    let inclusion_config = config.inclusion_config()?;
+    let tls = config.grpc_tls_config()?;
+    // Built apart from gRPC's TLS, so checked on it's own
+    if config.listen.http.is_some() {
+        config.rest_tls_config()?;
+    }
+    println!("Config is valid");
+    if let Some(tls) = tls {
+        Server::builder()
+            .tls_config(tls)
+            .map_err(|e| format!("Invalid TLS certificate or key: {e}"))?;
+        println!("TLS certificate and key are valid");
+    }
+
+    let da_client = connect_da_backend(&inclusion_config).await?;
+    let head_height = da_client
+        .head_height()
+        .await
+        .map_err(|e| format!("DA node unreachable: {e}"))?;
+    println!("DA node reachable, it's head is at height {head_height}");
+
+    let zk_client = build_zk_backend(inclusion_config.zk_proof_mode);
+    zk_client
+        .check()
+        .await
+        .map_err(|e| format!("{} prover unreachable: {e:?}", zk_client.name()))?;
+    println!("{} prover reachable", zk_client.name());
+    Ok(())
+}
+
+async fn warm_setup(config: ServiceConfig) -> Result<(), Box<dyn Error>> {
+    let (service, _) = build_service(&config)?;
+    let zk_client = service.get_zk_client().await;
+    for kind in ProgramKind::ALL {
+        info!("Getting {kind:?} proof setup");
+        service
+            .get_proof_setup(&service.programs.current(kind), zk_client.clone())
+            .await?;
+    }
+    service.store.flush()?;
+    println!("Proof setups of all current programs are in the DB");
+    Ok(())
+}
+
+async fn export_vk(config: ServiceConfig) -> Result<(), Box<dyn Error>> {
+    let (service, _) = build_service(&config)?;
+    let zk_client = service.get_zk_client().await;
+    for kind in ProgramKind::ALL {
+        let program = service.programs.current(kind);
+        let setup = service.get_proof_setup(&program, zk_client.clone()).await?;
+        println!(
+            "{}",
+            json!({
+                "program": format!("{kind:?}"),
+                "program_id": format!("0x{}", hex::encode(program.id)),
+                "vkey": setup.vk.bytes32(),
+            })
+        );
+    }
+    // Setups computed here are kept for the service
+    service.store.flush()?;
+    Ok(())
+}
+
+async fn serve(config: ServiceConfig) -> Result<(), Box<dyn Error>> {
+    let service_socket = config.listen.grpc.expect("Checked by validate");
+    let admin_socket = config.listen.admin;
+    let http_socket = config.listen.http;
+    let service_prometheus_socket = config.listen.prometheus.expect("Checked by validate");
+    let tls = config.grpc_tls_config()?;
+    let http_tls = match http_socket {
+        Some(_) => config.rest_tls_config()?,
+        None => None,
+    };
+    let (inclusion_service, job_receivers) = build_service(&config)?;
+    let store = inclusion_service.store.clone();
+    let job_queue = inclusion_service.job_queue.clone();
 
     debug!("Starting Prometheus service");
     tokio::spawn({
@@ -78,11 +205,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tokio::spawn({
         let service = inclusion_service.clone();
         async move {
-            let program_id = get_program_id().await;
-            info!("zkstack-inclusion program id: {}", hex::encode(&program_id));
-            let zk_client = service.clone().get_zk_client_remote().await;
+            let zk_client = service.clone().get_zk_client().await;
             debug!("ZK client prepared, acquiring setup");
-            let _ = service.get_proof_setup(&program_id, zk_client).await;
+            let _ = service
+                .get_proof_setup(
+                    &service.programs.current(ProgramKind::ZkStackInclusion),
+                    zk_client,
+                )
+                .await;
             info!("ZK client ready!");
         }
         // TODO: crash whole program if this fails
@@ -98,9 +228,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     });
 
     debug!("Starting service");
+    // Returns once shut down and drained
+    let job_worker = tokio::spawn({
+        let service = inclusion_service.clone();
+        async move { service.job_worker(job_receivers).await }
+    });
+
+    debug!("Starting retry scheduler");
+    tokio::spawn({
+        let service = inclusion_service.clone();
+        async move { service.retry_scheduler().await }
+    });
+
+    debug!("Starting retention sweeper");
+    tokio::spawn({
+        let service = inclusion_service.clone();
+        async move { service.retention_sweeper().await }
+    });
+
+    debug!("Starting aggregation worker");
     tokio::spawn({
         let service = inclusion_service.clone();
-        async move { service.job_worker(job_receiver).await }
+        async move { service.aggregation_worker().await }
     });
 
     debug!("Connecting to DA client");
@@ -117,33 +266,82 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         // TODO: crash whole program if this fails
     });
 
+    debug!("Starting health reporter");
+    let (health_reporter, health_service) = tonic_health::server::health_reporter();
+    tokio::spawn({
+        let service = inclusion_service.clone();
+        async move { service.health_reporter(health_reporter).await }
+    });
+
     debug!("Restarting unfinished jobs");
-    for (job_key, queue_data) in queue_db.iter().flatten() {
-        let job: Job = bincode::deserialize(&job_key).unwrap();
-        debug!("Sending {job:?}");
-        if let Ok(job_status) = bincode::deserialize::<JobStatus>(&queue_data) {
-            match job_status {
-                JobStatus::DataAvailabilityPending
-                | JobStatus::DataAvailable(_)
-                | JobStatus::ZkProofPending(_) => {
-                    let _ = job_sender
-                        .send(Some(job))
-                        .map_err(|e| error!("Failed to send existing job to worker: {}", e));
-                }
-                _ => {
-                    error!("Unexpected job in queue! DB is in invalid state!")
+    // May wait on room in the job queue, so we don't block starting the gRPC service
+    let unfinished_jobs: Vec<_> = store.iter(StoreTree::Queue).flatten().collect();
+    tokio::spawn(async move {
+        for (job_key, queue_data) in unfinished_jobs {
+            let job = Job::from_key(&job_key).unwrap();
+            debug!("Sending {job:?}");
+            if let Ok(job_status) = schema::decode::<JobStatus>(&queue_data) {
+                match JobStage::for_status(&job_status) {
+                    Some(stage) => {
+                        let _ = job_queue
+                            .enqueue(job, stage)
+                            .await
+                            .map_err(|e| error!("Failed to send existing job to worker: {}", e));
+                    }
+                    None => {
+                        error!("Unexpected job in queue! DB is in invalid state!")
+                    }
                 }
             }
         }
+    });
+
+    if let Some(admin_socket) = admin_socket {
+        info!("Starting Admin gRPC Service on {admin_socket}");
+        let reflection = reflection_service()?;
+        tokio::spawn({
+            let service = inclusion_service.clone();
+            async move {
+                let _ = Server::builder()
+                    .add_service(AdminServer::new(AdminServiceArc(service)))
+                    .add_service(reflection)
+                    .serve(admin_socket)
+                    .await
+                    .map_err(|e| error!("Admin gRPC Service failed: {e}"));
+            }
+        });
+    }
+
+    if let Some(http_socket) = http_socket {
+        info!("Starting HTTP gateway on {http_socket}");
+        tokio::spawn({
+            let service = inclusion_service.clone();
+            async move {
+                let _ = service
+                    .serve_rest(http_socket, http_tls)
+                    .await
+                    .map_err(|e| error!("HTTP gateway failed: {e}"));
+            }
+        });
     }
 
     info!("Starting gRPC Service");
-    Server::builder()
+    let mut server = Server::builder();
+    if let Some(tls) = tls {
+        info!("Serving gRPC over TLS");
+        server = server.tls_config(tls)?;
+    }
+    server
         .add_service(InclusionServer::new(InclusionServiceArc(
             inclusion_service.clone(),
         )))
-        .serve(service_socket)
+        .add_service(health_service)
+        .add_service(reflection_service()?)
+        .serve_with_shutdown(service_socket, async {
+            let _ = job_worker.await;
+        })
         .await?;
 
+    info!("Shutdown complete");
     Ok(())
 }